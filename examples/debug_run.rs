@@ -20,14 +20,7 @@ fn main() -> anyhow::Result<()> {
     let ctx = pollster::block_on(GpuContext::new(0, GpuBackend::Auto))?;
 
     println!("Creating solver...");
-    let mut solver = KangarooSolver::new(
-        ctx,
-        pubkey.clone(),
-        start,
-        range_bits,
-        dp_bits,
-        num_kangaroos,
-    )?;
+    let mut solver = KangarooSolver::new(ctx, pubkey, start, range_bits, dp_bits, num_kangaroos)?;
 
     println!("Starting solve...");
     let mut i = 0;
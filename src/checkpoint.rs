@@ -0,0 +1,390 @@
+//! Checkpoint file format for resuming long-running searches
+//!
+//! A checkpoint captures everything needed to continue a run after a crash:
+//! the kangaroo herd (read back from the GPU), the DP table, the jump table
+//! parameters, the operation counter and the run parameters.
+//!
+//! Layout (all integers little-endian):
+//!
+//! ```text
+//! magic        8 bytes  "KANGCKPT"
+//! version      u32
+//! payload_len  u64
+//! payload      payload_len bytes
+//! checksum     32 bytes  SHA-256(payload)
+//! ```
+
+use crate::cpu::gaudry_schost::{self, SetOverlap};
+use crate::cpu::init::Herds;
+use crate::cpu::jumps::{JumpDistribution, JumpMean, JumpTableSpec};
+use crate::crypto::Point;
+use crate::gpu::{GpuDistinguishedPoint, GpuKangaroo};
+use crate::range::KeyRange;
+use anyhow::{anyhow, bail, Context, Result};
+use bitcoin::hashes::{sha256, Hash};
+use k256::elliptic_curve::sec1::{FromEncodedPoint, ToEncodedPoint};
+use k256::{AffinePoint, EncodedPoint, ProjectivePoint};
+use std::path::Path;
+
+const MAGIC: &[u8; 8] = b"KANGCKPT";
+/// Bump whenever the payload layout changes
+pub const CHECKPOINT_VERSION: u32 = 5;
+
+const FLAG_NEGATION: u32 = 1;
+const HERDS_SHIFT: u32 = 1;
+const HERDS_MASK: u32 = 0b11;
+
+/// Snapshot of a `KangarooSolver` run
+#[derive(Clone)]
+pub struct Checkpoint {
+    pub pubkey: Point,
+    pub range: KeyRange,
+    pub dp_bits: u32,
+    /// Regenerates the jump table on resume
+    pub jumps: JumpTableSpec,
+    /// SHA-256 over the jump distances, guards against resuming with a different table
+    pub jump_table_hash: [u8; 32],
+    pub total_ops: u64,
+    /// Herd walks with the negation map
+    pub negation: bool,
+    pub herds: Herds,
+    /// Tame and wild sets of a Gaudry–Schost run
+    pub gaudry_schost: Option<SetOverlap>,
+    /// Seed the herd was placed with, to replay the run with `--seed`
+    pub seed: u64,
+    pub kangaroos: Vec<GpuKangaroo>,
+    pub dps: Vec<GpuDistinguishedPoint>,
+}
+
+impl Checkpoint {
+    pub fn num_kangaroos(&self) -> u32 {
+        self.kangaroos.len() as u32
+    }
+
+    /// Serialize to the versioned, checksummed binary format
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut payload = Vec::new();
+
+        let encoded = self.pubkey.to_affine().to_encoded_point(true);
+        payload.extend_from_slice(encoded.as_bytes());
+        payload.extend_from_slice(&self.range.start());
+        payload.extend_from_slice(&self.range.end());
+        payload.extend_from_slice(&self.dp_bits.to_le_bytes());
+        write_jump_spec(&mut payload, &self.jumps);
+        payload.extend_from_slice(&self.jump_table_hash);
+        payload.extend_from_slice(&self.total_ops.to_le_bytes());
+        let flags = if self.negation { FLAG_NEGATION } else { 0 };
+        let flags =
+            flags | self.herds.code() << HERDS_SHIFT | gaudry_schost::flag_bits(self.gaudry_schost);
+        payload.extend_from_slice(&flags.to_le_bytes());
+        payload.extend_from_slice(&self.seed.to_le_bytes());
+
+        payload.extend_from_slice(&(self.kangaroos.len() as u64).to_le_bytes());
+        payload.extend_from_slice(bytemuck::cast_slice(&self.kangaroos));
+
+        payload.extend_from_slice(&(self.dps.len() as u64).to_le_bytes());
+        payload.extend_from_slice(bytemuck::cast_slice(&self.dps));
+
+        let checksum = sha256::Hash::hash(&payload);
+
+        let mut out = Vec::with_capacity(payload.len() + 52);
+        out.extend_from_slice(MAGIC);
+        out.extend_from_slice(&CHECKPOINT_VERSION.to_le_bytes());
+        out.extend_from_slice(&(payload.len() as u64).to_le_bytes());
+        out.extend_from_slice(&payload);
+        out.extend_from_slice(checksum.as_byte_array());
+        out
+    }
+
+    /// Parse and validate a checkpoint
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let mut header = Reader::new(bytes);
+        if header.take(8)? != MAGIC {
+            bail!("Not a kangaroo checkpoint (bad magic)");
+        }
+        let version = header.u32()?;
+        if version != CHECKPOINT_VERSION {
+            bail!(
+                "Unsupported checkpoint version {} (expected {})",
+                version,
+                CHECKPOINT_VERSION
+            );
+        }
+        let payload_len = header.u64()? as usize;
+        let payload = header.take(payload_len)?;
+        let checksum = header.take(32)?;
+        if sha256::Hash::hash(payload).as_byte_array() != checksum {
+            bail!("Checkpoint checksum mismatch (file is corrupted)");
+        }
+
+        let mut r = Reader::new(payload);
+
+        let encoded = EncodedPoint::from_bytes(r.take(33)?)
+            .map_err(|e| anyhow!("Invalid pubkey in checkpoint: {e}"))?;
+        let affine = Option::<AffinePoint>::from(AffinePoint::from_encoded_point(&encoded))
+            .ok_or_else(|| anyhow!("Checkpoint pubkey is not on curve"))?;
+
+        let mut start = [0u8; 32];
+        start.copy_from_slice(r.take(32)?);
+        let mut end = [0u8; 32];
+        end.copy_from_slice(r.take(32)?);
+        let range = KeyRange::new(start, end).context("Invalid range in checkpoint")?;
+        let dp_bits = r.u32()?;
+        let jumps = read_jump_spec(&mut r)?;
+        let mut jump_table_hash = [0u8; 32];
+        jump_table_hash.copy_from_slice(r.take(32)?);
+        let total_ops = r.u64()?;
+        let flags = r.u32()?;
+        let negation = flags & FLAG_NEGATION != 0;
+        let herds = Herds::from_code(flags >> HERDS_SHIFT & HERDS_MASK)?;
+        let gaudry_schost = gaudry_schost::from_flag_bits(flags)?;
+        let seed = r.u64()?;
+
+        let num_kangaroos = r.u64()? as usize;
+        let kangaroos = r.pod_vec::<GpuKangaroo>(num_kangaroos)?;
+
+        let num_dps = r.u64()? as usize;
+        let dps = r.pod_vec::<GpuDistinguishedPoint>(num_dps)?;
+
+        Ok(Self {
+            pubkey: ProjectivePoint::from(affine),
+            range,
+            dp_bits,
+            jumps,
+            jump_table_hash,
+            total_ops,
+            negation,
+            herds,
+            gaudry_schost,
+            seed,
+            kangaroos,
+            dps,
+        })
+    }
+
+    /// Write atomically (temp file + rename) so a crash never leaves a torn checkpoint
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let mut tmp = path.as_os_str().to_owned();
+        tmp.push(".tmp");
+        let tmp = std::path::PathBuf::from(tmp);
+
+        std::fs::write(&tmp, self.to_bytes())
+            .with_context(|| format!("Failed to write checkpoint {}", tmp.display()))?;
+        std::fs::rename(&tmp, path)
+            .with_context(|| format!("Failed to move checkpoint to {}", path.display()))?;
+        Ok(())
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let bytes = std::fs::read(path)
+            .with_context(|| format!("Failed to read checkpoint {}", path.display()))?;
+        Self::from_bytes(&bytes).with_context(|| format!("Invalid checkpoint {}", path.display()))
+    }
+}
+
+/// size u32, distribution u32, mean kind u32, mean value u64, seed u64
+pub(crate) fn write_jump_spec(out: &mut Vec<u8>, spec: &JumpTableSpec) {
+    let distribution: u32 = match spec.distribution {
+        JumpDistribution::PowersOfTwo => 0,
+        JumpDistribution::Random => 1,
+        JumpDistribution::Geometric => 2,
+    };
+    let (mean_kind, mean_value): (u32, u64) = match spec.mean {
+        JumpMean::Sqrt => (0, 0),
+        JumpMean::Herd(m) => (1, m),
+        JumpMean::Bits(bits) => (2, u64::from(bits)),
+    };
+    out.extend_from_slice(&spec.size.to_le_bytes());
+    out.extend_from_slice(&distribution.to_le_bytes());
+    out.extend_from_slice(&mean_kind.to_le_bytes());
+    out.extend_from_slice(&mean_value.to_le_bytes());
+    out.extend_from_slice(&spec.seed.to_le_bytes());
+}
+
+pub(crate) fn read_jump_spec(r: &mut Reader) -> Result<JumpTableSpec> {
+    let size = r.u32()?;
+    let distribution = match r.u32()? {
+        0 => JumpDistribution::PowersOfTwo,
+        1 => JumpDistribution::Random,
+        2 => JumpDistribution::Geometric,
+        other => bail!("Unknown jump distribution {}", other),
+    };
+    let (mean_kind, mean_value) = (r.u32()?, r.u64()?);
+    let mean = match mean_kind {
+        0 => JumpMean::Sqrt,
+        1 => JumpMean::Herd(mean_value),
+        2 => JumpMean::Bits(mean_value as u32),
+        other => bail!("Unknown jump mean {}", other),
+    };
+    Ok(JumpTableSpec {
+        size,
+        mean,
+        distribution,
+        seed: r.u64()?,
+    })
+}
+
+/// SHA-256 over the jump distance limbs
+pub fn jump_table_hash(jump_distances: &[[u32; 8]]) -> [u8; 32] {
+    sha256::Hash::hash(bytemuck::cast_slice(jump_distances)).to_byte_array()
+}
+
+/// Bounds-checked little-endian reader
+pub(crate) struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    pub(crate) fn new(data: &'a [u8]) -> Self {
+        Self { data, pos: 0 }
+    }
+
+    pub(crate) fn take(&mut self, n: usize) -> Result<&'a [u8]> {
+        let end = self
+            .pos
+            .checked_add(n)
+            .filter(|&end| end <= self.data.len())
+            .ok_or_else(|| anyhow!("File truncated"))?;
+        let slice = &self.data[self.pos..end];
+        self.pos = end;
+        Ok(slice)
+    }
+
+    pub(crate) fn u32(&mut self) -> Result<u32> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    pub(crate) fn u64(&mut self) -> Result<u64> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+
+    pub(crate) fn pod_vec<T: bytemuck::Pod>(&mut self, count: usize) -> Result<Vec<T>> {
+        let size = std::mem::size_of::<T>();
+        let bytes = self.take(
+            count
+                .checked_mul(size)
+                .ok_or_else(|| anyhow!("File truncated"))?,
+        )?;
+        Ok(bytes
+            .chunks_exact(size)
+            .map(bytemuck::pod_read_unaligned)
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use k256::elliptic_curve::ops::MulByGenerator;
+    use k256::Scalar;
+
+    fn sample() -> Checkpoint {
+        let kangaroo = GpuKangaroo {
+            x: [1, 2, 3, 4, 5, 6, 7, 8],
+            y: [9; 8],
+            dist: [0x1234, 0, 0, 0, 0, 0, 0, 0],
+            ktype: 1,
+            is_active: 1,
+            last_jump: 3,
+            steps_since_dp: 99,
+            cycle_steps: 128,
+            cycle_x: [5, 6],
+            target: 0,
+        };
+        let dp = GpuDistinguishedPoint {
+            x: [0xdead_beef; 8],
+            dist: [42, 0, 0, 0, 0, 0, 0, 0],
+            ktype: 0,
+            kangaroo_id: 7,
+            kind: 0,
+            target: 0,
+            _padding: [0; 4],
+        };
+        Checkpoint {
+            pubkey: ProjectivePoint::mul_by_generator(&Scalar::from(0x12345u64)),
+            range: KeyRange::from_bits([0x10; 32], 20),
+            dp_bits: 8,
+            jumps: JumpTableSpec {
+                size: 512,
+                mean: JumpMean::Herd(1 << 20),
+                distribution: JumpDistribution::Geometric,
+                seed: 9,
+            },
+            jump_table_hash: [0xab; 32],
+            total_ops: 123_456_789,
+            negation: true,
+            herds: Herds::Vow,
+            gaudry_schost: None,
+            seed: 0xfeed_f00d,
+            kangaroos: vec![kangaroo; 3],
+            dps: vec![dp; 2],
+        }
+    }
+
+    #[test]
+    fn test_checkpoint_roundtrip() {
+        let cp = sample();
+        let restored = Checkpoint::from_bytes(&cp.to_bytes()).unwrap();
+
+        assert_eq!(restored.pubkey, cp.pubkey);
+        assert_eq!(restored.range, cp.range);
+        assert_eq!(restored.dp_bits, 8);
+        assert_eq!(restored.jumps, cp.jumps);
+        assert_eq!(restored.jump_table_hash, cp.jump_table_hash);
+        assert_eq!(restored.total_ops, 123_456_789);
+        assert!(restored.negation);
+        assert_eq!(restored.seed, 0xfeed_f00d);
+        assert_eq!(restored.num_kangaroos(), 3);
+        assert_eq!(restored.kangaroos[1].last_jump, 3);
+        assert_eq!(restored.kangaroos[2].x, cp.kangaroos[2].x);
+        assert_eq!(restored.kangaroos[2].dist, cp.kangaroos[2].dist);
+        assert_eq!(restored.dps.len(), 2);
+        assert_eq!(restored.dps[1].kangaroo_id, 7);
+
+        let sets = SetOverlap {
+            tame_percent: 90,
+            wild_percent: 60,
+        };
+        let gs = Checkpoint {
+            negation: false,
+            gaudry_schost: Some(sets),
+            ..sample()
+        };
+        let restored = Checkpoint::from_bytes(&gs.to_bytes()).unwrap();
+        assert_eq!(restored.gaudry_schost, Some(sets));
+        assert!(!restored.negation);
+    }
+
+    #[test]
+    fn test_checkpoint_detects_corruption() {
+        let mut bytes = sample().to_bytes();
+        let mid = bytes.len() / 2;
+        bytes[mid] ^= 0x01;
+        let err = Checkpoint::from_bytes(&bytes).err().unwrap();
+        assert!(err.to_string().contains("checksum"));
+    }
+
+    #[test]
+    fn test_checkpoint_rejects_other_version() {
+        let mut bytes = sample().to_bytes();
+        bytes[8..12].copy_from_slice(&(CHECKPOINT_VERSION + 1).to_le_bytes());
+        let err = Checkpoint::from_bytes(&bytes).err().unwrap();
+        assert!(err.to_string().contains("version"));
+    }
+
+    #[test]
+    fn test_checkpoint_rejects_truncated() {
+        let bytes = sample().to_bytes();
+        assert!(Checkpoint::from_bytes(&bytes[..bytes.len() - 10]).is_err());
+    }
+
+    #[test]
+    fn test_checkpoint_save_load() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("run.kcp");
+        sample().save(&path).unwrap();
+        let restored = Checkpoint::load(&path).unwrap();
+        assert_eq!(restored.total_ops, 123_456_789);
+    }
+}
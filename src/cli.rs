@@ -1,6 +1,9 @@
-//! CLI utilities for progress bars and tracing
+//! CLI utilities for progress bars, search loops and tracing
 
-use indicatif::ProgressStyle;
+use indicatif::{ProgressBar, ProgressStyle};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::OnceLock;
+use tracing::warn;
 use tracing_subscriber::{fmt, EnvFilter};
 
 /// Initialize tracing with optional verbosity.
@@ -21,20 +24,135 @@ pub fn init_tracing(verbose: bool, quiet: bool) {
         .try_init();
 }
 
-/// Returns the standard progress bar style.
-pub fn default_progress_style() -> ProgressStyle {
-    ProgressStyle::default_bar()
-        .template("{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {pos}/{len} ({eta})")
-        .expect("Invalid progress bar template")
-        .progress_chars("#>-")
-}
-
 /// Returns the standard progress bar style with a message field.
+///
+/// The bar runs to the expected operation count; the ETA is part of the message
+/// (see `SearchStats`), since indicatif's own one ends at the bar's length.
 pub fn default_progress_style_with_msg() -> ProgressStyle {
     ProgressStyle::default_bar()
-        .template(
-            "{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {pos}/{len} ({eta}) {msg}",
-        )
+        .template("{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {pos}/{len} {msg}")
         .expect("Invalid progress bar template")
         .progress_chars("#>-")
 }
+
+/// Progress bar of `len` units in the standard style, hidden when `quiet`
+pub fn progress_bar(quiet: bool, len: u64) -> ProgressBar {
+    if quiet {
+        return ProgressBar::hidden();
+    }
+    let pb = ProgressBar::new(len);
+    pb.set_style(default_progress_style_with_msg());
+    pb
+}
+
+/// Why a search loop ended without a result
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Stop {
+    /// `--max-ops` reached; holds the limit
+    Limit(u64),
+    /// Ctrl-C, after this many operations
+    Interrupted(u64),
+}
+
+impl std::fmt::Display for Stop {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Stop::Limit(ops) => write!(f, "stopped after {} operations (--max-ops)", ops),
+            Stop::Interrupted(ops) => write!(f, "interrupted after {} operations", ops),
+        }
+    }
+}
+
+/// Ctrl-C flag of the running search. A process can install only one Ctrl-C
+/// handler, so it is installed on first use and every `SearchLoop` shares it.
+fn interrupted() -> &'static AtomicBool {
+    static INTERRUPTED: OnceLock<AtomicBool> = OnceLock::new();
+    INTERRUPTED.get_or_init(|| {
+        if let Err(e) = ctrlc::set_handler(|| interrupted().store(true, Ordering::SeqCst)) {
+            warn!("Could not install Ctrl-C handler: {}", e);
+        }
+        AtomicBool::new(false)
+    })
+}
+
+/// Progress bar, Ctrl-C flag and `--max-ops` limit of one search run
+pub struct SearchLoop {
+    pub pb: ProgressBar,
+    max_ops: u64,
+}
+
+impl SearchLoop {
+    /// Bar of `len` units, with the Ctrl-C flag cleared; `max_ops` 0 means no limit
+    pub fn new(quiet: bool, len: u64, max_ops: u64) -> Self {
+        interrupted().store(false, Ordering::SeqCst);
+        Self {
+            pb: progress_bar(quiet, len),
+            max_ops: if max_ops == 0 { u64::MAX } else { max_ops },
+        }
+    }
+
+    /// Why a search that has done `ops` operations must stop, if it must;
+    /// finishes the bar when it does
+    pub fn check(&self, ops: u64) -> Option<Stop> {
+        let stop = if ops >= self.max_ops {
+            Stop::Limit(self.max_ops)
+        } else if interrupted().load(Ordering::SeqCst) {
+            Stop::Interrupted(ops)
+        } else {
+            return None;
+        };
+        self.pb.finish_with_message(match stop {
+            Stop::Limit(_) => "LIMIT REACHED",
+            Stop::Interrupted(_) => "INTERRUPTED",
+        });
+        Some(stop)
+    }
+
+    /// Call `step` until it returns a result or [`check`](Self::check) stops the
+    /// search. `step` updates the bar and returns the operations done so far,
+    /// with the result once there is one; finishing the bar on a result is up
+    /// to the caller.
+    pub fn run<T>(
+        &self,
+        mut step: impl FnMut(&ProgressBar) -> anyhow::Result<(u64, Option<T>)>,
+    ) -> anyhow::Result<Result<T, Stop>> {
+        loop {
+            let (ops, result) = step(&self.pb)?;
+            if let Some(result) = result {
+                return Ok(Ok(result));
+            }
+            if let Some(stop) = self.check(ops) {
+                return Ok(Err(stop));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_search_loop_stops_at_max_ops() {
+        let search = SearchLoop::new(true, 100, 30);
+        let mut ops = 0;
+        let outcome = search
+            .run(|_| {
+                ops += 10;
+                Ok((ops, None::<()>))
+            })
+            .unwrap();
+        assert_eq!(outcome, Err(Stop::Limit(30)));
+        assert_eq!(ops, 30);
+
+        // A result ends the loop before the limit is checked
+        assert_eq!(search.run(|_| Ok((50, Some(7)))).unwrap(), Ok(7));
+
+        // Every loop of the process sees Ctrl-C, and a new one starts uninterrupted
+        let second = SearchLoop::new(true, 100, 0);
+        interrupted().store(true, Ordering::SeqCst);
+        assert_eq!(search.check(10), Some(Stop::Interrupted(10)));
+        assert_eq!(second.check(20), Some(Stop::Interrupted(20)));
+        assert_eq!(SearchLoop::new(true, 100, 0).check(20), None);
+    }
+}
@@ -3,14 +3,16 @@
 //! GPU uses little-endian [u32; 8] limbs, while k256 uses big-endian [u8; 32].
 
 use crate::gpu::GpuAffinePoint;
+use crate::math::negate_256_be;
+use k256::elliptic_curve::ops::Reduce;
 use k256::elliptic_curve::sec1::ToEncodedPoint;
-use k256::AffinePoint;
+use k256::U256 as K256U256;
+use k256::{AffinePoint, Scalar};
 
 /// Convert GPU limbs (little-endian u32) to big-endian bytes.
 ///
 /// GPU format: limbs[0] contains least significant 32 bits
 /// Output: bytes[0] contains most significant byte
-#[allow(dead_code)]
 pub fn limbs_to_be_bytes(limbs: &[u32; 8]) -> [u8; 32] {
     let mut bytes = [0u8; 32];
     for i in 0..8 {
@@ -47,11 +49,50 @@ pub fn limbs_to_le_bytes(arr: &[u32; 8]) -> [u8; 32] {
     bytes
 }
 
+/// Convert little-endian bytes to [u32; 8] limbs.
+pub fn le_bytes_to_limbs(bytes: &[u8]) -> [u32; 8] {
+    let mut limbs = [0u32; 8];
+    for (limb, chunk) in limbs.iter_mut().zip(bytes.chunks_exact(4)) {
+        *limb = u32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]);
+    }
+    limbs
+}
+
 /// Convert 32-byte big-endian scalar to [u32; 8] little-endian limbs.
 pub fn scalar_be_to_limbs(bytes: &[u8; 32]) -> [u32; 8] {
     be_bytes_to_limbs(bytes)
 }
 
+/// Convert a little-endian U256 to a scalar (reduced mod n).
+pub fn le_bytes_to_scalar(bytes: &[u8; 32]) -> Scalar {
+    <Scalar as Reduce<K256U256>>::reduce(K256U256::from_le_slice(bytes))
+}
+
+/// Convert a big-endian private key (any length up to 32 bytes) to a scalar.
+pub fn key_bytes_to_scalar(bytes: &[u8]) -> Scalar {
+    let mut padded = [0u8; 32];
+    let len = bytes.len().min(32);
+    padded[32 - len..].copy_from_slice(&bytes[bytes.len() - len..]);
+    <Scalar as Reduce<K256U256>>::reduce(K256U256::from_be_slice(&padded))
+}
+
+/// Convert a scalar to a big-endian private key without leading zero bytes.
+pub fn scalar_to_key_bytes(scalar: &Scalar) -> Vec<u8> {
+    let bytes = scalar.to_bytes();
+    let first_nonzero = bytes.iter().position(|&x| x != 0).unwrap_or(31);
+    bytes[first_nonzero..].to_vec()
+}
+
+/// Convert a signed (two's complement mod 2^256) GPU distance to a scalar.
+pub fn signed_limbs_to_scalar(limbs: &[u32; 8]) -> Scalar {
+    let be = limbs_to_be_bytes(limbs);
+    if be[0] & 0x80 != 0 {
+        -<Scalar as Reduce<K256U256>>::reduce(K256U256::from_be_slice(&negate_256_be(&be)))
+    } else {
+        <Scalar as Reduce<K256U256>>::reduce(K256U256::from_be_slice(&be))
+    }
+}
+
 /// Convert k256 `AffinePoint` to GPU format.
 pub fn affine_to_gpu(point: &AffinePoint) -> GpuAffinePoint {
     let encoded = point.to_encoded_point(false);
@@ -105,6 +146,13 @@ mod tests {
         assert_eq!(bytes[31], 0x04);
     }
 
+    #[test]
+    fn test_limbs_to_le_bytes_roundtrip() {
+        let original: [u32; 8] = [0xdeadbeef, 2, 3, 4, 5, 6, 7, 0x80000000];
+        let bytes = limbs_to_le_bytes(&original);
+        assert_eq!(le_bytes_to_limbs(&bytes), original);
+    }
+
     #[test]
     fn test_scalar_be_to_limbs() {
         let mut bytes = [0u8; 32];
@@ -116,6 +164,26 @@ mod tests {
         assert_eq!(limbs[0] & 0xFF, 0x42);
     }
 
+    #[test]
+    fn test_signed_limbs_to_scalar() {
+        assert_eq!(
+            signed_limbs_to_scalar(&[5, 0, 0, 0, 0, 0, 0, 0]),
+            Scalar::from(5u64)
+        );
+        assert_eq!(signed_limbs_to_scalar(&[u32::MAX; 8]), -Scalar::ONE);
+        let minus_five = [
+            u32::MAX - 4,
+            u32::MAX,
+            u32::MAX,
+            u32::MAX,
+            u32::MAX,
+            u32::MAX,
+            u32::MAX,
+            u32::MAX,
+        ];
+        assert_eq!(signed_limbs_to_scalar(&minus_five), -Scalar::from(5u64));
+    }
+
     #[test]
     fn test_u256_to_u128() {
         let mut val = [0u8; 32];
@@ -0,0 +1,278 @@
+//! Baby-step giant-step: a deterministic search of small ranges
+//!
+//! A table holds `x(j·G)` for the baby steps `j` in `1..=m`. Each giant step
+//! checks the `2m + 1` keys around a centre `c`: `Q - c·G` is `±j·G` exactly when
+//! the key offset is `c ± j`, and both signs share an x-coordinate. Centres move
+//! through the range in strides of `2m + 1`, so after `⌈N / (2m + 1)⌉` giant
+//! steps every key has been checked and finding nothing proves the key is not in
+//! the range.
+
+use crate::convert::{le_bytes_to_scalar, scalar_to_key_bytes, u256_to_u128};
+use crate::crypto::verify_key;
+use crate::range::KeyRange;
+use anyhow::{bail, Result};
+use k256::elliptic_curve::bigint::Encoding;
+use k256::elliptic_curve::group::Group;
+use k256::elliptic_curve::ops::MulByGenerator;
+use k256::elliptic_curve::point::AffineCoordinates;
+use k256::elliptic_curve::BatchNormalize;
+use k256::{AffinePoint, ProjectivePoint, Scalar};
+use rayon::prelude::*;
+
+/// Baby-step table budget unless the caller picks another one (MiB)
+pub const DEFAULT_MEMORY_MIB: u64 = 1024;
+/// Widest range searched with BSGS when no algorithm is asked for (bits)
+pub const AUTO_MAX_BITS: u32 = 48;
+
+/// Giant steps one search may take; wider ranges need a bigger table or a kangaroo search
+const MAX_GIANT_STEPS: u128 = 1 << 40;
+/// Table bytes per baby step
+const ENTRY_BYTES: u64 = std::mem::size_of::<(u64, u64)>() as u64;
+/// Consecutive steps computed from one scalar multiplication and normalized together
+const CHUNK: u64 = 1 << 14;
+
+/// Baby-step table sized for one range
+pub struct BsgsSolver {
+    range: KeyRange,
+    width: u128,
+    baby_steps: u64,
+    giant_steps: u64,
+    /// `(x fingerprint, j)` of `j·G` for `j` in `1..=baby_steps`, sorted
+    table: Vec<(u64, u64)>,
+}
+
+impl BsgsSolver {
+    /// Build the table for `range`, with as many baby steps as `memory_bytes`
+    /// holds (or fewer, when the range does not need them)
+    pub fn new(range: KeyRange, memory_bytes: u64) -> Result<Self> {
+        let width = range.width();
+        if width.bits() > 127 {
+            bail!("BSGS cannot search {}: too wide", range);
+        }
+        let width = u256_to_u128(&width.to_le_bytes());
+
+        let affordable = memory_bytes / ENTRY_BYTES;
+        if affordable == 0 {
+            bail!("A BSGS table needs at least {} bytes", ENTRY_BYTES);
+        }
+        // m baby steps and N / 2m giant steps cost the least together at m = √(N/2)
+        let wanted = ((width as f64 / 2.0).sqrt().ceil() as u64).max(1);
+        let baby_steps = wanted.min(affordable);
+        let giant_steps = width.div_ceil(2 * u128::from(baby_steps) + 1);
+        if giant_steps > MAX_GIANT_STEPS {
+            bail!(
+                "BSGS over {} with a {} MiB table takes {} giant steps; give it more memory or use a kangaroo search",
+                range,
+                memory_bytes >> 20,
+                giant_steps
+            );
+        }
+
+        let mut table = vec![(0u64, 0u64); baby_steps as usize];
+        table
+            .par_chunks_mut(CHUNK as usize)
+            .enumerate()
+            .for_each(|(chunk, entries)| {
+                let first = chunk as u64 * CHUNK + 1;
+                let mut point = ProjectivePoint::mul_by_generator(&Scalar::from(first));
+                let points: Vec<ProjectivePoint> = (0..entries.len())
+                    .map(|_| {
+                        let p = point;
+                        point += ProjectivePoint::GENERATOR;
+                        p
+                    })
+                    .collect();
+                let affine = normalize(&points);
+                for ((entry, p), j) in entries.iter_mut().zip(&affine).zip(first..) {
+                    *entry = (fingerprint(p), j);
+                }
+            });
+        table.par_sort_unstable();
+
+        Ok(Self {
+            range,
+            width,
+            baby_steps,
+            giant_steps: giant_steps as u64,
+            table,
+        })
+    }
+
+    pub fn baby_steps(&self) -> u64 {
+        self.baby_steps
+    }
+
+    /// Giant steps of a search that does not find the key
+    pub fn giant_steps(&self) -> u64 {
+        self.giant_steps
+    }
+
+    pub fn table_bytes(&self) -> u64 {
+        self.baby_steps * ENTRY_BYTES
+    }
+
+    /// Search the range for the key of `pubkey`; `None` means it is not there.
+    ///
+    /// `progress` is called from the worker threads with the number of giant
+    /// steps each of them has just finished.
+    pub fn solve(
+        &self,
+        pubkey: &ProjectivePoint,
+        progress: impl Fn(u64) + Sync,
+    ) -> Option<Vec<u8>> {
+        let start = le_bytes_to_scalar(&self.range.start());
+        let stride = Scalar::from(2 * self.baby_steps + 1);
+        let stride_point = ProjectivePoint::mul_by_generator(&stride).to_affine();
+        // Q - c·G for the first centre, c = start + m
+        let origin =
+            pubkey - &ProjectivePoint::mul_by_generator(&(start + Scalar::from(self.baby_steps)));
+
+        (0..self.giant_steps.div_ceil(CHUNK))
+            .into_par_iter()
+            .find_map_any(|chunk| {
+                let first = chunk * CHUNK;
+                let last = (first + CHUNK).min(self.giant_steps);
+                let mut point =
+                    origin - ProjectivePoint::mul_by_generator(&(stride * Scalar::from(first)));
+                let points: Vec<ProjectivePoint> = (first..last)
+                    .map(|_| {
+                        let p = point;
+                        point -= stride_point;
+                        p
+                    })
+                    .collect();
+                let affine = normalize(&points);
+                let key = affine
+                    .iter()
+                    .zip(first..)
+                    .find_map(|(p, giant)| self.check(p, giant, start, pubkey));
+                progress(last - first);
+                key
+            })
+    }
+
+    /// The key, if `point` = `Q - c·G` of giant step `giant` is in the table
+    fn check(
+        &self,
+        point: &AffinePoint,
+        giant: u64,
+        start: Scalar,
+        pubkey: &ProjectivePoint,
+    ) -> Option<Vec<u8>> {
+        let centre =
+            u128::from(giant) * (2 * u128::from(self.baby_steps) + 1) + u128::from(self.baby_steps);
+        let key = |offset: u128| {
+            (offset < self.width).then(|| scalar_to_key_bytes(&(start + Scalar::from(offset))))
+        };
+
+        if *point == AffinePoint::IDENTITY {
+            return key(centre);
+        }
+        let x = fingerprint(point);
+        let first = self.table.partition_point(|&(fp, _)| fp < x);
+        self.table[first..]
+            .iter()
+            .take_while(|&&(fp, _)| fp == x)
+            .flat_map(|&(_, j)| [centre - u128::from(j), centre + u128::from(j)])
+            .filter_map(key)
+            .find(|key| verify_key(key, pubkey))
+    }
+}
+
+/// `batch_normalize`, keeping the identity out of the batch inversion: k256
+/// only spots a zero z in normalized form, and a giant step landing on the key
+/// gives one that is not
+fn normalize(points: &[ProjectivePoint]) -> Vec<AffinePoint> {
+    let identity: Vec<bool> = points.iter().map(|p| p.is_identity().into()).collect();
+    let finite: Vec<ProjectivePoint> = points
+        .iter()
+        .zip(&identity)
+        .map(|(&p, &identity)| {
+            if identity {
+                ProjectivePoint::GENERATOR
+            } else {
+                p
+            }
+        })
+        .collect();
+    let mut affine = ProjectivePoint::batch_normalize(finite.as_slice());
+    for (p, &identity) in affine.iter_mut().zip(&identity) {
+        if identity {
+            *p = AffinePoint::IDENTITY;
+        }
+    }
+    affine
+}
+
+/// Low 64 bits of x
+fn fingerprint(point: &AffinePoint) -> u64 {
+    let x = point.x();
+    let mut low = [0u8; 8];
+    low.copy_from_slice(&x[24..32]);
+    u64::from_be_bytes(low)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::convert::key_bytes_to_scalar;
+    use k256::U256 as K256U256;
+    use std::sync::atomic::{AtomicU64, Ordering};
+
+    fn key(value: u64) -> [u8; 32] {
+        K256U256::from_u64(value).to_le_bytes()
+    }
+
+    fn pubkey(value: u64) -> ProjectivePoint {
+        ProjectivePoint::mul_by_generator(&Scalar::from(value))
+    }
+
+    #[test]
+    fn test_sizing() {
+        let range = KeyRange::new(key(1000), key(1000 + (1 << 20) - 1)).unwrap();
+        let bsgs = BsgsSolver::new(range, 1 << 30).unwrap();
+        assert_eq!(bsgs.baby_steps(), 725);
+        assert_eq!(bsgs.giant_steps(), 723);
+
+        // A small budget trades table entries for giant steps
+        let bsgs = BsgsSolver::new(range, 100 * ENTRY_BYTES).unwrap();
+        assert_eq!((bsgs.baby_steps(), bsgs.giant_steps()), (100, 5217));
+        assert_eq!(bsgs.table_bytes(), 1600);
+
+        assert!(BsgsSolver::new(range, ENTRY_BYTES - 1).is_err());
+        assert!(BsgsSolver::new(KeyRange::from_bits([0; 32], 100), 1 << 20).is_err());
+    }
+
+    #[test]
+    fn test_finds_every_position() {
+        let range = KeyRange::new(key(0x4_0000), key(0x4_ffff)).unwrap();
+        // One baby step: several chunks of giant steps, centres on the key
+        // (0x4_0001, 0x4_0004) and a last stride that overshoots the range
+        let bsgs = BsgsSolver::new(range, ENTRY_BYTES).unwrap();
+        assert_eq!(bsgs.giant_steps(), 21_846);
+        let steps = AtomicU64::new(0);
+        for k in [0x4_0000, 0x4_0003, 0x4_0004, 0x4_8765, 0x4_fffe, 0x4_ffff] {
+            let found = bsgs
+                .solve(&pubkey(k), |n| {
+                    steps.fetch_add(n, Ordering::Relaxed);
+                })
+                .unwrap();
+            assert_eq!(key_bytes_to_scalar(&found), Scalar::from(k));
+        }
+        assert!(steps.load(Ordering::Relaxed) > 0);
+    }
+
+    #[test]
+    fn test_proves_absence() {
+        let range = KeyRange::new(key(0x4_0000), key(0x4_ffff)).unwrap();
+        let bsgs = BsgsSolver::new(range, 1 << 20).unwrap();
+        let steps = AtomicU64::new(0);
+        for k in [0x3_ffff, 0x5_0000, 7] {
+            let found = bsgs.solve(&pubkey(k), |n| {
+                steps.fetch_add(n, Ordering::Relaxed);
+            });
+            assert_eq!(found, None);
+        }
+        assert_eq!(steps.load(Ordering::Relaxed), 3 * bsgs.giant_steps());
+    }
+}
@@ -2,7 +2,11 @@
 //!
 //! Used for performance comparison with GPU implementation.
 
-use k256::elliptic_curve::ops::{MulByGenerator, Reduce};
+use crate::convert::le_bytes_to_scalar;
+use crate::cpu::jumps::jump_index;
+use crate::crypto::mul_base;
+use crate::range::KeyRange;
+use k256::elliptic_curve::ops::Reduce;
 use k256::elliptic_curve::sec1::ToEncodedPoint;
 use k256::U256 as K256U256;
 use k256::{ProjectivePoint, Scalar};
@@ -12,8 +16,9 @@ use std::time::{Duration, Instant};
 /// Pure CPU Kangaroo solver using k256
 pub struct CpuKangarooSolver {
     pubkey: ProjectivePoint,
-    start: Scalar, // Use Scalar for full 256-bit arithmetic
-    range_bits: u32,
+    /// Solves `pubkey = k * base`
+    base: ProjectivePoint,
+    range: KeyRange,
     dp_mask: u128,                     // DP check only needs low bits
     tame_table: HashMap<u128, Scalar>, // x_low -> distance (Scalar)
     wild_table: HashMap<u128, Scalar>,
@@ -21,19 +26,35 @@ pub struct CpuKangarooSolver {
 }
 
 impl CpuKangarooSolver {
+    /// Search `[start, start + 2^range_bits)`; `start_bytes` is big-endian
     pub fn new(
         pubkey: ProjectivePoint,
         start_bytes: [u8; 32],
         range_bits: u32,
         dp_bits: u32,
     ) -> Self {
-        let start_uint = K256U256::from_be_slice(&start_bytes);
-        let start = Scalar::reduce(start_uint);
+        let mut start = start_bytes;
+        start.reverse();
+        Self::for_range(pubkey, KeyRange::from_bits(start, range_bits), dp_bits)
+    }
+
+    /// Search an exact interval
+    pub fn for_range(pubkey: ProjectivePoint, range: KeyRange, dp_bits: u32) -> Self {
+        Self::with_base(pubkey, ProjectivePoint::GENERATOR, range, dp_bits)
+    }
+
+    /// Search an exact interval for `k` with `pubkey = k * base`
+    pub fn with_base(
+        pubkey: ProjectivePoint,
+        base: ProjectivePoint,
+        range: KeyRange,
+        dp_bits: u32,
+    ) -> Self {
         let dp_mask = (1u128 << dp_bits) - 1;
         Self {
             pubkey,
-            start,
-            range_bits,
+            base,
+            range,
             dp_mask,
             tame_table: HashMap::new(),
             wild_table: HashMap::new(),
@@ -44,34 +65,13 @@ impl CpuKangarooSolver {
     pub fn solve(&mut self, timeout: Duration) -> Option<Vec<u8>> {
         let start_time = Instant::now();
 
-        // Calculate middle of range: 2^(range_bits - 1)
-        // Construct scalar for range_middle
-        let mut mid_bytes = [0u8; 32];
-        // Bit position: range_bits - 1.
-        // e.g. range_bits=135 -> bit 134.
-        // Byte index (from end): 134 // 8 = 16. remainder 6.
-        // mid_bytes[31 - 16] |= 1 << 6.
-        // Be careful with indices.
-        // Let's use U256 bit setting if possible, or manual.
-        // range_bits is usually small (<256).
-
-        // Actually, simpler: range_middle = 1 << (range_bits - 1).
-        // Convert this to Scalar.
-        let bit = self.range_bits - 1;
-        let byte_idx = 31 - (bit / 8) as usize;
-        let bit_idx = bit % 8;
-        mid_bytes[byte_idx] |= 1 << bit_idx;
-
-        let range_middle_uint = K256U256::from_be_slice(&mid_bytes);
-        let range_middle = Scalar::reduce(range_middle_uint);
-
-        // mid = start + range_middle
-        let mid = self.start + range_middle;
+        // Walks start from the middle of the range
+        let mid = le_bytes_to_scalar(&self.range.midpoint());
 
         // Initialize tame kangaroo at mid
         // tame_scalar = mid
         // tame_dist = 0 (relative to mid)
-        let mut tame_pos = ProjectivePoint::mul_by_generator(&mid);
+        let mut tame_pos = mul_base(&self.base, &mid);
         let mut tame_dist = Scalar::ZERO;
 
         // Initialize wild kangaroo at pubkey
@@ -83,32 +83,24 @@ impl CpuKangarooSolver {
             .map(|i| {
                 let mut h = 0x811c9dc5u32;
                 h = (h ^ (i as u32)).wrapping_mul(0x01000193);
-                let mean_exp = (self.range_bits / 2).saturating_sub(2).max(8);
-
-                // Generate u128 approximation for jump (usually jumps are small < 2^128)
-                // If range_bits is huge (e.g. 256), jumps should be ~2^128.
-                // CPU solver is mostly for smaller ranges, but let's support up to 128-bit jumps.
-                let base = 1u128 << (mean_exp - 1);
-                // Cap mask to avoid overflow if mean_exp is large
-                let mask = if mean_exp >= 128 {
-                    u128::MAX
+                let mean_exp = (self.range.bits() / 2).saturating_sub(2).max(8);
+
+                // base + (h mod 2^mean_exp), in 256 bits so wide ranges get wide jumps
+                let base = K256U256::ONE.shl_vartime(mean_exp as usize - 1);
+                let noise = if mean_exp >= 32 {
+                    h
                 } else {
-                    (1u128 << mean_exp) - 1
+                    h & ((1u32 << mean_exp) - 1)
                 };
-                let val = base + (h as u128 & mask);
-                let val = if i == 0 { val | 1 } else { val };
-
-                // Convert u128 to Scalar
-                let mut bytes = [0u8; 32];
-                bytes[16..].copy_from_slice(&val.to_be_bytes());
-                let uint = K256U256::from_be_slice(&bytes);
-                Scalar::reduce(uint)
+                let val = base.wrapping_add(&K256U256::from_u32(noise));
+                let val = if i == 0 { val | K256U256::ONE } else { val };
+                Scalar::reduce(val)
             })
             .collect();
 
         let jump_points: Vec<ProjectivePoint> = jump_distances
             .iter()
-            .map(ProjectivePoint::mul_by_generator)
+            .map(|d| mul_base(&self.base, d))
             .collect();
 
         loop {
@@ -117,8 +109,8 @@ impl CpuKangarooSolver {
             }
 
             // Tame step
-            let tame_x = get_x_low(&tame_pos);
-            let jump_idx = (tame_x & 15) as usize;
+            let (tame_top, tame_x) = get_x(&tame_pos);
+            let jump_idx = jump_index(tame_top, 16) as usize;
             tame_pos += jump_points[jump_idx];
             tame_dist += jump_distances[jump_idx];
             self.ops += 1;
@@ -142,8 +134,8 @@ impl CpuKangarooSolver {
             }
 
             // Wild step
-            let wild_x = get_x_low(&wild_pos);
-            let jump_idx = (wild_x & 15) as usize;
+            let (wild_top, wild_x) = get_x(&wild_pos);
+            let jump_idx = jump_index(wild_top, 16) as usize;
             wild_pos += jump_points[jump_idx];
             wild_dist += jump_distances[jump_idx];
             self.ops += 1;
@@ -168,14 +160,16 @@ impl CpuKangarooSolver {
     }
 }
 
-fn get_x_low(point: &ProjectivePoint) -> u128 {
+/// Top 32 bits of x (picks the jump) and low 128 bits (DP test and table key)
+fn get_x(point: &ProjectivePoint) -> (u32, u128) {
     let affine = point.to_affine();
     let encoded = affine.to_encoded_point(false);
     let x_bytes = encoded.x().unwrap();
-    // Get low 128 bits (last 16 bytes)
+    let mut top = [0u8; 4];
+    top.copy_from_slice(&x_bytes[..4]);
     let mut low = [0u8; 16];
     low.copy_from_slice(&x_bytes[16..32]);
-    u128::from_be_bytes(low)
+    (u32::from_be_bytes(top), u128::from_be_bytes(low))
 }
 
 #[cfg(test)]
@@ -203,4 +197,18 @@ mod tests {
         let trimmed = hex_key.trim_start_matches('0');
         assert_eq!(trimmed, "12345");
     }
+
+    #[test]
+    fn test_cpu_solver_on_another_base() {
+        // P = 0x12345 * Q with Q = 0x1234 * G
+        let base = ProjectivePoint::GENERATOR * Scalar::from(0x1234u64);
+        let pubkey = base * Scalar::from(0x1_2345u64);
+        let mut start = [0u8; 32];
+        start[2] = 1;
+
+        let mut solver =
+            CpuKangarooSolver::with_base(pubkey, base, KeyRange::from_bits(start, 15), 4);
+        let key = solver.solve(Duration::from_secs(10)).unwrap();
+        assert!(crate::crypto::verify_key_with_base(&key, &pubkey, &base));
+    }
 }
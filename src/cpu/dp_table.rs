@@ -1,7 +1,37 @@
 //! Distinguished Point hash table for collision detection
 
-use crate::gpu::GpuDistinguishedPoint;
+use crate::convert::{
+    be_bytes_to_limbs, le_bytes_to_limbs, le_bytes_to_scalar, scalar_to_key_bytes,
+    signed_limbs_to_scalar,
+};
+use crate::cpu::init::Herds;
+use crate::crypto::{mul_base, Point};
+use crate::gpu::{GpuDistinguishedPoint, DP_KIND_POINT, KTYPE_MIRRORED_WILD, KTYPE_WILD};
+use crate::math::{add_256, subtract_256};
+use crate::range::KeyRange;
 use dashmap::DashMap;
+use k256::{ProjectivePoint, Scalar};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Result of [`DPTable::insert`]
+#[derive(Debug, PartialEq, Eq)]
+pub enum Insertion {
+    /// Nothing to act on
+    Stored,
+    /// Tame/wild collision: the private key of `target`
+    Key { target: u32, key: Vec<u8> },
+    /// Two kangaroos of the same herd met and will now walk in lockstep;
+    /// `respawn` is the `kangaroo_id` that should restart elsewhere
+    Merged { respawn: u32 },
+    /// Wild kangaroos of two targets met: `k_a` is `m * k_b + c` for one of the
+    /// `(m, c)` candidates. The newcomer `respawn` now duplicates the other's walk.
+    Relation {
+        a: u32,
+        b: u32,
+        candidates: Vec<(Scalar, Scalar)>,
+        respawn: u32,
+    },
+}
 
 /// Stored DP with full affine X for proper verification
 #[derive(Clone)]
@@ -9,25 +39,85 @@ struct StoredDP {
     affine_x: [u8; 32],
     dist: Vec<u8>,
     ktype: u32,
+    kangaroo_id: u32,
+    target: u32,
 }
 
 /// Thread-safe DP table for collision detection
 pub struct DPTable {
     table: DashMap<u64, Vec<StoredDP>>,
     start: [u8; 32], // search range start for key computation
+    /// Negation-map walk: `start` is the range midpoint and candidates are checked against `pubkeys`
+    symmetric: bool,
+    /// Three- and four-kangaroo search: distances relative to the midpoint `start`,
+    /// with wilds walking from the pubkey and from its negation
+    mirrored: bool,
+    /// Target pubkeys by index (only needed for the negation map and mirrored herds)
+    pubkeys: Vec<Point>,
+    /// Point the pubkeys are multiples of, for checking candidate keys
+    base: Point,
+    /// Stored tame and wild DPs, kept alongside the map so stats need no scan
+    tame: AtomicUsize,
+    wild: AtomicUsize,
 }
 
 impl DPTable {
     pub fn new(start: [u8; 32]) -> Self {
+        Self::with_targets(start, Vec::new(), false)
+    }
+
+    /// Table for the negation-map walk, whose distances are relative to `mid`
+    /// (see `HerdLayout::Symmetric`)
+    pub fn new_symmetric(mid: [u8; 32], pubkey: Point) -> Self {
+        Self::with_targets(mid, vec![pubkey], true)
+    }
+
+    /// Table for several pubkeys sharing one tame herd; wild DPs carry their target index
+    pub fn with_targets(start: [u8; 32], pubkeys: Vec<Point>, symmetric: bool) -> Self {
         Self {
             table: DashMap::new(),
             start,
+            symmetric,
+            mirrored: false,
+            pubkeys,
+            base: ProjectivePoint::GENERATOR,
+            tame: AtomicUsize::new(0),
+            wild: AtomicUsize::new(0),
         }
     }
 
+    /// Table matching the herd of `HerdLayout::for_search` with the same arguments
+    pub fn for_search(
+        pubkeys: Vec<Point>,
+        range: KeyRange,
+        negation: bool,
+        herds: Herds,
+        base: Point,
+    ) -> Self {
+        let table = if negation {
+            Self::with_targets(range.midpoint(), pubkeys, true)
+        } else if herds != Herds::Vow {
+            Self {
+                mirrored: true,
+                ..Self::with_targets(range.midpoint(), pubkeys, false)
+            }
+        } else {
+            Self::with_targets(range.start(), pubkeys, false)
+        };
+        Self { base, ..table }
+    }
+
     /// Insert DP and check for collision
     /// Returns private key if collision found between tame and wild
     pub fn insert_and_check(&self, dp: GpuDistinguishedPoint) -> Option<Vec<u8>> {
+        match self.insert(dp) {
+            Insertion::Key { key, .. } => Some(key),
+            _ => None,
+        }
+    }
+
+    /// Insert DP and report what it collided with, including same-type merges
+    pub fn insert(&self, dp: GpuDistinguishedPoint) -> Insertion {
         let dist_bytes = u32_array_to_bytes(&dp.dist);
 
         // X is already in affine coordinates (no Z conversion needed)
@@ -61,58 +151,122 @@ impl DPTable {
             affine_x[7],
         ]);
 
-        // Check for existing DPs with same hash
-        if let Some(mut existing_list) = self.table.get_mut(&hash_key) {
-            for existing in existing_list.iter() {
-                // Verify full affine X match (not just hash)
-                if existing.affine_x != affine_x {
-                    continue;
+        // Lookup and insert under one shard lock, so concurrent inserts of the same
+        // new x cannot replace each other's list
+        let mut existing_list = self.table.entry(hash_key).or_default();
+        for existing in existing_list.iter() {
+            // Verify full affine X match (not just hash)
+            if existing.affine_x != affine_x {
+                continue;
+            }
+            if self.mirrored {
+                let key = self.pubkeys.first().and_then(|pubkey| {
+                    compute_mirrored_private_key(
+                        &self.start,
+                        (existing.ktype, &existing.dist),
+                        (dp.ktype, &dist_bytes),
+                        pubkey,
+                        &self.base,
+                    )
+                });
+                if let Some(key) = key {
+                    tracing::info!("Collision found! Key: 0x{}", hex::encode(&key));
+                    return Insertion::Key { target: 0, key };
                 }
+                if existing.ktype != dp.ktype {
+                    // A wild and a mirrored wild at opposite points: no information
+                    return Insertion::Stored;
+                }
+            }
 
-                // Same affine X - check if tame vs wild collision
-                if existing.ktype == dp.ktype {
-                    // Same type collision - log for debugging
-                    let ktype_str = if dp.ktype == 0 {
-                        "tame-tame"
-                    } else {
-                        "wild-wild"
-                    };
-                    tracing::debug!(
-                        "Same-type collision ({}): affine_x={}",
-                        ktype_str,
-                        hex::encode(&affine_x[..8])
-                    );
-                    return None;
+            // Same affine X - check if tame vs wild collision
+            if existing.ktype == dp.ktype && (dp.ktype == 0 || existing.target == dp.target) {
+                // Same type collision - log for debugging
+                let ktype_str = if dp.ktype == 0 {
+                    "tame-tame"
+                } else {
+                    "wild-wild"
+                };
+                tracing::debug!(
+                    "Same-type collision ({}): affine_x={}",
+                    ktype_str,
+                    hex::encode(&affine_x[..8])
+                );
+                // Both now walk the same path; the newcomer is redundant unless the
+                // stored tame is the one further out
+                let respawn = if dp.ktype == 0 && magnitude(&existing.dist) > magnitude(&dist_bytes)
+                {
+                    existing.kangaroo_id
+                } else {
+                    dp.kangaroo_id
+                };
+                return Insertion::Merged { respawn };
+            }
+            if existing.ktype == dp.ktype {
+                tracing::debug!(
+                    "Wild-wild collision between targets {} and {}",
+                    dp.target,
+                    existing.target
+                );
+                return Insertion::Relation {
+                    a: dp.target,
+                    b: existing.target,
+                    candidates: self.wild_relation(&dist_bytes, &existing.dist),
+                    respawn: dp.kangaroo_id,
+                };
+            }
+            let target = if existing.ktype == 0 {
+                dp.target
+            } else {
+                existing.target
+            };
+            let key = if self.symmetric {
+                let (tame, wild) = if existing.ktype == 0 {
+                    (existing.dist.as_slice(), &dist_bytes[..])
+                } else {
+                    (&dist_bytes[..], existing.dist.as_slice())
+                };
+                let key = self.pubkeys.get(target as usize).and_then(|pubkey| {
+                    compute_symmetric_private_key(&self.start, tame, wild, pubkey, &self.base)
+                });
+                match key {
+                    Some(key) => key,
+                    None => {
+                        tracing::warn!(
+                            "Tame/wild collision at affine_x={} yields no valid key",
+                            hex::encode(&affine_x[..8])
+                        );
+                        return Insertion::Stored;
+                    }
                 }
-                let key = compute_private_key(
+            } else {
+                compute_private_key(
                     &self.start,
                     &existing.dist,
                     &dist_bytes,
                     existing.ktype,
                     dp.ktype,
-                );
-                tracing::info!("Collision found! Key: 0x{}", hex::encode(&key));
-                return Some(key);
-            }
-            // No collision, add to list
-            existing_list.push(StoredDP {
-                affine_x,
-                dist: dist_bytes.to_vec(),
-                ktype: dp.ktype,
-            });
-        } else {
-            // New hash key
-            self.table.insert(
-                hash_key,
-                vec![StoredDP {
-                    affine_x,
-                    dist: dist_bytes.to_vec(),
-                    ktype: dp.ktype,
-                }],
-            );
+                )
+            };
+            tracing::info!("Collision found! Key: 0x{}", hex::encode(&key));
+            return Insertion::Key { target, key };
         }
+        // No collision, add to list
+        existing_list.push(StoredDP {
+            affine_x,
+            dist: dist_bytes.to_vec(),
+            ktype: dp.ktype,
+            kangaroo_id: dp.kangaroo_id,
+            target: dp.target,
+        });
+        self.count(dp.ktype);
 
-        None
+        Insertion::Stored
+    }
+
+    fn count(&self, ktype: u32) {
+        let counter = if ktype == 0 { &self.tame } else { &self.wild };
+        counter.fetch_add(1, Ordering::Relaxed);
     }
 
     #[allow(dead_code)]
@@ -126,22 +280,63 @@ impl DPTable {
     }
 
     pub fn total_dps(&self) -> usize {
-        self.table.iter().map(|entry| entry.value().len()).sum()
+        let (tame, wild) = self.count_by_type();
+        tame + wild
     }
 
-    pub fn count_by_type(&self) -> (usize, usize) {
-        let mut tame = 0;
-        let mut wild = 0;
+    /// Snapshot every stored DP in GPU layout (for checkpoints)
+    pub fn export(&self) -> Vec<GpuDistinguishedPoint> {
+        let mut dps = Vec::with_capacity(self.total_dps());
         for entry in &self.table {
-            for dp in entry.value() {
-                if dp.ktype == 0 {
-                    tame += 1;
-                } else {
-                    wild += 1;
+            for stored in entry.value() {
+                dps.push(GpuDistinguishedPoint {
+                    x: be_bytes_to_limbs(&stored.affine_x),
+                    dist: le_bytes_to_limbs(&stored.dist),
+                    ktype: stored.ktype,
+                    kangaroo_id: stored.kangaroo_id,
+                    kind: DP_KIND_POINT,
+                    target: stored.target,
+                    _padding: [0; 4],
+                });
+            }
+        }
+        dps
+    }
+
+    /// `(m, c)` with `k_a = m * k_b + c` for wild `a` at `d_a` meeting wild `b` at `d_b`
+    fn wild_relation(&self, d_a: &[u8], d_b: &[u8]) -> Vec<(Scalar, Scalar)> {
+        let d_a = signed_limbs_to_scalar(&le_bytes_to_limbs(d_a));
+        let d_b = signed_limbs_to_scalar(&le_bytes_to_limbs(d_b));
+        if !self.symmetric {
+            // k_a + d_a = k_b + d_b
+            return vec![(Scalar::ONE, d_b - d_a)];
+        }
+
+        // Each stored point is s_x * (k_x - mid) + d_x up to an overall sign e, so
+        // k_a - mid = s_a * (e * s_b * (k_b - mid) + e * d_b - d_a)
+        let mid = le_bytes_to_scalar(&self.start);
+        let signs = [Scalar::ONE, -Scalar::ONE];
+        let mut candidates = Vec::with_capacity(8);
+        for s_a in signs {
+            for s_b in signs {
+                for e in signs {
+                    let m = s_a * e * s_b;
+                    let c = (Scalar::ONE - m) * mid + s_a * (e * d_b - d_a);
+                    if !candidates.contains(&(m, c)) {
+                        candidates.push((m, c));
+                    }
                 }
             }
         }
-        (tame, wild)
+        candidates
+    }
+
+    /// Stored `(tame, wild)` DPs
+    pub fn count_by_type(&self) -> (usize, usize) {
+        (
+            self.tame.load(Ordering::Relaxed),
+            self.wild.load(Ordering::Relaxed),
+        )
     }
 }
 
@@ -194,25 +389,357 @@ fn compute_private_key(
     result[first_nonzero..].to_vec()
 }
 
-fn add_256(a: &[u8], b: &[u8], result: &mut [u8]) {
-    let mut carry = 0u16;
-    for i in 0..32 {
-        let sum = u16::from(a[i]) + u16::from(b[i]) + carry;
-        result[i] = sum as u8;
-        carry = sum >> 8;
+/// Resolve a negation-map collision.
+///
+/// The tame sits at `±d_t * G` and the wild at `±(k' + d_w) * G` with
+/// `k = mid + k'`. Equal x only tells us the points agree up to sign, so
+/// `k' = ±d_t - d_w` or `k' = -(±d_t - d_w)`; each candidate is checked
+/// against the pubkey.
+fn compute_symmetric_private_key(
+    mid: &[u8; 32],
+    tame_dist: &[u8],
+    wild_dist: &[u8],
+    pubkey: &Point,
+    base: &Point,
+) -> Option<Vec<u8>> {
+    let mid = le_bytes_to_scalar(mid);
+    let d_t = signed_limbs_to_scalar(&le_bytes_to_limbs(tame_dist));
+    let d_w = signed_limbs_to_scalar(&le_bytes_to_limbs(wild_dist));
+
+    [d_t - d_w, d_w - d_t, -d_t - d_w, d_t + d_w]
+        .into_iter()
+        .map(|offset| mid + offset)
+        .find(|k| mul_base(base, k) == *pubkey)
+        .map(|k| scalar_to_key_bytes(&k))
+}
+
+/// Resolve an x-match in a three- or four-kangaroo search.
+///
+/// Around `mid` every kangaroo sits at `(s * k' + d) * G` with `k = mid + k'`
+/// and `s` = 0, 1 or -1 for tames, wilds and mirrored wilds. Equal x means
+/// `s_a * k' + d_a = ±(s_b * k' + d_b)`; every sign that pins down `k'` gives
+/// a candidate, which is checked against the pubkey. Besides tame/wild pairs
+/// this solves wild/mirrored meetings (`k' = (d_m - d_w) / 2`) and two wilds of
+/// one kind at opposite points.
+fn compute_mirrored_private_key(
+    mid: &[u8; 32],
+    a: (u32, &[u8]),
+    b: (u32, &[u8]),
+    pubkey: &Point,
+    base: &Point,
+) -> Option<Vec<u8>> {
+    let slope = |ktype: u32| match ktype {
+        KTYPE_WILD => Scalar::ONE,
+        KTYPE_MIRRORED_WILD => -Scalar::ONE,
+        _ => Scalar::ZERO,
+    };
+    let (s_a, d_a) = (slope(a.0), signed_limbs_to_scalar(&le_bytes_to_limbs(a.1)));
+    let (s_b, d_b) = (slope(b.0), signed_limbs_to_scalar(&le_bytes_to_limbs(b.1)));
+    let mid = le_bytes_to_scalar(mid);
+
+    [Scalar::ONE, -Scalar::ONE]
+        .into_iter()
+        .filter_map(|e| {
+            let inverse = Option::<Scalar>::from((s_a - e * s_b).invert())?;
+            Some(mid + (e * d_b - d_a) * inverse)
+        })
+        .find(|k| mul_base(base, k) == *pubkey)
+        .map(|k| scalar_to_key_bytes(&k))
+}
+
+/// |d| of a two's complement little-endian distance, big-endian for comparison
+fn magnitude(dist: &[u8]) -> [u8; 32] {
+    let mut m = [0u8; 32];
+    if dist[31] & 0x80 != 0 {
+        subtract_256(&[0u8; 32], dist, &mut m);
+    } else {
+        m.copy_from_slice(dist);
     }
+    m.reverse();
+    m
 }
 
-fn subtract_256(a: &[u8], b: &[u8], result: &mut [u8]) {
-    let mut borrow = 0i16;
-    for i in 0..32 {
-        let diff = i16::from(a[i]) - i16::from(b[i]) - borrow;
-        if diff < 0 {
-            result[i] = (diff + 256) as u8;
-            borrow = 1;
-        } else {
-            result[i] = diff as u8;
-            borrow = 0;
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use k256::elliptic_curve::ops::MulByGenerator;
+
+    fn dp(x0: u32, dist0: u32, ktype: u32, kangaroo_id: u32) -> GpuDistinguishedPoint {
+        GpuDistinguishedPoint {
+            x: [x0, 0, 0, 0, 0, 0, 0, 0x1234_5678],
+            dist: [dist0, 0, 0, 0, 0, 0, 0, 0],
+            ktype,
+            kangaroo_id,
+            kind: 0,
+            target: 0,
+            _padding: [0; 4],
+        }
+    }
+
+    #[test]
+    fn test_export_roundtrip() {
+        let table = DPTable::new([0u8; 32]);
+        assert!(table.insert_and_check(dp(0x100, 5, 0, 1)).is_none());
+        assert!(table.insert_and_check(dp(0x200, 9, 1, 2)).is_none());
+
+        let mut exported = table.export();
+        exported.sort_by_key(|d| d.x[0]);
+        assert_eq!(exported.len(), 2);
+        assert_eq!(exported[0].x, dp(0x100, 5, 0, 1).x);
+        assert_eq!(exported[0].dist[0], 5);
+        assert_eq!(exported[1].ktype, 1);
+        assert_eq!(exported[1].kangaroo_id, 2);
+
+        let restored = DPTable::new([0u8; 32]);
+        for d in exported {
+            assert!(restored.insert_and_check(d).is_none());
         }
+        assert_eq!(restored.count_by_type(), (1, 1));
+    }
+
+    #[test]
+    fn test_symmetric_collision_tries_all_signs() {
+        // mid = 1000, k = 1013 so k' = 13. Wild walked to -(k' + 4) = -17 and
+        // was negated, tame reached 17 and was stored as d = -17: same x.
+        let mut mid = [0u8; 32];
+        mid[..2].copy_from_slice(&1000u16.to_le_bytes());
+        let pubkey = ProjectivePoint::mul_by_generator(&Scalar::from(1013u64));
+        let neg = |v: u32| {
+            let mut limbs = [u32::MAX; 8];
+            limbs[0] = v.wrapping_neg();
+            limbs
+        };
+
+        let table = DPTable::new_symmetric(mid, pubkey);
+        let mut tame = dp(0x400, 0, 0, 0);
+        tame.dist = neg(17);
+        let mut wild = dp(0x400, 0, 1, 1);
+        wild.dist = neg(4);
+        assert!(table.insert_and_check(tame).is_none());
+        let key = table.insert_and_check(wild).unwrap();
+        assert_eq!(key, 1013u16.to_be_bytes().to_vec());
+    }
+
+    #[test]
+    fn test_mirrored_herd_collisions() {
+        // [0, 2000]: mid = 1000, k = 1013 so k' = 13. A wild at d sits at 13 + d,
+        // a mirrored wild at d - 13.
+        let key = |k: u64| ProjectivePoint::mul_by_generator(&Scalar::from(k));
+        let range = KeyRange::new([0u8; 32], {
+            let mut end = [0u8; 32];
+            end[..2].copy_from_slice(&2000u16.to_le_bytes());
+            end
+        })
+        .unwrap();
+        let table = || {
+            DPTable::for_search(
+                vec![key(1013)],
+                range,
+                false,
+                Herds::Three,
+                ProjectivePoint::GENERATOR,
+            )
+        };
+        let expected = Some(1013u16.to_be_bytes().to_vec());
+        let minus = |v: u32| {
+            let mut limbs = [u32::MAX; 8];
+            limbs[0] = v.wrapping_neg();
+            limbs
+        };
+
+        // Tame at 7 meets the mirrored wild at 20
+        let t = table();
+        assert!(t.insert_and_check(dp(0x500, 7, 0, 0)).is_none());
+        assert_eq!(t.insert_and_check(dp(0x500, 20, 2, 1)), expected);
+
+        // Wild at -3 (point 10) meets the mirrored wild at 23: k' = (23 + 3) / 2
+        let t = table();
+        assert!(t.insert_and_check(dp(0x600, 23, 2, 0)).is_none());
+        let mut wild = dp(0x600, 0, 1, 1);
+        wild.dist = minus(3);
+        assert_eq!(t.insert_and_check(wild), expected);
+
+        // Tame at -20 shares x with the wild at 7 (point 20): the opposite sign
+        let t = table();
+        let mut tame = dp(0x700, 0, 0, 0);
+        tame.dist = minus(20);
+        assert!(t.insert_and_check(tame).is_none());
+        assert_eq!(t.insert_and_check(dp(0x700, 7, 1, 1)), expected);
+
+        // A wild at 2 and a mirrored wild at -2 are opposite points: nothing learned
+        let t = table();
+        assert!(t.insert_and_check(dp(0x800, 2, 1, 0)).is_none());
+        let mut mirrored = dp(0x800, 0, 2, 1);
+        mirrored.dist = minus(2);
+        assert_eq!(t.insert(mirrored), Insertion::Stored);
+    }
+
+    #[test]
+    fn test_collisions_on_another_base() {
+        // The negation-map case above with P = 1013 * Q, Q = 5 * G
+        let base = ProjectivePoint::mul_by_generator(&Scalar::from(5u64));
+        let mut end = [0u8; 32];
+        end[..2].copy_from_slice(&2000u16.to_le_bytes());
+        let range = KeyRange::new([0u8; 32], end).unwrap();
+        let pubkey = base * Scalar::from(1013u64);
+        let neg = |v: u32| {
+            let mut limbs = [u32::MAX; 8];
+            limbs[0] = v.wrapping_neg();
+            limbs
+        };
+
+        let table = DPTable::for_search(vec![pubkey], range, true, Herds::Vow, base);
+        let mut tame = dp(0x400, 0, 0, 0);
+        tame.dist = neg(17);
+        let mut wild = dp(0x400, 0, 1, 1);
+        wild.dist = neg(4);
+        assert!(table.insert_and_check(tame).is_none());
+        let key = table.insert_and_check(wild).unwrap();
+        assert_eq!(key, 1013u16.to_be_bytes().to_vec());
+
+        // Against G the same candidates match nothing
+        let table = DPTable::for_search(
+            vec![pubkey],
+            range,
+            true,
+            Herds::Vow,
+            ProjectivePoint::GENERATOR,
+        );
+        assert!(table.insert_and_check(tame).is_none());
+        assert!(table.insert_and_check(wild).is_none());
+    }
+
+    #[test]
+    fn test_concurrent_inserts_of_a_new_x_keep_one_dp() {
+        // Every thread stores the same fresh points: exactly one insert per point may win
+        let table = DPTable::new([0u8; 32]);
+        let stored = AtomicUsize::new(0);
+        std::thread::scope(|s| {
+            for thread in 0..4 {
+                let (table, stored) = (&table, &stored);
+                s.spawn(move || {
+                    for i in 0..2000 {
+                        let mut point = dp(0x700, 10, 0, thread);
+                        point.x[7] = i;
+                        if table.insert(point) == Insertion::Stored {
+                            stored.fetch_add(1, Ordering::Relaxed);
+                        }
+                    }
+                });
+            }
+        });
+        assert_eq!(stored.into_inner(), 2000);
+        assert_eq!(table.count_by_type(), (2000, 0));
+        assert_eq!(table.len(), 2000);
+    }
+
+    #[test]
+    fn test_tame_wild_collision_yields_key() {
+        // start + tame_dist - wild_dist = 0 + 10 - 3 = 7
+        let table = DPTable::new([0u8; 32]);
+        assert!(table.insert_and_check(dp(0x300, 10, 0, 0)).is_none());
+        let key = table.insert_and_check(dp(0x300, 3, 1, 1)).unwrap();
+        assert_eq!(key, vec![7]);
+    }
+
+    #[test]
+    fn test_same_type_merge_respawns_one_kangaroo() {
+        let table = DPTable::new([0u8; 32]);
+        assert_eq!(table.insert(dp(0x500, 40, 1, 3)), Insertion::Stored);
+        // Wild-wild: the newcomer goes
+        assert_eq!(
+            table.insert(dp(0x500, 40, 1, 9)),
+            Insertion::Merged { respawn: 9 }
+        );
+
+        // Tame-tame: keep whichever is closer to the start
+        assert_eq!(table.insert(dp(0x600, 50, 0, 4)), Insertion::Stored);
+        assert_eq!(
+            table.insert(dp(0x600, 20, 0, 5)),
+            Insertion::Merged { respawn: 4 }
+        );
+        assert_eq!(
+            table.insert(dp(0x600, 80, 0, 6)),
+            Insertion::Merged { respawn: 6 }
+        );
+        assert_eq!(table.count_by_type(), (1, 1));
+    }
+
+    #[test]
+    fn test_tame_merge_keeps_smaller_signed_distance() {
+        // Negation-map distances are two's complement: -30 is further out than 20
+        let table = DPTable::new([0u8; 32]);
+        let mut minus_30 = dp(0x800, 0u32.wrapping_sub(30), 0, 1);
+        minus_30.dist[1..].fill(u32::MAX);
+        assert_eq!(table.insert(minus_30), Insertion::Stored);
+        assert_eq!(
+            table.insert(dp(0x800, 20, 0, 2)),
+            Insertion::Merged { respawn: 1 }
+        );
+        assert_eq!(
+            table.insert(dp(0x800, 40, 0, 3)),
+            Insertion::Merged { respawn: 3 }
+        );
+    }
+
+    #[test]
+    fn test_multi_target_collisions() {
+        let table = DPTable::with_targets([0u8; 32], Vec::new(), false);
+        let mut wild = dp(0x700, 3, 1, 8);
+        wild.target = 2;
+        assert_eq!(table.insert(dp(0x700, 10, 0, 0)), Insertion::Stored);
+        assert_eq!(
+            table.insert(wild),
+            Insertion::Key {
+                target: 2,
+                key: vec![7]
+            }
+        );
+
+        // Wild 1 at 5 meets wild 0 at 12: k_1 + 5 = k_0 + 12
+        let mut other = dp(0x800, 12, 1, 9);
+        other.target = 0;
+        let mut newcomer = dp(0x800, 5, 1, 10);
+        newcomer.target = 1;
+        assert_eq!(table.insert(other), Insertion::Stored);
+        assert_eq!(
+            table.insert(newcomer),
+            Insertion::Relation {
+                a: 1,
+                b: 0,
+                candidates: vec![(Scalar::ONE, Scalar::from(7u64))],
+                respawn: 10
+            }
+        );
+    }
+
+    #[test]
+    fn test_symmetric_wild_relation() {
+        // mid = 1000, k_a = 1013 (k'_a = 13), k_b = 990 (k'_b = -10).
+        // Wild a stored 13 + 4 = 17, wild b flipped: -(-10) + d_b = -17, d_b = -27.
+        let mut mid = [0u8; 32];
+        mid[..2].copy_from_slice(&1000u16.to_le_bytes());
+        let table = DPTable::with_targets(mid, Vec::new(), true);
+        let mut b = dp(0x900, 0, 1, 1);
+        b.dist = [
+            0u32.wrapping_sub(27),
+            u32::MAX,
+            u32::MAX,
+            u32::MAX,
+            u32::MAX,
+            u32::MAX,
+            u32::MAX,
+            u32::MAX,
+        ];
+        let mut a = dp(0x900, 4, 1, 2);
+        a.target = 1;
+
+        assert_eq!(table.insert(b), Insertion::Stored);
+        let Insertion::Relation { candidates, .. } = table.insert(a) else {
+            panic!("expected a relation");
+        };
+        assert!(candidates
+            .iter()
+            .any(|(m, c)| *m * Scalar::from(990u64) + c == Scalar::from(1013u64)));
     }
 }
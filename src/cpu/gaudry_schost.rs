@@ -0,0 +1,252 @@
+//! Gaudry–Schost: random restarts in a tame and a wild set
+//!
+//! Walks are not kept going until they meet. Each one stops at its first DP and
+//! starts over at a random point of its set: tames at `start + t` with `t` in
+//! the tame set, wilds at `P + w` with `w` in the wild set. Both sets are centred
+//! on the middle of the range, so the wild set moves with the key and keys near
+//! the ends of the range see less overlap. A tame and a wild walk that meet give
+//! the key exactly like a kangaroo collision, so the herd layout and the DP table
+//! are shared with the van Oorschot–Wiener search.
+
+use crate::cpu::init::{hash_seed, HerdLayout};
+use crate::gpu::{GpuKangaroo, KTYPE_TAME, KTYPE_WILD};
+use anyhow::{bail, Result};
+use k256::U256 as K256U256;
+use rayon::prelude::*;
+
+/// Tame set width used unless the caller picks another one (percent of the range)
+pub const DEFAULT_TAME_SET: u32 = 100;
+/// Wild set width used unless the caller picks another one (percent of the range)
+pub const DEFAULT_WILD_SET: u32 = 50;
+
+/// Checkpoint and work file flag bits: tame percent in bits 8-15, wild in 16-23
+const SETS_SHIFT: u32 = 8;
+const SET_MASK: u32 = 0xFF;
+
+/// Key positions averaged over for the expected work
+const FACTOR_SAMPLES: u32 = 1000;
+
+/// Widths of the tame and wild sets in percent of the range width
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SetOverlap {
+    /// Tame set, centred on the middle of the range
+    pub tame_percent: u32,
+    /// Wild set, centred on the key
+    pub wild_percent: u32,
+}
+
+impl Default for SetOverlap {
+    fn default() -> Self {
+        Self {
+            tame_percent: DEFAULT_TAME_SET,
+            wild_percent: DEFAULT_WILD_SET,
+        }
+    }
+}
+
+impl SetOverlap {
+    /// Both sets within the range, and overlapping wherever the key lies
+    pub fn validate(&self) -> Result<()> {
+        for (name, percent) in [("Tame", self.tame_percent), ("Wild", self.wild_percent)] {
+            if !(1..=100).contains(&percent) {
+                bail!("{} set of {}% is outside 1..=100%", name, percent);
+            }
+        }
+        if self.tame_percent + self.wild_percent <= 100 {
+            bail!(
+                "Tame and wild sets of {}% and {}% miss keys at the ends of the range; they must add up to more than 100%",
+                self.tame_percent,
+                self.wild_percent
+            );
+        }
+        Ok(())
+    }
+
+    /// Expected operations until a tame and a wild point coincide, in units of √N.
+    ///
+    /// With the points spread evenly over sets of `t·N` and `w·N` keys that
+    /// overlap in `o·N`, the first tame/wild coincidence is Rayleigh distributed
+    /// with mean `√(π·t·w/o)·√N`; this averages it over the key's position.
+    pub fn sqrt_factor(&self) -> f64 {
+        let tame = f64::from(self.tame_percent) / 100.0;
+        let wild = f64::from(self.wild_percent) / 100.0;
+        let mean = (0..FACTOR_SAMPLES)
+            .map(|i| {
+                let key = (f64::from(i) + 0.5) / f64::from(FACTOR_SAMPLES) - 0.5;
+                let overlap =
+                    (tame / 2.0).min(key + wild / 2.0) - (-tame / 2.0).max(key - wild / 2.0);
+                (tame * wild / overlap).sqrt()
+            })
+            .sum::<f64>()
+            / f64::from(FACTOR_SAMPLES);
+        std::f64::consts::PI.sqrt() * mean
+    }
+
+    /// Initial herd: half tames, then wilds split evenly between the targets,
+    /// each at a random point of its set
+    pub fn herd(&self, layout: &HerdLayout, num_kangaroos: u32, seed: u64) -> Vec<GpuKangaroo> {
+        let tames = num_kangaroos / 2;
+        let targets = layout.targets().max(1);
+        let width = layout.range_size();
+        (0..num_kangaroos)
+            .into_par_iter()
+            .map(|i| {
+                let (ktype, target) = if i < tames {
+                    (KTYPE_TAME, 0)
+                } else {
+                    (KTYPE_WILD, (i - tames) % targets)
+                };
+                layout.spawn(ktype, target, self.offset(ktype, width, hash_seed(i, seed)))
+            })
+            .collect()
+    }
+
+    /// A fresh walk of type `ktype` at a random point of its set
+    pub fn restart(&self, layout: &HerdLayout, ktype: u32, target: u32) -> GpuKangaroo {
+        let random = K256U256::from_le_slice(&rand::random::<[u8; 32]>());
+        let offset = self.offset(ktype, layout.range_size(), random);
+        layout.spawn(ktype, target, offset)
+    }
+
+    /// Offset in `[0, width)` of the point `random` picks in the set of `ktype`
+    fn offset(&self, ktype: u32, width: K256U256, random: K256U256) -> K256U256 {
+        let percent = if ktype == KTYPE_TAME {
+            self.tame_percent
+        } else {
+            self.wild_percent
+        };
+        let set = percent_of(width, percent).max(K256U256::ONE);
+        width
+            .shr_vartime(1)
+            .wrapping_sub(&set.shr_vartime(1))
+            .wrapping_add(&random.wrapping_rem(&set))
+    }
+}
+
+impl std::fmt::Display for SetOverlap {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Gaudry–Schost (tame set {}%, wild set {}%)",
+            self.tame_percent, self.wild_percent
+        )
+    }
+}
+
+/// Flag bits recording `sets` (none without Gaudry–Schost)
+pub(crate) fn flag_bits(sets: Option<SetOverlap>) -> u32 {
+    sets.map_or(0, |sets| {
+        (sets.tame_percent | sets.wild_percent << 8) << SETS_SHIFT
+    })
+}
+
+pub(crate) fn from_flag_bits(flags: u32) -> Result<Option<SetOverlap>> {
+    let sets = SetOverlap {
+        tame_percent: flags >> SETS_SHIFT & SET_MASK,
+        wild_percent: flags >> (SETS_SHIFT + 8) & SET_MASK,
+    };
+    if sets.tame_percent == 0 && sets.wild_percent == 0 {
+        return Ok(None);
+    }
+    sets.validate()?;
+    Ok(Some(sets))
+}
+
+/// `width * percent / 100`, dividing first where the product would not fit
+fn percent_of(width: K256U256, percent: u32) -> K256U256 {
+    let percent = K256U256::from_u32(percent);
+    let hundred = K256U256::from_u32(100);
+    if width.bits() <= 248 {
+        width.wrapping_mul(&percent).wrapping_div(&hundred)
+    } else {
+        width.wrapping_div(&hundred).wrapping_mul(&percent)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cpu::jumps::JumpTableSpec;
+    use crate::cpu::{CpuWalker, DPTable, Insertion};
+    use crate::crypto::parse_pubkey;
+    use crate::gpu::DP_KIND_STUCK;
+    use crate::range::KeyRange;
+
+    fn sets(tame_percent: u32, wild_percent: u32) -> SetOverlap {
+        SetOverlap {
+            tame_percent,
+            wild_percent,
+        }
+    }
+
+    #[test]
+    fn test_sqrt_factor() {
+        // Two full-width sets: Gaudry and Schost's 2.08·√N
+        assert!((sets(100, 100).sqrt_factor() - 2.08).abs() < 0.01);
+        // A half-width wild set lies inside the tame set for half of the keys
+        assert!((SetOverlap::default().sqrt_factor() - 1.925).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_validate_and_flags() {
+        assert!(SetOverlap::default().validate().is_ok());
+        assert!(sets(0, 100).validate().is_err());
+        assert!(sets(100, 101).validate().is_err());
+        assert!(sets(50, 50).validate().is_err());
+
+        assert_eq!(from_flag_bits(flag_bits(None) | 0b111).unwrap(), None);
+        let bits = flag_bits(Some(sets(80, 40)));
+        assert_eq!(bits & 0xFF, 0);
+        assert_eq!(from_flag_bits(bits | 1).unwrap(), Some(sets(80, 40)));
+        assert!(from_flag_bits(1 << SETS_SHIFT).is_err());
+    }
+
+    #[test]
+    fn test_offsets_stay_in_their_sets() {
+        let width = K256U256::from_u32(1000);
+        let sets = SetOverlap::default();
+        for i in 0..2000u32 {
+            let random = hash_seed(i, 1);
+            let tame = sets.offset(KTYPE_TAME, width, random);
+            assert!(tame < width);
+            let wild = sets.offset(KTYPE_WILD, width, random);
+            assert!(wild >= K256U256::from_u32(250) && wild < K256U256::from_u32(750));
+        }
+
+        // The full key space does not overflow
+        let full = K256U256::MAX;
+        assert!(sets.offset(KTYPE_WILD, full, full) < full);
+    }
+
+    #[test]
+    fn test_restarts_solve_puzzle_20() {
+        // Puzzle 20: key 0xd2c55 in [0x80000, 0xfffff]
+        let pubkey =
+            parse_pubkey("033c4a45cbd643ff97d77f41ea37e843648d50fd894b864b0d52febc62f6454f7c")
+                .unwrap();
+        let mut start = [0u8; 32];
+        start[2] = 0x08;
+        let range = KeyRange::from_bits(start, 19);
+        let walker = CpuWalker::new(&range, &JumpTableSpec::default(), 4, false, 1 << 10).unwrap();
+        let layout = HerdLayout::new(vec![pubkey], range, false);
+        let table = DPTable::new(start);
+        let sets = SetOverlap::default();
+
+        let mut herd = sets.herd(&layout, 512, 3);
+        let key = (0..400).find_map(|_| {
+            let dps = walker.walk(&mut herd, 64, 0);
+            dps.into_iter().find_map(|dp| {
+                let k = &mut herd[dp.kangaroo_id as usize];
+                *k = sets.restart(&layout, k.ktype, k.target);
+                if dp.kind == DP_KIND_STUCK {
+                    return None;
+                }
+                match table.insert(dp) {
+                    Insertion::Key { key, .. } => Some(key),
+                    _ => None,
+                }
+            })
+        });
+        assert_eq!(key, Some(vec![0x0d, 0x2c, 0x55]));
+    }
+}
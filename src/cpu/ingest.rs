@@ -0,0 +1,129 @@
+//! Off-thread DP insertion, so the GPU loop never waits on the DP table
+
+use crate::cpu::{DPTable, Insertion};
+use crate::gpu::GpuDistinguishedPoint;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+
+/// DPs whose insertion did more than store them, with what it found
+pub type Hits = Vec<(GpuDistinguishedPoint, Insertion)>;
+
+/// A thread inserting DP batches into a `DPTable`
+pub struct DpIngest {
+    batches: Option<Sender<Vec<GpuDistinguishedPoint>>>,
+    results: Receiver<Hits>,
+    in_flight: usize,
+    worker: Option<JoinHandle<()>>,
+}
+
+impl DpIngest {
+    pub fn spawn(table: Arc<DPTable>) -> Self {
+        let (batch_tx, batch_rx) = mpsc::channel::<Vec<GpuDistinguishedPoint>>();
+        let (result_tx, result_rx) = mpsc::channel();
+        let worker = std::thread::Builder::new()
+            .name("dp-ingest".to_string())
+            .spawn(move || {
+                for batch in batch_rx {
+                    let hits: Hits = batch
+                        .into_iter()
+                        .filter_map(|dp| match table.insert(dp) {
+                            Insertion::Stored => None,
+                            found => Some((dp, found)),
+                        })
+                        .collect();
+                    if result_tx.send(hits).is_err() {
+                        break;
+                    }
+                }
+            })
+            .expect("failed to spawn DP ingestion thread");
+
+        Self {
+            batches: Some(batch_tx),
+            results: result_rx,
+            in_flight: 0,
+            worker: Some(worker),
+        }
+    }
+
+    /// Queue a batch; its hits show up in a later `drain` or `flush`
+    pub fn submit(&mut self, dps: Vec<GpuDistinguishedPoint>) {
+        if dps.is_empty() {
+            return;
+        }
+        if let Some(ref batches) = self.batches {
+            if batches.send(dps).is_ok() {
+                self.in_flight += 1;
+            }
+        }
+    }
+
+    /// Hits of the batches finished so far, without waiting
+    pub fn drain(&mut self) -> Hits {
+        let mut hits = Vec::new();
+        while let Ok(batch) = self.results.try_recv() {
+            self.in_flight -= 1;
+            hits.extend(batch);
+        }
+        hits
+    }
+
+    /// Wait for every queued batch and return their hits
+    pub fn flush(&mut self) -> Hits {
+        let mut hits = Vec::new();
+        while self.in_flight > 0 {
+            match self.results.recv() {
+                Ok(batch) => hits.extend(batch),
+                // The thread is gone; nothing more will arrive
+                Err(_) => break,
+            }
+            self.in_flight -= 1;
+        }
+        self.in_flight = 0;
+        hits
+    }
+}
+
+impl Drop for DpIngest {
+    fn drop(&mut self) {
+        self.batches.take();
+        if let Some(worker) = self.worker.take() {
+            let _ = worker.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dp(ktype: u32, dist0: u32) -> GpuDistinguishedPoint {
+        GpuDistinguishedPoint {
+            x: [0x100, 0, 0, 0, 0, 0, 0, 0x1234_5678],
+            dist: [dist0, 0, 0, 0, 0, 0, 0, 0],
+            ktype,
+            kangaroo_id: ktype,
+            kind: 0,
+            target: 0,
+            _padding: [0; 4],
+        }
+    }
+
+    #[test]
+    fn test_ingest_reports_only_hits() {
+        let table = Arc::new(DPTable::new([0u8; 32]));
+        let mut ingest = DpIngest::spawn(table.clone());
+
+        ingest.submit(vec![dp(0, 105)]);
+        ingest.submit(Vec::new());
+        assert!(ingest.flush().is_empty());
+        assert_eq!(table.total_dps(), 1);
+
+        ingest.submit(vec![dp(1, 5)]);
+        let hits = ingest.flush();
+        assert_eq!(hits.len(), 1);
+        assert!(matches!(hits[0].1, Insertion::Key { .. }));
+        assert!(ingest.drain().is_empty());
+    }
+}
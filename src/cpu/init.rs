@@ -1,199 +1,444 @@
-//! Kangaroo initialization and jump table generation.
+//! Kangaroo initialization and herd layout.
 
-use crate::convert::{affine_to_gpu, scalar_be_to_limbs};
-use crate::crypto::{Point, U256};
-use crate::gpu::{GpuAffinePoint, GpuKangaroo};
+use crate::convert::{affine_to_gpu, le_bytes_to_scalar, limbs_to_be_bytes, scalar_be_to_limbs};
+use crate::crypto::{mul_base, Point, U256};
+use crate::gpu::{GpuKangaroo, KTYPE_MIRRORED_WILD, KTYPE_TAME, KTYPE_WILD, NO_JUMP};
 use crate::math::negate_256_be;
-use anyhow::Result;
-use k256::elliptic_curve::ops::{MulByGenerator, Reduce};
+use crate::range::KeyRange;
+use anyhow::{bail, Result};
+use k256::elliptic_curve::bigint::Encoding;
+use k256::elliptic_curve::ops::Reduce;
+use k256::elliptic_curve::point::AffineCoordinates;
 use k256::U256 as K256U256;
 use k256::{ProjectivePoint, Scalar};
+use rand::RngCore;
 use rayon::prelude::*;
 
-/// Generate jump table with precomputed points.
-///
-/// Uses FNV-1a pseudo-random generation (Strategy S2) for better distribution
-/// than simple powers of 2.
-pub fn generate_jump_table(range_bits: u32) -> (Vec<GpuAffinePoint>, Vec<[u32; 8]>) {
-    const TABLE_SIZE: usize = 256;
-
-    let mut points = Vec::with_capacity(TABLE_SIZE);
-    let mut distances = Vec::with_capacity(TABLE_SIZE);
-
-    // Target mean step size: sqrt(N) / 2
-    let mean_exp = range_bits / 2;
-
-    // Generate random scalars with magnitude around 2^mean_exp.
-    for i in 0..TABLE_SIZE {
-        // FNV-1a like hash to generate deterministic random steps
-        let mut h = 0x811c9dc5u32;
-        h = (h ^ (i as u32)).wrapping_mul(0x01000193);
-
-        // Strategy: fill bytes up to mean_exp / 8
-        // We need ceil(mean_exp / 8) bytes to cover mean_exp bits
-        let num_bytes = mean_exp.div_ceil(8);
-        let limit_byte = (num_bytes as usize).min(32);
-        let mut scalar_bytes = [0u8; 32];
-
-        // Use the hash to fill bytes
-        #[allow(clippy::needless_range_loop)]
-        for b in (32 - limit_byte)..32 {
-            h = (h ^ (b as u32)).wrapping_mul(0x01000193);
-            scalar_bytes[b] = (h & 0xFF) as u8;
-        }
+/// Herd seed used unless the caller picks another one
+pub const DEFAULT_SEED: u64 = 0xCAFEBABE;
 
-        // Mask the top byte to ensure we don't exceed our target range
-        // If mean_exp=20, num_bytes=3. Top byte index is 32-3=29.
-        // 20 % 8 = 4. We want 4 bits (0..3) from the top byte?
-        // No, if mean_exp=20, we want values up to 2^20.
-        // Byte 31 (LSB) -> bits 0..7
-        // Byte 30 -> bits 8..15
-        // Byte 29 -> bits 16..23
-        // We want to mask Byte 29 to keep bits 16..19 (4 bits).
-        // 20 % 8 = 4.
-
-        let rem = mean_exp % 8;
-        if rem != 0 {
-            let mask = (1u8 << rem) - 1;
-            if 32 - limit_byte < 32 {
-                scalar_bytes[32 - limit_byte] &= mask;
-            }
-        } else if limit_byte > 0 {
-            // If exact multiple of 8, we typically allow full byte?
-            // e.g. mean_exp=16. limit=2. Bytes 30, 31.
-            // We want up to 2^16? No, 2^16 requires 17 bits usually (bit 16 set).
-            // But here we are generating random offsets *up to* 2^mean_exp.
-            // So for 16, we probably want 0..2^16-1. So 2 full bytes is fine.
-        }
+/// Fresh herd seed from the OS RNG, so separate runs never repeat a walk
+pub fn random_seed() -> u64 {
+    rand::rngs::OsRng.next_u64()
+}
+
+/// Herd composition of a kangaroo search
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Herds {
+    /// van Oorschot–Wiener: half tame, half wild (~2·√N operations)
+    #[default]
+    Vow,
+    /// Galbraith–Pollard–Ruprai: a third each of tames, wilds from P and
+    /// wilds from -P (~1.82·√N)
+    Three,
+    /// Galbraith–Pollard–Ruprai: half tame, a quarter each of wilds from P and
+    /// from -P (~1.71·√N)
+    Four,
+}
 
-        // Ensure at least 1
-        if scalar_bytes.iter().all(|&x| x == 0) {
-            scalar_bytes[31] = 1;
+impl Herds {
+    /// Expected operations of the search in units of √N
+    pub fn sqrt_factor(self) -> f64 {
+        match self {
+            Herds::Vow => 2.0,
+            Herds::Three => 1.82,
+            Herds::Four => 1.71,
         }
+    }
 
-        // Compute point = scalar * G
-        let scalar_uint = K256U256::from_be_slice(&scalar_bytes);
-        let scalar = Scalar::reduce(scalar_uint);
-        let point = ProjectivePoint::mul_by_generator(&scalar);
-        let affine = point.to_affine();
+    /// Code stored in checkpoint and work file flags
+    pub(crate) fn code(self) -> u32 {
+        match self {
+            Herds::Vow => 0,
+            Herds::Three => 1,
+            Herds::Four => 2,
+        }
+    }
 
-        points.push(affine_to_gpu(&affine));
-        distances.push(scalar_be_to_limbs(&scalar_bytes));
+    pub(crate) fn from_code(code: u32) -> Result<Self> {
+        Ok(match code {
+            0 => Herds::Vow,
+            1 => Herds::Three,
+            2 => Herds::Four,
+            _ => bail!("Unknown herd composition {}", code),
+        })
     }
 
-    // Debug logging for first few
-    for i in 0..4u32 {
-        tracing::debug!("Jump table[{}] generated", i);
+    /// `(ktype, index within its herd, herd size)` of kangaroo `i` out of `n`
+    fn role(self, i: u32, n: u32) -> (u32, u32, u32) {
+        let (tames, wilds) = match self {
+            Herds::Vow => (n / 2, n - n / 2),
+            Herds::Three => (n / 3, n / 3),
+            Herds::Four => (n / 2, n / 4),
+        };
+        if i < tames {
+            (KTYPE_TAME, i, tames)
+        } else if i < tames + wilds {
+            (KTYPE_WILD, i - tames, wilds)
+        } else {
+            (KTYPE_MIRRORED_WILD, i - tames - wilds, n - tames - wilds)
+        }
     }
+}
 
-    (points, distances)
+impl std::fmt::Display for Herds {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Herds::Vow => "tame and wild (van Oorschot–Wiener)",
+            Herds::Three => "three kangaroos (tame, wild, wild from -P)",
+            Herds::Four => "four kangaroos (2 tame, wild, wild from -P)",
+        })
+    }
 }
 
 /// Initialize kangaroo positions.
 ///
 /// Half are "tame" (start at known point), half are "wild" (start near pubkey).
+/// `seed` randomises the jitter within each kangaroo's grid cell.
+/// Points are multiples of `base` (G for an ordinary key search).
 pub fn initialize_kangaroos(
     pubkey: &Point,
-    start: &U256,
-    range_bits: u32,
+    base: &Point,
+    range: &KeyRange,
     num_kangaroos: u32,
+    seed: u64,
 ) -> Result<Vec<GpuKangaroo>> {
-    let half = num_kangaroos / 2;
+    tracing::debug!(
+        "Kangaroo init: range={}, range_size={}",
+        range,
+        range.width()
+    );
 
-    // Handle the case where range_bits is 128 or more (u128 overflow)
-    // Actually our u128 math might overflow if range_bits=128, but usually it is <128.
-    // If range_bits=128, range_size=0 (overflow) in u128.
-    // But GpuKangaroo supports full 256 bits.
-    // For initialization we assume range < 128 bits for now as logic uses u128.
+    let layout = HerdLayout::with_base(vec![*pubkey], *range, false, *base);
+    Ok(layout.herd(num_kangaroos, seed))
+}
 
-    // Re-check range_size calculation to avoid panic on overflow if range_bits=128
-    let range_size = if range_bits >= 128 {
-        u128::MAX
-    } else {
-        1u128 << range_bits
-    };
+/// The part of a start grid shared by several herds that one herd takes: its
+/// kangaroos are kangaroos `first..` of `total`. Herds on disjoint slices
+/// never start in the same grid cell.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct GridSlice {
+    pub first: u64,
+    pub total: u64,
+}
 
-    let range_middle = if range_bits >= 128 {
-        u128::MAX / 2
-    } else {
-        1u128 << (range_bits - 1)
-    };
-
-    // Use u256_to_u128 for start (assuming start fits in 128 bits for this logic?
-    // No, start can be large.
-    // Wait, the original code used `u256_to_u128(start)`.
-    // If start is 256-bit (e.g. 0x8000...), `u256_to_u128` might truncate or panic?
-    // Let's check `convert.rs`.
-    // Assuming for now we are solving in a sub-range so start is arbitrary.
-    // But `init_tame_kangaroo` uses `start_val + tame_offset`.
-    // If start is large, we need full 256-bit arithmetic.
-    // The original code used u128 for offsets and simple math.
-    // We should stick to that assumption or improve it.
-    // Let's assume start fits in u128 OR we handle full scalar math.
-    // Since `init_tame_kangaroo_at_offset` takes `start_val: u128`, it seems the original code assumed
-    // start is small OR we only care about the lower 128 bits for the offset logic?
-    // NO, start is the base key. It can be large.
-    // `init_tame_kangaroo` in original code:
-    // `let tame_scalar = start_val + tame_offset;` where start_val is u128.
-    // This implies the original code ONLY supported start values < 2^128.
-    // That's a limitation of the original code. I will keep it for now but note it.
-    // Actually, let's fix it by passing `start` as U256 to the helper.
-
-    // Wait, let's stick to the interface.
-    // I will read `convert.rs` later to see `u256_to_u128`.
-    // For now, I'll restore `hash_seed` and the logic.
+/// Where a search puts its kangaroos, for the initial herd and for reseeding.
+///
+/// With several pubkeys (targets) the tame herd is shared and the wild herd
+/// is split evenly between them. `base` is the point keys are multiples of,
+/// so `*` below is multiplication by it: G unless solving `P = k * Q`.
+#[derive(Clone, Debug)]
+pub enum HerdLayout {
+    /// Tames at `start + offset`, wilds within half a range of their target pubkey
+    Standard {
+        pubkeys: Vec<Point>,
+        base: Point,
+        start: U256,
+        width: K256U256,
+    },
+    /// Negation-map layout. The search is shifted so the range is centred on
+    /// zero: each shifted pubkey is `pubkey - mid * G`, tames start at `d * G`
+    /// and wilds at `shifted_pubkey + d * G` with `d` in
+    /// `[-width/2, width - width/2)`. Each point is stored as the even-y member
+    /// of {P, -P}, with its distance negated if needed.
+    Symmetric {
+        shifted_pubkeys: Vec<Point>,
+        base: Point,
+        width: K256U256,
+    },
+    /// Three- and four-kangaroo layout, centred like `Symmetric` but without
+    /// the negation map: tames start at `d * G` with `d` across the range,
+    /// wilds at `shifted_pubkey + d * G` and mirrored wilds at
+    /// `-shifted_pubkey + d * G`, with `d` within a quarter range of zero.
+    Mirrored {
+        shifted_pubkey: Point,
+        base: Point,
+        width: K256U256,
+        herds: Herds,
+    },
+}
 
-    tracing::debug!(
-        "Kangaroo init: range_bits={}, range_size=0x{:x}, range_middle=0x{:x}",
-        range_bits,
-        range_size,
-        range_middle
-    );
+impl HerdLayout {
+    /// Layout of a search over `range`
+    pub fn new(pubkeys: Vec<Point>, range: KeyRange, negation: bool) -> Self {
+        Self::with_base(pubkeys, range, negation, ProjectivePoint::GENERATOR)
+    }
 
-    // Grid delta for even distribution (S2 strategy)
-    let grid_delta = if num_kangaroos > 0 {
-        range_size / (num_kangaroos as u128)
-    } else {
-        range_size
-    };
+    /// Layout of a search over `range` for keys with `pubkey = k * base`
+    pub fn with_base(pubkeys: Vec<Point>, range: KeyRange, negation: bool, base: Point) -> Self {
+        if negation {
+            // Centre the search on zero so a reflection only flips the distance's sign
+            let mid = le_bytes_to_scalar(&range.midpoint());
+            let mid_point = mul_base(&base, &mid);
+            Self::Symmetric {
+                shifted_pubkeys: pubkeys.iter().map(|pubkey| pubkey - &mid_point).collect(),
+                base,
+                width: range.width(),
+            }
+        } else {
+            Self::Standard {
+                pubkeys,
+                base,
+                start: range.start(),
+                width: range.width(),
+            }
+        }
+    }
 
-    // Parallel initialization with rayon
-    let kangaroos: Vec<GpuKangaroo> = (0..num_kangaroos)
-        .into_par_iter()
-        .map(|i| {
-            let is_tame = i < half;
+    /// Layout of a search over `range` with the given herd composition and
+    /// base; three and four kangaroos take a single pubkey and no negation map
+    pub fn for_search(
+        pubkeys: Vec<Point>,
+        range: KeyRange,
+        negation: bool,
+        herds: Herds,
+        base: Point,
+    ) -> Result<Self> {
+        if herds == Herds::Vow {
+            return Ok(Self::with_base(pubkeys, range, negation, base));
+        }
+        if negation || pubkeys.len() != 1 {
+            bail!("Three and four kangaroos need a single pubkey and no negation map");
+        }
+        let mid = le_bytes_to_scalar(&range.midpoint());
+        Ok(Self::Mirrored {
+            shifted_pubkey: pubkeys[0] - mul_base(&base, &mid),
+            base,
+            width: range.width(),
+            herds,
+        })
+    }
 
-            // Grid-based offset + small random jitter
-            let grid_pos = (i as u128) * grid_delta;
-            let prng_seed = hash_seed(i, 0xCAFEBABE);
-            let jitter = prng_seed % (grid_delta / 2 + 1);
+    /// Number of distinct start offsets
+    pub fn range_size(&self) -> K256U256 {
+        let (Self::Standard { width, .. }
+        | Self::Symmetric { width, .. }
+        | Self::Mirrored { width, .. }) = self;
+        *width
+    }
 
-            let offset = (grid_pos + jitter) % range_size;
+    /// The point keys are multiples of
+    pub fn base(&self) -> Point {
+        let (Self::Standard { base, .. }
+        | Self::Symmetric { base, .. }
+        | Self::Mirrored { base, .. }) = self;
+        *base
+    }
 
-            let (point, dist) = if is_tame {
-                init_tame_kangaroo_at_offset(start, offset)
-            } else {
-                init_wild_kangaroo_at_offset(pubkey, offset, range_middle)
-            };
+    fn herds(&self) -> Herds {
+        match self {
+            Self::Mirrored { herds, .. } => *herds,
+            _ => Herds::Vow,
+        }
+    }
+
+    /// Offset of the range midpoint, where wild offsets are centred
+    fn range_middle(&self) -> K256U256 {
+        self.range_size().shr_vartime(1)
+    }
+
+    /// A random start offset in `[0, range_size)`
+    pub fn random_offset(&self) -> K256U256 {
+        K256U256::from_le_slice(&rand::random::<[u8; 32]>()).wrapping_rem(&self.range_size())
+    }
+
+    /// Number of target pubkeys
+    pub fn targets(&self) -> u32 {
+        match self {
+            Self::Standard { pubkeys, .. }
+            | Self::Symmetric {
+                shifted_pubkeys: pubkeys,
+                ..
+            } => pubkeys.len() as u32,
+            Self::Mirrored { .. } => 1,
+        }
+    }
+
+    /// Spread a herd over the range: tames first, then wilds (and mirrored wilds).
+    ///
+    /// The van Oorschot–Wiener herd lies on one grid over all kangaroos, the
+    /// three- and four-kangaroo herds on one grid per herd.
+    pub fn herd(&self, num_kangaroos: u32, seed: u64) -> Vec<GpuKangaroo> {
+        self.herd_in(num_kangaroos, seed, None)
+    }
+
+    /// Like `herd`, but on `grid`'s slice of a grid shared with the other
+    /// devices of the search (the whole grid for `None`)
+    pub fn herd_in(
+        &self,
+        num_kangaroos: u32,
+        seed: u64,
+        grid: Option<GridSlice>,
+    ) -> Vec<GpuKangaroo> {
+        let herds = self.herds();
+        self.place(num_kangaroos, seed, grid, |i| herds.role(i, num_kangaroos))
+    }
 
-            let gpu_point = affine_to_gpu(&point);
+    /// A herd of `num_kangaroos` kangaroos all of type `ktype` (wilds split
+    /// between the targets), spread over the range like `herd`
+    pub fn single_herd(&self, ktype: u32, num_kangaroos: u32, seed: u64) -> Vec<GpuKangaroo> {
+        self.place(num_kangaroos, seed, None, |i| (ktype, i, num_kangaroos))
+    }
 
-            GpuKangaroo {
-                x: gpu_point.x,
-                y: gpu_point.y,
-                dist,
-                ktype: if is_tame { 0 } else { 1 },
-                is_active: 1,
-                _padding: [0; 6],
+    /// Kangaroos `0..num_kangaroos`, each with the `(ktype, index within its
+    /// herd, herd size)` that `role` gives it
+    fn place(
+        &self,
+        num_kangaroos: u32,
+        seed: u64,
+        grid: Option<GridSlice>,
+        role: impl Fn(u32) -> (u32, u32, u32) + Sync,
+    ) -> Vec<GpuKangaroo> {
+        let range_size = self.range_size();
+        let targets = self.targets().max(1);
+        let herds = self.herds();
+
+        // On a slice of a shared grid the herd spreads over its own cells only
+        let (herd_start, herd_span) = match grid {
+            Some(grid) => {
+                let cell = range_size.wrapping_div(&K256U256::from_u64(grid.total.max(1)));
+                (
+                    K256U256::from_u64(grid.first).wrapping_mul(&cell),
+                    cell.wrapping_mul(&K256U256::from_u32(num_kangaroos)),
+                )
             }
-        })
-        .collect();
+            None => (K256U256::ZERO, range_size),
+        };
+
+        // Parallel initialization with rayon
+        (0..num_kangaroos)
+            .into_par_iter()
+            .map(|i| {
+                let (ktype, index, herd_size) = role(i);
+                let target = if ktype == KTYPE_TAME {
+                    0
+                } else {
+                    index % targets
+                };
+                let (slot, slots) = if herds == Herds::Vow {
+                    (i, num_kangaroos)
+                } else {
+                    (index, herd_size)
+                };
+
+                // Grid delta for even distribution (S2 strategy)
+                let grid_delta = herd_span.wrapping_div(&K256U256::from_u32(slots.max(1)));
+                let jitter_span = grid_delta.shr_vartime(1).wrapping_add(&K256U256::ONE);
+
+                // Grid-based offset + small random jitter
+                let grid_pos =
+                    herd_start.wrapping_add(&K256U256::from_u32(slot).wrapping_mul(&grid_delta));
+                let jitter = hash_seed(i, seed).wrapping_rem(&jitter_span);
+
+                let offset = grid_pos.wrapping_add(&jitter).wrapping_rem(&range_size);
+
+                self.spawn(ktype, target, offset)
+            })
+            .collect()
+    }
 
-    Ok(kangaroos)
+    /// A fresh kangaroo of type `ktype` at `offset` (in `[0, range_size)`)
+    /// into the range; `target` picks the pubkey of a wild kangaroo
+    pub fn spawn(&self, ktype: u32, target: u32, offset: K256U256) -> GpuKangaroo {
+        let is_tame = ktype == KTYPE_TAME;
+        let target = if is_tame { 0 } else { target };
+        let base = self.base();
+        let (point, dist) = match self {
+            Self::Standard { pubkeys, start, .. } => {
+                if is_tame {
+                    init_tame_kangaroo_at_offset(&base, start, &offset)
+                } else {
+                    let range_middle = self.range_middle();
+                    init_wild_kangaroo_at_offset(
+                        &base,
+                        &pubkeys[target as usize],
+                        &offset,
+                        &range_middle,
+                    )
+                }
+            }
+            Self::Symmetric {
+                shifted_pubkeys, ..
+            } => {
+                let range_middle = self.range_middle();
+                let (point, dist) = if is_tame {
+                    // d = 0 would put the tame at infinity
+                    let offset = if offset == range_middle {
+                        offset.wrapping_add(&K256U256::ONE)
+                    } else {
+                        offset
+                    };
+                    init_wild_kangaroo_at_offset(
+                        &base,
+                        &ProjectivePoint::IDENTITY,
+                        &offset,
+                        &range_middle,
+                    )
+                } else {
+                    init_wild_kangaroo_at_offset(
+                        &base,
+                        &shifted_pubkeys[target as usize],
+                        &offset,
+                        &range_middle,
+                    )
+                };
+
+                if bool::from(point.y_is_odd()) {
+                    let negated = negate_256_be(&limbs_to_be_bytes(&dist));
+                    (-point, scalar_be_to_limbs(&negated))
+                } else {
+                    (point, dist)
+                }
+            }
+            Self::Mirrored { shifted_pubkey, .. } => {
+                let range_middle = self.range_middle();
+                if is_tame {
+                    // d = 0 would put the tame at infinity
+                    let offset = if offset == range_middle {
+                        offset.wrapping_add(&K256U256::ONE)
+                    } else {
+                        offset
+                    };
+                    init_wild_kangaroo_at_offset(
+                        &base,
+                        &ProjectivePoint::IDENTITY,
+                        &offset,
+                        &range_middle,
+                    )
+                } else {
+                    // Squeeze [0, width) into the middle half, i.e. d in [-width/4, width/4)
+                    let offset = offset
+                        .shr_vartime(1)
+                        .wrapping_add(&range_middle.shr_vartime(1));
+                    let origin = if ktype == KTYPE_MIRRORED_WILD {
+                        -*shifted_pubkey
+                    } else {
+                        *shifted_pubkey
+                    };
+                    init_wild_kangaroo_at_offset(&base, &origin, &offset, &range_middle)
+                }
+            }
+        };
+        let gpu_point = affine_to_gpu(&point);
+
+        GpuKangaroo {
+            x: gpu_point.x,
+            y: gpu_point.y,
+            dist,
+            ktype,
+            is_active: 1,
+            last_jump: NO_JUMP,
+            steps_since_dp: 0,
+            cycle_steps: 0,
+            cycle_x: [0; 2],
+            target,
+        }
+    }
 }
 
-/// FNV-1a hash for deterministic PRNG seeding.
-fn hash_seed(index: u32, salt: u64) -> u128 {
+/// FNV-1a hash for deterministic PRNG seeding, stretched to 256 bits so the
+/// jitter covers the grid spacing of any range
+pub(crate) fn hash_seed(index: u32, salt: u64) -> K256U256 {
     let mut h = 0xcbf29ce484222325u64; // FNV offset basis
 
     h ^= index as u64;
@@ -205,87 +450,300 @@ fn hash_seed(index: u32, salt: u64) -> u128 {
     h ^= h >> 33;
 
     let h2 = h.wrapping_mul(0xc4ceb9fe1a85ec53) ^ (index as u64).wrapping_mul(0x9e3779b97f4a7c15);
-    ((h as u128) << 64) | (h2 as u128)
+    let h3 = (h2 ^ (h2 >> 29)).wrapping_mul(0xbf58476d1ce4e5b9);
+    let h4 = (h3 ^ (h3 >> 32)).wrapping_mul(0x94d049bb133111eb);
+
+    let mut bytes = [0u8; 32];
+    for (chunk, word) in bytes.chunks_exact_mut(8).zip([h2, h, h3, h4]) {
+        chunk.copy_from_slice(&word.to_le_bytes());
+    }
+    K256U256::from_le_slice(&bytes)
 }
 
 /// Initialize a tame kangaroo at a specific offset from start
-fn init_tame_kangaroo_at_offset(start: &U256, offset: u128) -> (k256::AffinePoint, [u32; 8]) {
-    // Convert start (U256) to Scalar
-    // U256 is usually little-endian or we need to check crate::crypto::U256
-    // In `tools/kangaroo/src/crypto/mod.rs` likely uses `k256::U256` or custom.
-    // The original code used `u256_to_u128` which implies `start` might be compatible.
-    // Let's assume we can convert `start` to big-endian bytes.
-    // `start.to_be_bytes()`?
-    // Let's use `u256_to_u128` if we assume start is small, OR fix the math.
-    // BETTER: Use `k256::U256` add.
-
-    // Since I don't know the exact `U256` type (it's imported from `crate::crypto::U256`),
-    // I will use `u256_to_u128` as per original logic if I can't find better,
-    // BUT the original code passed `start_val: u128`.
-    // I changed the signature to `start: &U256`.
-
-    // Let's assume start fits in u128 for now to minimize breakage or check `crypto::U256`.
-    // Actually, looking at `cpu/solver.rs`: `start: U256`.
-    // `u256_to_u128` is imported from `crate::convert`.
-
-    // Convert start (LE bytes) to K256U256
-    let start_uint = K256U256::from_le_slice(start);
-
-    // Convert offset to K256U256
-    let mut offset_le = [0u8; 32];
-    offset_le[0..16].copy_from_slice(&offset.to_le_bytes());
-    let offset_uint = K256U256::from_le_slice(&offset_le);
-
-    // Add to get absolute scalar
-    let sum = start_uint.wrapping_add(&offset_uint);
-
-    let scalar = Scalar::reduce(sum);
-    let point = ProjectivePoint::mul_by_generator(&scalar);
-
-    // Store offset as dist (relative to start)
-    // Distance tracks the offset from start, so it fits in u128 (range size)
-    let mut offset_bytes = [0u8; 32];
-    offset_bytes[16..].copy_from_slice(&offset.to_be_bytes());
-
-    (point.to_affine(), scalar_be_to_limbs(&offset_bytes))
+fn init_tame_kangaroo_at_offset(
+    base: &Point,
+    start: &U256,
+    offset: &K256U256,
+) -> (k256::AffinePoint, [u32; 8]) {
+    let sum = K256U256::from_le_slice(start).wrapping_add(offset);
+    let point = mul_base(base, &Scalar::reduce(sum));
+
+    // The distance is the offset from start
+    (point.to_affine(), scalar_be_to_limbs(&offset.to_be_bytes()))
 }
 
 /// Initialize a wild kangaroo at a specific offset
 fn init_wild_kangaroo_at_offset(
+    base: &Point,
     pubkey: &Point,
-    raw_offset: u128,
-    range_middle: u128,
+    raw_offset: &K256U256,
+    range_middle: &K256U256,
 ) -> (k256::AffinePoint, [u32; 8]) {
     // Center the offset: map [0, range) to [-range/2, range/2)
-    let centered_offset = raw_offset as i128 - range_middle as i128;
-
-    if centered_offset >= 0 {
-        let offset = centered_offset as u128;
-        let mut offset_bytes = [0u8; 32];
-        offset_bytes[16..].copy_from_slice(&offset.to_be_bytes());
+    if raw_offset >= range_middle {
+        let offset = raw_offset.wrapping_sub(range_middle);
+        let wild_point = *pubkey + mul_base(base, &Scalar::reduce(offset));
 
-        let scalar_uint = K256U256::from_be_slice(&offset_bytes);
-        let scalar = Scalar::reduce(scalar_uint);
-        let offset_point = ProjectivePoint::mul_by_generator(&scalar);
-        let wild_point = *pubkey + offset_point;
-
-        (wild_point.to_affine(), scalar_be_to_limbs(&offset_bytes))
+        (
+            wild_point.to_affine(),
+            scalar_be_to_limbs(&offset.to_be_bytes()),
+        )
     } else {
         // Negative offset: subtract from pubkey
-        let abs_offset = (-centered_offset) as u128;
-        let mut offset_bytes = [0u8; 32];
-        offset_bytes[16..].copy_from_slice(&abs_offset.to_be_bytes());
-
-        let scalar_uint = K256U256::from_be_slice(&offset_bytes);
-        let scalar = Scalar::reduce(scalar_uint);
-        let offset_point = ProjectivePoint::mul_by_generator(&scalar);
-        let wild_point = *pubkey - offset_point;
+        let abs_offset = range_middle.wrapping_sub(raw_offset);
+        let wild_point = *pubkey - mul_base(base, &Scalar::reduce(abs_offset));
 
         // Store negative offset as two's complement
-        let neg_offset_bytes = negate_256_be(&offset_bytes);
+        let neg_offset_bytes = K256U256::ZERO.wrapping_sub(&abs_offset).to_be_bytes();
         (
             wild_point.to_affine(),
             scalar_be_to_limbs(&neg_offset_bytes),
         )
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::convert::signed_limbs_to_scalar;
+    use crate::cpu::jumps::{generate_jump_table, JumpTableSpec};
+    use k256::elliptic_curve::ops::MulByGenerator;
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_symmetric_herd_is_canonical() {
+        let shifted = ProjectivePoint::mul_by_generator(&Scalar::from(12_345u64));
+        let layout = HerdLayout::Symmetric {
+            shifted_pubkeys: vec![shifted],
+            base: ProjectivePoint::GENERATOR,
+            width: K256U256::ONE.shl_vartime(20),
+        };
+        let herd = layout.herd(16, DEFAULT_SEED);
+
+        for k in &herd {
+            // Even y, i.e. the limb form of the class representative
+            assert_eq!(k.y[0] & 1, 0);
+            assert_eq!(k.last_jump, NO_JUMP);
+
+            let step = ProjectivePoint::mul_by_generator(&signed_limbs_to_scalar(&k.dist));
+            let candidates = if k.ktype == 0 {
+                vec![step]
+            } else {
+                vec![shifted + step, step - shifted]
+            };
+            assert!(candidates
+                .iter()
+                .any(|p| affine_to_gpu(&p.to_affine()).x == k.x));
+        }
+    }
+
+    #[test]
+    fn test_spawn_places_fresh_kangaroo() {
+        let mut start = [0u8; 32];
+        start[0] = 100;
+        let layout = HerdLayout::Standard {
+            pubkeys: vec![ProjectivePoint::mul_by_generator(&Scalar::from(12_345u64))],
+            base: ProjectivePoint::GENERATOR,
+            start,
+            width: K256U256::ONE.shl_vartime(20),
+        };
+
+        let k = layout.spawn(KTYPE_TAME, 0, K256U256::from_u8(5));
+        let expected = ProjectivePoint::mul_by_generator(&Scalar::from(105u64));
+        assert_eq!(k.x, affine_to_gpu(&expected.to_affine()).x);
+        assert_eq!(k.dist[0], 5);
+        assert_eq!((k.ktype, k.is_active, k.last_jump), (0, 1, NO_JUMP));
+        assert_eq!((k.steps_since_dp, k.cycle_steps), (0, 0));
+        assert_eq!(layout.spawn(KTYPE_WILD, 0, K256U256::from_u8(5)).ktype, 1);
+    }
+
+    #[test]
+    fn test_wild_herd_is_split_between_targets() {
+        let pubkeys: Vec<Point> = [11u64, 22, 33]
+            .iter()
+            .map(|&k| ProjectivePoint::mul_by_generator(&Scalar::from(k)))
+            .collect();
+        let layout = HerdLayout::Standard {
+            pubkeys,
+            base: ProjectivePoint::GENERATOR,
+            start: [0u8; 32],
+            width: K256U256::ONE.shl_vartime(20),
+        };
+
+        let herd = layout.herd(12, DEFAULT_SEED);
+        let targets: Vec<u32> = herd.iter().map(|k| k.target).collect();
+        assert_eq!(targets, vec![0, 0, 0, 0, 0, 0, 0, 1, 2, 0, 1, 2]);
+        assert!(herd[..6].iter().all(|k| k.ktype == 0));
+    }
+
+    #[test]
+    fn test_herd_placement_above_128_bits() {
+        for range_bits in [130u32, 160] {
+            let mut start = [0u8; 32];
+            start[(range_bits / 8) as usize] = 1 << (range_bits % 8);
+            let start_scalar = le_bytes_to_scalar(&start);
+            let pubkey = ProjectivePoint::mul_by_generator(&start_scalar)
+                + ProjectivePoint::mul_by_generator(&Scalar::from(0x1234_5678u64));
+            let layout =
+                HerdLayout::new(vec![pubkey], KeyRange::from_bits(start, range_bits), false);
+            let (range_size, range_middle) = (layout.range_size(), layout.range_middle());
+            let herd = layout.herd(16, DEFAULT_SEED);
+            let above_128 = K256U256::ONE.shl_vartime(128);
+            let mut wide = [false; 2];
+
+            for k in &herd {
+                let dist = signed_limbs_to_scalar(&k.dist);
+                let (expected, span) = if k.ktype == 0 {
+                    (
+                        ProjectivePoint::mul_by_generator(&(start_scalar + dist)),
+                        range_size,
+                    )
+                } else {
+                    (
+                        pubkey + ProjectivePoint::mul_by_generator(&dist),
+                        range_middle,
+                    )
+                };
+                assert_eq!(affine_to_gpu(&expected.to_affine()).x, k.x);
+
+                let raw = K256U256::from_be_slice(&limbs_to_be_bytes(&k.dist));
+                let magnitude = if k.dist[7] >> 31 == 1 {
+                    K256U256::ZERO.wrapping_sub(&raw)
+                } else {
+                    raw
+                };
+                assert!(magnitude <= span, "{}-bit offset out of range", range_bits);
+                wide[k.ktype as usize] |= magnitude > above_128;
+            }
+
+            // Both herds spread past 2^128, which u128 offsets could not
+            assert_eq!(wide, [true, true]);
+
+            let offset = layout.random_offset();
+            assert!(offset < range_size);
+        }
+    }
+
+    #[test]
+    fn test_herd_on_another_base() {
+        // P = k * Q with Q = 3 * G: every kangaroo sits on a multiple of Q
+        let base = ProjectivePoint::mul_by_generator(&Scalar::from(3u64));
+        let mut start = [0u8; 32];
+        start[2] = 1;
+        let start_scalar = le_bytes_to_scalar(&start);
+        let pubkey = base * Scalar::from(0x1_2345u64);
+        let range = KeyRange::from_bits(start, 16);
+
+        let layout = HerdLayout::with_base(vec![pubkey], range, false, base);
+        assert_eq!(layout.base(), base);
+        for k in layout.herd(16, DEFAULT_SEED) {
+            let dist = signed_limbs_to_scalar(&k.dist);
+            let expected = if k.ktype == KTYPE_TAME {
+                base * (start_scalar + dist)
+            } else {
+                pubkey + base * dist
+            };
+            assert_eq!(affine_to_gpu(&expected.to_affine()).x, k.x);
+        }
+
+        let layout = HerdLayout::for_search(vec![pubkey], range, false, Herds::Four, base).unwrap();
+        let mid = le_bytes_to_scalar(&range.midpoint());
+        for k in layout.herd(16, DEFAULT_SEED) {
+            let dist = signed_limbs_to_scalar(&k.dist);
+            let origin = match k.ktype {
+                KTYPE_TAME => ProjectivePoint::IDENTITY,
+                KTYPE_WILD => pubkey - base * mid,
+                _ => base * mid - pubkey,
+            };
+            assert_eq!(affine_to_gpu(&(origin + base * dist).to_affine()).x, k.x);
+        }
+    }
+
+    #[test]
+    fn test_seed_replays_the_herd() {
+        let range = KeyRange::from_bits([0u8; 32], 40);
+        let layout = HerdLayout::new(vec![ProjectivePoint::GENERATOR], range, false);
+        let seed = random_seed();
+        let positions =
+            |seed| -> Vec<[u32; 8]> { layout.herd(16, seed).iter().map(|k| k.dist).collect() };
+
+        assert_eq!(positions(seed), positions(seed));
+        assert_ne!(positions(seed), positions(seed ^ 1));
+    }
+
+    #[test]
+    fn test_grid_slices_do_not_share_cells() {
+        let range = KeyRange::from_bits([0u8; 32], 20);
+        let layout = HerdLayout::new(vec![ProjectivePoint::GENERATOR], range, false);
+        let middle = layout.range_middle();
+        // (ktype, grid cell) of each kangaroo, with 32 cells over the range
+        let cells = |herd: Vec<GpuKangaroo>| -> HashSet<(u32, u64)> {
+            herd.iter()
+                .map(|k| {
+                    let dist = K256U256::from_be_slice(&limbs_to_be_bytes(&k.dist));
+                    let offset = if k.ktype == KTYPE_TAME {
+                        dist
+                    } else {
+                        dist.wrapping_add(&middle)
+                    };
+                    (k.ktype, offset.shr_vartime(15).as_words()[0])
+                })
+                .collect()
+        };
+
+        // Different seeds alone only move the jitter
+        let (a, b) = (DEFAULT_SEED, DEFAULT_SEED ^ (1 << 32));
+        assert_eq!(cells(layout.herd(16, a)), cells(layout.herd(16, b)));
+
+        let first = cells(layout.herd_in(
+            16,
+            a,
+            Some(GridSlice {
+                first: 0,
+                total: 32,
+            }),
+        ));
+        let second = cells(layout.herd_in(
+            16,
+            b,
+            Some(GridSlice {
+                first: 16,
+                total: 32,
+            }),
+        ));
+        assert_eq!((first.len(), second.len()), (16, 16));
+        assert!(first.is_disjoint(&second));
+        assert!(first.iter().all(|&(_, cell)| cell < 16));
+        assert!(second.iter().all(|&(_, cell)| (16..32).contains(&cell)));
+    }
+
+    #[test]
+    fn test_exact_interval_placement_and_jumps() {
+        // 0x3000 keys: not a power of two
+        let mut start = [0u8; 32];
+        start[1] = 0x10;
+        let mut end = [0u8; 32];
+        end[..2].copy_from_slice(&[0xFF, 0x3F]);
+        let range = KeyRange::new(start, end).unwrap();
+        let layout = HerdLayout::new(vec![ProjectivePoint::GENERATOR], range, false);
+        assert_eq!(layout.range_size(), K256U256::from_u32(0x3000));
+
+        for k in layout
+            .herd(64, DEFAULT_SEED)
+            .iter()
+            .filter(|k| k.ktype == 0)
+        {
+            assert!(k.dist[0] < 0x3000 && k.dist[1..] == [0; 7]);
+        }
+        for _ in 0..100 {
+            assert!(layout.random_offset() < layout.range_size());
+        }
+
+        // sqrt(0x3000) = 110: every jump is in [1, 110)
+        let (_, distances) = generate_jump_table(&range, &JumpTableSpec::default()).unwrap();
+        assert!(distances.iter().all(|d| (1..110).contains(&d[0])));
+        let mean = distances.iter().map(|d| d[0] as f64).sum::<f64>() / distances.len() as f64;
+        assert!((40.0..70.0).contains(&mean), "mean jump {}", mean);
+    }
+}
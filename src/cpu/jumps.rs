@@ -0,0 +1,392 @@
+//! Jump table generation and jump selection
+//!
+//! The same spec and `jump_index` are used by the GPU kernel and the CPU walker,
+//! so every device of a search walks the same pseudo-random graph.
+
+use crate::convert::{affine_to_gpu, scalar_be_to_limbs};
+use crate::crypto::{mul_base, Point};
+use crate::gpu::GpuAffinePoint;
+use crate::range::KeyRange;
+use anyhow::{bail, Result};
+use clap::ValueEnum;
+use k256::elliptic_curve::bigint::Encoding;
+use k256::elliptic_curve::ops::Reduce;
+use k256::U256 as K256U256;
+use k256::{ProjectivePoint, Scalar};
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+
+pub const MIN_JUMP_TABLE_SIZE: u32 = 16;
+pub const MAX_JUMP_TABLE_SIZE: u32 = 4096;
+
+/// Largest `JumpMean::Bits`, so `2 * mean` still fits in 256 bits
+const MAX_MEAN_BITS: u32 = 254;
+
+/// Target mean jump distance for a range of `N` keys
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum JumpMean {
+    /// √N / 2, optimal for one tame and one wild kangaroo
+    Sqrt,
+    /// m·√N / 4 for `m` kangaroos in total (capped at N / 4)
+    Herd(u64),
+    /// 2^bits
+    Bits(u32),
+}
+
+impl std::str::FromStr for JumpMean {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.trim() {
+            "sqrt" => Ok(JumpMean::Sqrt),
+            bits => bits
+                .parse()
+                .map(JumpMean::Bits)
+                .map_err(|_| format!("'{}' is neither 'sqrt' nor a bit count", s)),
+        }
+    }
+}
+
+/// How jump distances are spread around the mean
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum JumpDistribution {
+    /// Powers of two 2^0 .. 2^(k-1), k picked to hit the mean (Pollard)
+    PowersOfTwo,
+    /// Uniform in [1, 2 * mean)
+    Random,
+    /// Geometric progression 1, r, r^2, ... with the ratio picked to hit the mean
+    Geometric,
+}
+
+/// Everything that determines a jump table besides the range
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct JumpTableSpec {
+    pub size: u32,
+    pub mean: JumpMean,
+    pub distribution: JumpDistribution,
+    /// Seeds the random draws and the order of the entries
+    pub seed: u64,
+}
+
+impl Default for JumpTableSpec {
+    fn default() -> Self {
+        Self {
+            size: 256,
+            mean: JumpMean::Sqrt,
+            distribution: JumpDistribution::Random,
+            seed: 0,
+        }
+    }
+}
+
+impl JumpTableSpec {
+    pub fn validate(&self) -> Result<()> {
+        if !(MIN_JUMP_TABLE_SIZE..=MAX_JUMP_TABLE_SIZE).contains(&self.size) {
+            bail!(
+                "Jump table size {} is outside {}..={}",
+                self.size,
+                MIN_JUMP_TABLE_SIZE,
+                MAX_JUMP_TABLE_SIZE
+            );
+        }
+        if let JumpMean::Bits(bits) = self.mean {
+            if bits > MAX_MEAN_BITS {
+                bail!("Mean jump of 2^{} exceeds 2^{}", bits, MAX_MEAN_BITS);
+            }
+        }
+        Ok(())
+    }
+
+    /// Target mean distance over `range`, at least 1
+    pub fn target_mean(&self, range: &KeyRange) -> K256U256 {
+        let sqrt_n = range.width().sqrt_vartime();
+        let mean = match self.mean {
+            JumpMean::Sqrt => sqrt_n.shr_vartime(1),
+            JumpMean::Herd(m) => {
+                // sqrt_n < 2^128, so the product fits
+                let mean = sqrt_n.wrapping_mul(&K256U256::from_u64(m)).shr_vartime(2);
+                mean.min(range.width().shr_vartime(2))
+            }
+            JumpMean::Bits(bits) => K256U256::ONE.shl_vartime(bits as usize),
+        };
+        mean.max(K256U256::ONE)
+    }
+}
+
+/// Generate the jump points and distances for `range`.
+///
+/// Distances are little-endian limbs, all in `[1, 2^255)`.
+pub fn generate_jump_table(
+    range: &KeyRange,
+    spec: &JumpTableSpec,
+) -> Result<(Vec<GpuAffinePoint>, Vec<[u32; 8]>)> {
+    generate_jump_table_with_base(range, spec, &ProjectivePoint::GENERATOR)
+}
+
+/// Jump table of a search for `k` with `P = k * base`: the same distances as
+/// `generate_jump_table`, with jump points `d * base`
+pub fn generate_jump_table_with_base(
+    range: &KeyRange,
+    spec: &JumpTableSpec,
+    base: &Point,
+) -> Result<(Vec<GpuAffinePoint>, Vec<[u32; 8]>)> {
+    spec.validate()?;
+    let size = spec.size as usize;
+    let mean = spec.target_mean(range);
+    let mut rng = StdRng::seed_from_u64(spec.seed);
+
+    let mut distances: Vec<K256U256> = match spec.distribution {
+        JumpDistribution::Random => {
+            let spread = mean.shl_vartime(1).wrapping_sub(&K256U256::ONE);
+            (0..size)
+                .map(|_| {
+                    K256U256::from_le_slice(&rng.gen::<[u8; 32]>())
+                        .wrapping_rem(&spread)
+                        .wrapping_add(&K256U256::ONE)
+                })
+                .collect()
+        }
+        JumpDistribution::PowersOfTwo => {
+            let target = uint_to_f64(&mean);
+            // Smallest k whose spread of exponents reaches the mean
+            let k = (1..=MAX_MEAN_BITS)
+                .find(|&k| power_of_two_mean(k, size) >= target)
+                .unwrap_or(MAX_MEAN_BITS);
+            (0..size)
+                .map(|i| K256U256::ONE.shl_vartime(power_of_two_exponent(i, k, size)))
+                .collect()
+        }
+        JumpDistribution::Geometric => {
+            let ratio = geometric_ratio(uint_to_f64(&mean), size);
+            (0..size)
+                .map(|i| uint_from_f64(ratio.powi(i as i32).round()).max(K256U256::ONE))
+                .collect()
+        }
+    };
+    // Entry order carries no meaning, but keep it from following the index hash
+    distances.shuffle(&mut rng);
+
+    let (points, distances) = distances
+        .iter()
+        .map(|distance| {
+            let point = mul_base(base, &Scalar::reduce(*distance));
+            (
+                affine_to_gpu(&point.to_affine()),
+                scalar_be_to_limbs(&distance.to_be_bytes()),
+            )
+        })
+        .unzip();
+    Ok((points, distances))
+}
+
+/// Jump taken from a point whose x has top limb `x_top`.
+///
+/// Hashes the top 32 bits of x, which the DP test never looks at (it masks the
+/// low bits), so distinguished points do not all take the same jump. Mirrors
+/// `jump_index` in `kangaroo_affine.wgsl`.
+pub fn jump_index(x_top: u32, table_size: u32) -> u32 {
+    let h = x_top.wrapping_mul(0x9E37_79B1);
+    (h ^ (h >> 16)) % table_size
+}
+
+/// Summary of a table's distances, as printed by `kangaroo jumptable --stats`
+#[derive(Clone, Debug, PartialEq)]
+pub struct JumpStats {
+    pub mean: f64,
+    pub min: K256U256,
+    pub max: K256U256,
+    /// (bit length, entries), ascending
+    pub histogram: Vec<(u32, usize)>,
+}
+
+impl JumpStats {
+    pub fn of(distances: &[[u32; 8]]) -> Self {
+        let values: Vec<K256U256> = distances.iter().map(limbs_to_uint).collect();
+        let mean = values.iter().map(uint_to_f64).sum::<f64>() / values.len().max(1) as f64;
+        let mut histogram = std::collections::BTreeMap::new();
+        for value in &values {
+            *histogram.entry(value.bits() as u32).or_insert(0) += 1;
+        }
+        Self {
+            mean,
+            min: values.iter().copied().min().unwrap_or(K256U256::ZERO),
+            max: values.iter().copied().max().unwrap_or(K256U256::ZERO),
+            histogram: histogram.into_iter().collect(),
+        }
+    }
+}
+
+/// Exponent of entry `i` when `size` entries spread evenly over 2^0 .. 2^(k-1)
+fn power_of_two_exponent(i: usize, k: u32, size: usize) -> usize {
+    i * k as usize / size
+}
+
+fn power_of_two_mean(k: u32, size: usize) -> f64 {
+    (0..size)
+        .map(|i| 2f64.powi(power_of_two_exponent(i, k, size) as i32))
+        .sum::<f64>()
+        / size as f64
+}
+
+/// Ratio r with mean(1, r, ..., r^(size-1)) = `mean`, by bisection
+fn geometric_ratio(mean: f64, size: usize) -> f64 {
+    let mean_of = |r: f64| (0..size).map(|i| r.powi(i as i32)).sum::<f64>() / size as f64;
+    // r^(size-1) / size <= mean bounds r from above
+    let (mut lo, mut hi) = (1.0f64, (mean * size as f64).powf(1.0 / (size - 1) as f64));
+    for _ in 0..100 {
+        let mid = (lo + hi) / 2.0;
+        if mean_of(mid) < mean {
+            lo = mid;
+        } else {
+            hi = mid;
+        }
+    }
+    lo
+}
+
+fn limbs_to_uint(limbs: &[u32; 8]) -> K256U256 {
+    K256U256::from_le_slice(bytemuck::cast_slice(limbs))
+}
+
+fn uint_to_f64(value: &K256U256) -> f64 {
+    value
+        .to_be_bytes()
+        .iter()
+        .fold(0.0, |acc, &b| acc * 256.0 + f64::from(b))
+}
+
+/// Truncating conversion of a finite `value >= 0`
+fn uint_from_f64(value: f64) -> K256U256 {
+    if value < 2f64.powi(64) {
+        return K256U256::from_u64(value as u64);
+    }
+    let shift = value.log2().floor() as i32 - 63;
+    K256U256::from_u64((value / 2f64.powi(shift)) as u64).shl_vartime(shift as usize)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn range(bits: u32) -> KeyRange {
+        KeyRange::from_bits([0u8; 32], bits)
+    }
+
+    #[test]
+    fn test_distributions_hit_the_mean() {
+        for distribution in [
+            JumpDistribution::PowersOfTwo,
+            JumpDistribution::Random,
+            JumpDistribution::Geometric,
+        ] {
+            let spec = JumpTableSpec {
+                size: 512,
+                distribution,
+                ..JumpTableSpec::default()
+            };
+            // √(2^40) / 2 = 2^19
+            let (points, distances) = generate_jump_table(&range(40), &spec).unwrap();
+            assert_eq!((points.len(), distances.len()), (512, 512));
+
+            let stats = JumpStats::of(&distances);
+            let ratio = stats.mean / 2f64.powi(19);
+            assert!(
+                (0.5..2.0).contains(&ratio),
+                "{:?}: mean {}",
+                distribution,
+                ratio
+            );
+            assert!(stats.min >= K256U256::ONE);
+            assert_eq!(stats.histogram.iter().map(|&(_, n)| n).sum::<usize>(), 512);
+        }
+    }
+
+    #[test]
+    fn test_mean_policies() {
+        let spec = |mean| JumpTableSpec {
+            mean,
+            ..JumpTableSpec::default()
+        };
+        let r = range(40);
+        assert_eq!(
+            spec(JumpMean::Sqrt).target_mean(&r),
+            K256U256::from_u64(1 << 19)
+        );
+        assert_eq!(
+            spec(JumpMean::Herd(2)).target_mean(&r),
+            K256U256::from_u64(1 << 19)
+        );
+        assert_eq!(
+            spec(JumpMean::Herd(1 << 10)).target_mean(&r),
+            K256U256::from_u64(1 << 28)
+        );
+        // A herd as large as the range is capped at N / 4
+        assert_eq!(
+            spec(JumpMean::Herd(1 << 30)).target_mean(&r),
+            K256U256::from_u64(1 << 38)
+        );
+        assert_eq!(
+            spec(JumpMean::Bits(12)).target_mean(&r),
+            K256U256::from_u64(1 << 12)
+        );
+
+        assert_eq!("sqrt".parse(), Ok(JumpMean::Sqrt));
+        assert_eq!("24".parse(), Ok(JumpMean::Bits(24)));
+        assert!("auto".parse::<JumpMean>().is_err());
+    }
+
+    #[test]
+    fn test_spec_is_validated_and_deterministic() {
+        for size in [8, 5000] {
+            let spec = JumpTableSpec {
+                size,
+                ..JumpTableSpec::default()
+            };
+            assert!(generate_jump_table(&range(40), &spec).is_err());
+        }
+
+        let spec = JumpTableSpec {
+            size: 16,
+            ..JumpTableSpec::default()
+        };
+        let (_, a) = generate_jump_table(&range(40), &spec).unwrap();
+        let (_, b) = generate_jump_table(&range(40), &spec).unwrap();
+        let (_, c) = generate_jump_table(&range(40), &JumpTableSpec { seed: 1, ..spec }).unwrap();
+        assert_eq!(a, b);
+        assert_ne!(a, c);
+    }
+
+    #[test]
+    fn test_jump_points_are_multiples_of_the_base() {
+        use crate::convert::signed_limbs_to_scalar;
+        use k256::elliptic_curve::ops::MulByGenerator;
+
+        let spec = JumpTableSpec {
+            size: 16,
+            ..JumpTableSpec::default()
+        };
+        let base = ProjectivePoint::mul_by_generator(&Scalar::from(7u64));
+        let (_, distances) = generate_jump_table(&range(40), &spec).unwrap();
+        let (points, based) = generate_jump_table_with_base(&range(40), &spec, &base).unwrap();
+        assert_eq!(distances, based);
+        for (point, dist) in points.iter().zip(&distances) {
+            let expected = affine_to_gpu(&(base * signed_limbs_to_scalar(dist)).to_affine());
+            assert_eq!((point.x, point.y), (expected.x, expected.y));
+        }
+    }
+
+    #[test]
+    fn test_jump_index_ignores_dp_bits() {
+        // DPs share their low bits, yet still spread over the table
+        let mut used = [false; 64];
+        for top in 0..4096u32 {
+            let x_top = top.wrapping_mul(0x0101_0107);
+            let index = jump_index(x_top, 64);
+            assert!(index < 64);
+            used[index as usize] = true;
+        }
+        assert!(used.iter().all(|&u| u));
+        assert!((0..100u32).all(|x| jump_index(x, 300) < 300));
+    }
+}
@@ -1,8 +1,20 @@
 //! CPU-side utilities: collision detection and initialization
 
+pub mod bsgs;
 mod cpu_solver;
 mod dp_table;
+pub mod gaudry_schost;
+mod ingest;
 pub mod init;
+pub mod jumps;
+mod targets;
+mod walker;
+mod worker;
 
+pub use bsgs::BsgsSolver;
 pub use cpu_solver::CpuKangarooSolver;
-pub use dp_table::DPTable;
+pub use dp_table::{DPTable, Insertion};
+pub use ingest::{DpIngest, Hits};
+pub use targets::TargetSet;
+pub use walker::CpuWalker;
+pub use worker::{CpuWorker, CPU_HERD_SIZE};
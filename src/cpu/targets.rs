@@ -0,0 +1,186 @@
+//! Keys of a multi-target run and the relations found between them
+
+use crate::convert::{key_bytes_to_scalar, scalar_to_key_bytes};
+use crate::crypto::{mul_base, Point};
+use k256::{ProjectivePoint, Scalar};
+
+/// `k_a = m * k_b + c` for one of the candidates
+struct Relation {
+    a: usize,
+    b: usize,
+    candidates: Vec<(Scalar, Scalar)>,
+}
+
+/// Pubkeys solved together, the keys found so far and wild-wild relations.
+///
+/// A relation between two unsolved targets is kept until either side is
+/// solved, at which point the other follows without any further walking.
+pub struct TargetSet {
+    pubkeys: Vec<Point>,
+    /// Keys are `k` with `pubkey = k * base`
+    base: Point,
+    keys: Vec<Option<Scalar>>,
+    relations: Vec<Relation>,
+}
+
+impl TargetSet {
+    pub fn new(pubkeys: Vec<Point>) -> Self {
+        Self::with_base(pubkeys, ProjectivePoint::GENERATOR)
+    }
+
+    /// Targets of a search for `k` with `pubkey = k * base`
+    pub fn with_base(pubkeys: Vec<Point>, base: Point) -> Self {
+        let keys = vec![None; pubkeys.len()];
+        Self {
+            pubkeys,
+            base,
+            keys,
+            relations: Vec::new(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.pubkeys.len()
+    }
+
+    #[allow(dead_code)]
+    pub fn is_empty(&self) -> bool {
+        self.pubkeys.is_empty()
+    }
+
+    pub fn pubkeys(&self) -> &[Point] {
+        &self.pubkeys
+    }
+
+    /// Private key of `target` (big-endian, no leading zeros) once solved
+    pub fn key(&self, target: usize) -> Option<Vec<u8>> {
+        self.keys.get(target)?.as_ref().map(scalar_to_key_bytes)
+    }
+
+    pub fn solved(&self) -> usize {
+        self.keys.iter().filter(|k| k.is_some()).count()
+    }
+
+    pub fn all_solved(&self) -> bool {
+        self.keys.iter().all(Option::is_some)
+    }
+
+    /// Record a key found for `target`.
+    ///
+    /// Returns every target newly solved by it, directly or through relations.
+    /// Keys that do not match the pubkey are ignored.
+    pub fn solve(&mut self, target: usize, key: &[u8]) -> Vec<usize> {
+        if self.keys.get(target).is_none_or(Option::is_some) {
+            return Vec::new();
+        }
+        let key = key_bytes_to_scalar(key);
+        if mul_base(&self.base, &key) != self.pubkeys[target] {
+            tracing::warn!("Key for target {} does not match its pubkey", target);
+            return Vec::new();
+        }
+        self.keys[target] = Some(key);
+        let mut newly = vec![target];
+        newly.extend(self.propagate(vec![target]));
+        newly
+    }
+
+    /// Record a relation `k_a = m * k_b + c` (one of `candidates` holds).
+    ///
+    /// Returns every target newly solved by it.
+    pub fn relate(&mut self, a: usize, b: usize, candidates: Vec<(Scalar, Scalar)>) -> Vec<usize> {
+        if a == b || a >= self.len() || b >= self.len() {
+            return Vec::new();
+        }
+        self.relations.push(Relation { a, b, candidates });
+        let known: Vec<usize> = [a, b]
+            .into_iter()
+            .filter(|&t| self.keys[t].is_some())
+            .collect();
+        if known.len() == 1 {
+            self.propagate(known)
+        } else {
+            Vec::new()
+        }
+    }
+
+    /// Follow relations out of freshly solved targets; returns the targets reached
+    fn propagate(&mut self, mut pending: Vec<usize>) -> Vec<usize> {
+        let mut newly = Vec::new();
+        while let Some(solved) = pending.pop() {
+            let k = self.keys[solved].expect("propagating from an unsolved target");
+            for rel in &self.relations {
+                let (other, derived): (usize, Vec<Scalar>) = if rel.b == solved {
+                    (
+                        rel.a,
+                        rel.candidates.iter().map(|(m, c)| m * &k + c).collect(),
+                    )
+                } else if rel.a == solved {
+                    // m is +-1, its own inverse
+                    (
+                        rel.b,
+                        rel.candidates.iter().map(|(m, c)| m * &(k - c)).collect(),
+                    )
+                } else {
+                    continue;
+                };
+                if self.keys[other].is_some() {
+                    continue;
+                }
+                let pubkey = self.pubkeys[other];
+                if let Some(key) = derived
+                    .into_iter()
+                    .find(|key| mul_base(&self.base, key) == pubkey)
+                {
+                    self.keys[other] = Some(key);
+                    newly.push(other);
+                    pending.push(other);
+                }
+            }
+        }
+        newly
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use k256::elliptic_curve::ops::MulByGenerator;
+
+    fn pubkey(k: u64) -> Point {
+        ProjectivePoint::mul_by_generator(&Scalar::from(k))
+    }
+
+    #[test]
+    fn test_relations_chain_to_unsolved_targets() {
+        let mut targets = TargetSet::new(vec![pubkey(1000), pubkey(1300), pubkey(900)]);
+
+        // k_1 = k_0 + 300, k_2 = -k_1 + 2200 (second candidate is a decoy)
+        assert!(targets
+            .relate(1, 0, vec![(Scalar::ONE, Scalar::from(300u64))])
+            .is_empty());
+        let neg = -Scalar::ONE;
+        let decoy = (Scalar::ONE, Scalar::from(7u64));
+        assert!(targets
+            .relate(2, 1, vec![decoy, (neg, Scalar::from(2200u64))])
+            .is_empty());
+
+        assert!(targets.solve(0, &[0x03, 0xe9]).is_empty()); // wrong key
+        assert_eq!(targets.solve(0, &[0x03, 0xe8]), vec![0, 1, 2]);
+        assert!(targets.all_solved());
+        assert_eq!(targets.key(1), Some(vec![0x05, 0x14]));
+        assert_eq!(targets.key(2), Some(vec![0x03, 0x84]));
+    }
+
+    #[test]
+    fn test_relation_to_solved_target_solves_immediately() {
+        let mut targets = TargetSet::new(vec![pubkey(50), pubkey(80)]);
+        assert_eq!(targets.solve(1, &[80]), vec![1]);
+        // k_1 = k_0 + 30, solved from the b side
+        assert_eq!(
+            targets.relate(1, 0, vec![(Scalar::ONE, Scalar::from(30u64))]),
+            vec![0]
+        );
+        assert_eq!(targets.key(0), Some(vec![50]));
+        assert_eq!(targets.solved(), 2);
+    }
+}
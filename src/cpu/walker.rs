@@ -0,0 +1,345 @@
+//! CPU port of the GPU kangaroo kernel
+//!
+//! Steps herds in the `GpuKangaroo` layout with the same jump table and rules as
+//! `kangaroo_affine.wgsl` (jump index, DP test, negation map, stuck detection),
+//! so CPU and GPU kangaroos can feed one `DPTable`.
+
+use crate::convert::{be_bytes_to_limbs, limbs_to_be_bytes};
+use crate::cpu::jumps::{generate_jump_table_with_base, jump_index, JumpTableSpec};
+use crate::crypto::Point;
+use crate::gpu::{
+    GpuDistinguishedPoint, GpuKangaroo, DP_KIND_POINT, DP_KIND_STUCK, KANGAROO_PARKED,
+};
+use crate::math::{create_dp_mask, is_distinguished};
+use crate::range::KeyRange;
+use anyhow::Result;
+use k256::{FieldBytes, FieldElement, ProjectivePoint};
+
+/// Jump table and kernel settings shared by all CPU workers of a search
+pub struct CpuWalker {
+    jump_points: Vec<(FieldElement, FieldElement)>,
+    jump_distances: Vec<[u32; 8]>,
+    jump_table_size: u32,
+    dp_mask: [u32; 8],
+    negation: bool,
+    stuck_steps: u32,
+}
+
+impl CpuWalker {
+    /// `stuck_steps` = 0 disables cycle and stuck detection, as in the kernel
+    #[allow(dead_code)]
+    pub fn new(
+        range: &KeyRange,
+        jumps: &JumpTableSpec,
+        dp_bits: u32,
+        negation: bool,
+        stuck_steps: u32,
+    ) -> Result<Self> {
+        Self::with_base(
+            range,
+            jumps,
+            &ProjectivePoint::GENERATOR,
+            dp_bits,
+            negation,
+            stuck_steps,
+        )
+    }
+
+    /// Walker whose jumps are multiples of `base`, for keys with `P = k * base`
+    pub fn with_base(
+        range: &KeyRange,
+        jumps: &JumpTableSpec,
+        base: &Point,
+        dp_bits: u32,
+        negation: bool,
+        stuck_steps: u32,
+    ) -> Result<Self> {
+        let (points, jump_distances) = generate_jump_table_with_base(range, jumps, base)?;
+        let jump_points = points
+            .iter()
+            .map(|p| (limbs_to_field(&p.x), limbs_to_field(&p.y)))
+            .collect();
+        Ok(Self {
+            jump_points,
+            jump_distances,
+            jump_table_size: jumps.size,
+            dp_mask: create_dp_mask(dp_bits),
+            negation,
+            stuck_steps,
+        })
+    }
+
+    /// Advance every active kangaroo of `herd` by `steps` jumps.
+    ///
+    /// Returns the DPs and stuck reports found on the way; `kangaroo_id` is
+    /// `first_id` plus the index in `herd`. There is no buffer to fill, so
+    /// unlike the kernel a kangaroo never waits on a DP.
+    pub fn walk(
+        &self,
+        herd: &mut [GpuKangaroo],
+        steps: u32,
+        first_id: u32,
+    ) -> Vec<GpuDistinguishedPoint> {
+        let mut dps = Vec::new();
+        let mut active: Vec<usize> = (0..herd.len())
+            .filter(|&i| herd[i].is_active == 1)
+            .collect();
+        let mut px: Vec<FieldElement> =
+            active.iter().map(|&i| limbs_to_field(&herd[i].x)).collect();
+        let mut py: Vec<FieldElement> =
+            active.iter().map(|&i| limbs_to_field(&herd[i].y)).collect();
+        let mut jumps = vec![0usize; active.len()];
+        let mut dx = vec![FieldElement::ONE; active.len()];
+
+        for _ in 0..steps {
+            if active.is_empty() {
+                break;
+            }
+
+            // Jump selection and dx = x_jump - x_point for the batch inversion
+            for (n, &i) in active.iter().enumerate() {
+                let size = self.jump_table_size;
+                let mut jump_idx = jump_index(x_top(&px[n]), size) as usize;
+                if self.negation && jump_idx as u32 == herd[i].last_jump {
+                    jump_idx = (jump_idx + 1) % size as usize;
+                }
+                jumps[n] = jump_idx;
+                dx[n] = (self.jump_points[jump_idx].0 - px[n]).normalize();
+            }
+            let dx_inv = batch_invert(&dx);
+
+            let mut parked = Vec::new();
+            for (n, &i) in active.iter().enumerate() {
+                let k = &mut herd[i];
+                let id = first_id + i as u32;
+
+                // DP test on the position before the jump
+                let x_limbs = field_to_limbs(&px[n]);
+                if is_distinguished(&x_limbs, &self.dp_mask) {
+                    k.steps_since_dp = 0;
+                    k.x = x_limbs;
+                    dps.push(dp_record(k, id, DP_KIND_POINT));
+                }
+
+                // dx = 0 (point equals jump point) is skipped, as in the kernel
+                if let Some(inv) = dx_inv[n] {
+                    let (jx, jy) = &self.jump_points[jumps[n]];
+                    let lambda = (*jy - py[n]) * inv;
+                    let x3 = (lambda.square() - px[n] - jx).normalize();
+                    let y3 = (lambda * (px[n] - x3) - py[n]).normalize();
+                    px[n] = x3;
+                    py[n] = y3;
+                    k.dist = add_limbs(&k.dist, &self.jump_distances[jumps[n]]);
+
+                    // Keep the class representative with even y
+                    if self.negation {
+                        k.last_jump = jumps[n] as u32;
+                        if bool::from(py[n].is_odd()) {
+                            py[n] = (-py[n]).normalize();
+                            k.dist = negate_limbs(&k.dist);
+                        }
+                    }
+                }
+
+                if self.stuck_steps != 0 {
+                    k.steps_since_dp += 1;
+
+                    // Brent cycle check, see the kernel
+                    k.cycle_steps = k.cycle_steps.wrapping_add(1);
+                    let x_limbs = field_to_limbs(&px[n]);
+                    let cycled = x_limbs[0] == k.cycle_x[0] && x_limbs[1] == k.cycle_x[1];
+                    if k.cycle_steps & k.cycle_steps.wrapping_sub(1) == 0 {
+                        k.cycle_x = [x_limbs[0], x_limbs[1]];
+                    }
+
+                    if cycled || k.steps_since_dp >= self.stuck_steps {
+                        k.x = x_limbs;
+                        k.y = field_to_limbs(&py[n]);
+                        k.is_active = KANGAROO_PARKED;
+                        dps.push(dp_record(k, id, DP_KIND_STUCK));
+                        parked.push(n);
+                    }
+                }
+            }
+
+            // Parked kangaroos stay where they stopped until they are reseeded
+            for &n in parked.iter().rev() {
+                active.remove(n);
+                px.remove(n);
+                py.remove(n);
+                jumps.remove(n);
+                dx.remove(n);
+            }
+        }
+
+        for (n, &i) in active.iter().enumerate() {
+            herd[i].x = field_to_limbs(&px[n]);
+            herd[i].y = field_to_limbs(&py[n]);
+        }
+        dps
+    }
+}
+
+fn dp_record(k: &GpuKangaroo, kangaroo_id: u32, kind: u32) -> GpuDistinguishedPoint {
+    GpuDistinguishedPoint {
+        x: k.x,
+        dist: k.dist,
+        ktype: k.ktype,
+        kangaroo_id,
+        kind,
+        target: k.target,
+        _padding: [0; 4],
+    }
+}
+
+/// Montgomery batch inversion; `None` for zero inputs (the others are unaffected)
+fn batch_invert(values: &[FieldElement]) -> Vec<Option<FieldElement>> {
+    let mut prefix = Vec::with_capacity(values.len());
+    let mut acc = FieldElement::ONE;
+    for v in values {
+        prefix.push(acc);
+        if !bool::from(v.normalizes_to_zero()) {
+            acc *= v;
+        }
+    }
+
+    let mut inv = acc.invert().unwrap();
+    let mut out = vec![None; values.len()];
+    for (n, v) in values.iter().enumerate().rev() {
+        if bool::from(v.normalizes_to_zero()) {
+            continue;
+        }
+        out[n] = Some(inv * prefix[n]);
+        inv *= v;
+    }
+    out
+}
+
+fn limbs_to_field(limbs: &[u32; 8]) -> FieldElement {
+    FieldElement::from_bytes(&FieldBytes::from(limbs_to_be_bytes(limbs))).unwrap()
+}
+
+fn field_to_limbs(value: &FieldElement) -> [u32; 8] {
+    be_bytes_to_limbs(&value.to_bytes().into())
+}
+
+/// Low limb of a normalized field element (`px[0]` in the kernel)
+/// Top 32 bits of a field element, limb 7 in the kernel
+fn x_top(value: &FieldElement) -> u32 {
+    let bytes = value.to_bytes();
+    u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+}
+
+/// `a + b mod 2^256`, like `scalar_add_256` in the kernel
+fn add_limbs(a: &[u32; 8], b: &[u32; 8]) -> [u32; 8] {
+    let mut out = [0u32; 8];
+    let mut carry = 0u64;
+    for i in 0..8 {
+        let sum = u64::from(a[i]) + u64::from(b[i]) + carry;
+        out[i] = sum as u32;
+        carry = sum >> 32;
+    }
+    out
+}
+
+/// `-a mod 2^256`, like `scalar_neg_256` in the kernel
+fn negate_limbs(a: &[u32; 8]) -> [u32; 8] {
+    add_limbs(&a.map(|limb| !limb), &[1, 0, 0, 0, 0, 0, 0, 0])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::convert::{affine_to_gpu, signed_limbs_to_scalar};
+    use crate::cpu::init::HerdLayout;
+    use crate::cpu::jumps::JumpDistribution;
+    use k256::elliptic_curve::ops::MulByGenerator;
+    use k256::{ProjectivePoint, Scalar};
+
+    #[test]
+    fn test_walk_keeps_points_on_their_distances() {
+        let mut start = [0u8; 32];
+        start[2] = 1;
+        let pubkey = ProjectivePoint::mul_by_generator(&Scalar::from(0x1_2345u64));
+        let range = KeyRange::from_bits(start, 20);
+        let small = JumpTableSpec {
+            size: 17,
+            distribution: JumpDistribution::PowersOfTwo,
+            ..JumpTableSpec::default()
+        };
+        for jumps in [JumpTableSpec::default(), small] {
+            let walker = CpuWalker::new(&range, &jumps, 30, false, 0).unwrap();
+            let layout = HerdLayout::new(vec![pubkey], range, false);
+            let mut herd = layout.herd(8, 1);
+
+            walker.walk(&mut herd, 50, 0);
+
+            let start_scalar = Scalar::from(0x1_0000u64);
+            for k in herd.iter().filter(|k| k.ktype == 0) {
+                let dist = signed_limbs_to_scalar(&k.dist);
+                let expected = ProjectivePoint::mul_by_generator(&(start_scalar + dist));
+                assert_eq!(affine_to_gpu(&expected.to_affine()).x, k.x);
+            }
+            for k in herd.iter().filter(|k| k.ktype == 1) {
+                let dist = signed_limbs_to_scalar(&k.dist);
+                let expected = pubkey + ProjectivePoint::mul_by_generator(&dist);
+                assert_eq!(affine_to_gpu(&expected.to_affine()).x, k.x);
+            }
+        }
+    }
+
+    #[test]
+    fn test_negation_walk_stays_canonical() {
+        let range = KeyRange::from_bits([0u8; 32], 20);
+        let walker = CpuWalker::new(&range, &JumpTableSpec::default(), 30, true, 0).unwrap();
+        let layout = HerdLayout::new(
+            vec![ProjectivePoint::mul_by_generator(&Scalar::from(7u64))],
+            range,
+            true,
+        );
+        let mut herd = layout.herd(4, 1);
+        let tame = herd[0];
+
+        walker.walk(&mut herd, 50, 0);
+
+        let k = &herd[0];
+        assert_eq!(k.y[0] & 1, 0);
+        assert_ne!(k.last_jump, tame.last_jump);
+        let step = ProjectivePoint::mul_by_generator(&signed_limbs_to_scalar(&k.dist));
+        assert_eq!(affine_to_gpu(&step.to_affine()).x, k.x);
+    }
+
+    #[test]
+    fn test_dps_and_stuck_reports() {
+        let range = KeyRange::from_bits([0u8; 32], 20);
+        let layout = HerdLayout::new(
+            vec![ProjectivePoint::mul_by_generator(&Scalar::from(7u64))],
+            range,
+            false,
+        );
+        let mut herd = layout.herd(4, 1);
+
+        // Every point is a DP with 0 bits
+        let dps = CpuWalker::new(&range, &JumpTableSpec::default(), 0, false, 0)
+            .unwrap()
+            .walk(&mut herd, 3, 100);
+        assert_eq!(dps.len(), 12);
+        assert!(dps.iter().all(|dp| dp.kind == DP_KIND_POINT));
+        assert!(dps.iter().all(|dp| (100..104).contains(&dp.kangaroo_id)));
+
+        // No DPs in reach: every kangaroo parks after 5 steps and then idles
+        let dps = CpuWalker::new(&range, &JumpTableSpec::default(), 30, false, 5)
+            .unwrap()
+            .walk(&mut herd, 20, 0);
+        assert_eq!(dps.len(), 4);
+        assert!(dps.iter().all(|dp| dp.kind == DP_KIND_STUCK));
+        assert!(herd.iter().all(|k| k.is_active == KANGAROO_PARKED));
+    }
+
+    #[test]
+    fn test_negate_limbs() {
+        let one = [1, 0, 0, 0, 0, 0, 0, 0];
+        assert_eq!(negate_limbs(&one), [u32::MAX; 8]);
+        assert_eq!(add_limbs(&negate_limbs(&one), &one), [0; 8]);
+    }
+}
@@ -0,0 +1,214 @@
+//! CPU worker thread state for hybrid and CPU-only runs
+
+use crate::cpu::init::{GridSlice, HerdLayout};
+use crate::cpu::walker::CpuWalker;
+use crate::cpu::{DPTable, Insertion};
+use crate::gpu::{GpuKangaroo, DP_KIND_STUCK};
+use std::sync::Arc;
+
+/// Kangaroos per CPU worker; large enough to amortize the batch inversion
+pub const CPU_HERD_SIZE: u32 = 512;
+
+/// Jumps per kangaroo in one `CpuWorker::step`
+const CPU_STEPS_PER_CALL: u32 = 256;
+
+/// Kangaroo ids of CPU workers start here so they never clash with GPU herds
+/// (a GPU solver ignores respawn requests for ids outside its own herd)
+pub const CPU_KANGAROO_BASE: u32 = 1 << 31;
+
+/// One CPU thread's herd, walking the GPU jump table into a shared DP table
+pub struct CpuWorker {
+    walker: Arc<CpuWalker>,
+    layout: HerdLayout,
+    dp_table: Arc<DPTable>,
+    herd: Vec<GpuKangaroo>,
+    first_id: u32,
+    total_ops: u64,
+}
+
+impl CpuWorker {
+    /// Worker `index` of a search; `seed` picks its herd and `grid` the slice
+    /// of the start grid it shares with the search's other devices
+    pub fn new(
+        walker: Arc<CpuWalker>,
+        layout: HerdLayout,
+        dp_table: Arc<DPTable>,
+        index: u32,
+        seed: u64,
+        grid: Option<GridSlice>,
+    ) -> Self {
+        let herd = layout.herd_in(CPU_HERD_SIZE, seed, grid);
+        Self {
+            walker,
+            layout,
+            dp_table,
+            herd,
+            first_id: CPU_KANGAROO_BASE + index * CPU_HERD_SIZE,
+            total_ops: 0,
+        }
+    }
+
+    /// Walk the herd one batch and check its DPs; returns the key once found
+    pub fn step(&mut self) -> Option<Vec<u8>> {
+        let dps = self
+            .walker
+            .walk(&mut self.herd, CPU_STEPS_PER_CALL, self.first_id);
+        self.total_ops += u64::from(CPU_HERD_SIZE) * u64::from(CPU_STEPS_PER_CALL);
+
+        for dp in dps {
+            if dp.kind == DP_KIND_STUCK {
+                self.respawn(dp.kangaroo_id);
+                continue;
+            }
+            match self.dp_table.insert(dp) {
+                Insertion::Key { key, .. } => return Some(key),
+                Insertion::Merged { respawn } | Insertion::Relation { respawn, .. } => {
+                    self.respawn(respawn)
+                }
+                Insertion::Stored => {}
+            }
+        }
+        None
+    }
+
+    /// Get total operations performed
+    pub fn total_operations(&self) -> u64 {
+        self.total_ops
+    }
+
+    /// Restart one of this worker's kangaroos at a random offset in its herd
+    fn respawn(&mut self, kangaroo_id: u32) {
+        let Some(index) = kangaroo_id.checked_sub(self.first_id) else {
+            return;
+        };
+        let Some(k) = self.herd.get_mut(index as usize) else {
+            return;
+        };
+        let offset = self.layout.random_offset();
+        *k = self.layout.spawn(k.ktype, k.target, offset);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cpu::init::Herds;
+    use crate::cpu::jumps::JumpTableSpec;
+    use crate::crypto::{parse_pubkey, verify_key, verify_key_with_base};
+    use crate::range::KeyRange;
+    use k256::{ProjectivePoint, Scalar};
+
+    #[test]
+    fn test_cpu_workers_share_a_table() {
+        let pubkey =
+            parse_pubkey("02e963ffdfe34e63b68aeb42a5826e08af087660e0dac1c3e79f7625ca4e6ae482")
+                .unwrap();
+        let mut start = [0u8; 32];
+        start[2] = 1;
+        let range = KeyRange::from_bits(start, 15);
+        let walker =
+            Arc::new(CpuWalker::new(&range, &JumpTableSpec::default(), 4, false, 1 << 10).unwrap());
+        let layout = HerdLayout::new(vec![pubkey], range, false);
+        let table = Arc::new(DPTable::for_search(
+            vec![pubkey],
+            range,
+            false,
+            Herds::Vow,
+            ProjectivePoint::GENERATOR,
+        ));
+
+        let mut workers: Vec<CpuWorker> = (0..2)
+            .map(|i| {
+                let grid = GridSlice {
+                    first: u64::from(i * CPU_HERD_SIZE),
+                    total: u64::from(2 * CPU_HERD_SIZE),
+                };
+                CpuWorker::new(
+                    walker.clone(),
+                    layout.clone(),
+                    table.clone(),
+                    i,
+                    i.into(),
+                    Some(grid),
+                )
+            })
+            .collect();
+        let key = (0..200)
+            .find_map(|_| workers.iter_mut().find_map(CpuWorker::step))
+            .expect("key not found");
+
+        assert!(verify_key(&key, &pubkey));
+        assert!(workers[0].total_operations() > 0);
+    }
+
+    #[test]
+    fn test_mirrored_herds_solve_puzzle_20() {
+        // Puzzle 20: key 0xd2c55 in [0x80000, 0xfffff]
+        let pubkey =
+            parse_pubkey("033c4a45cbd643ff97d77f41ea37e843648d50fd894b864b0d52febc62f6454f7c")
+                .unwrap();
+        let mut start = [0u8; 32];
+        start[2] = 0x08;
+        let range = KeyRange::from_bits(start, 19);
+        let walker =
+            Arc::new(CpuWalker::new(&range, &JumpTableSpec::default(), 4, false, 1 << 10).unwrap());
+
+        for herds in [Herds::Three, Herds::Four] {
+            let generator = ProjectivePoint::GENERATOR;
+            let layout =
+                HerdLayout::for_search(vec![pubkey], range, false, herds, generator).unwrap();
+            let table = Arc::new(DPTable::for_search(
+                vec![pubkey],
+                range,
+                false,
+                herds,
+                generator,
+            ));
+            let mut worker = CpuWorker::new(walker.clone(), layout, table, 0, 7, None);
+            let key = (0..400)
+                .find_map(|_| worker.step())
+                .unwrap_or_else(|| panic!("{:?}: key not found", herds));
+            assert_eq!(key, vec![0x0d, 0x2c, 0x55], "{:?}", herds);
+        }
+    }
+
+    #[test]
+    fn test_relative_discrete_log() {
+        // P = 0x12345 * Q with Q = 0x1234 * G, searched in [0x10000, 0x18000)
+        let base = ProjectivePoint::GENERATOR * Scalar::from(0x1234u64);
+        let pubkey = base * Scalar::from(0x1_2345u64);
+        let mut start = [0u8; 32];
+        start[2] = 1;
+        let range = KeyRange::from_bits(start, 15);
+
+        for negation in [false, true] {
+            let walker = Arc::new(
+                CpuWalker::with_base(
+                    &range,
+                    &JumpTableSpec::default(),
+                    &base,
+                    4,
+                    negation,
+                    1 << 10,
+                )
+                .unwrap(),
+            );
+            let layout =
+                HerdLayout::for_search(vec![pubkey], range, negation, Herds::Vow, base).unwrap();
+            let table = Arc::new(DPTable::for_search(
+                vec![pubkey],
+                range,
+                negation,
+                Herds::Vow,
+                base,
+            ));
+            let mut worker = CpuWorker::new(walker, layout, table, 0, 3, None);
+            let key = (0..400)
+                .find_map(|_| worker.step())
+                .unwrap_or_else(|| panic!("negation {}: key not found", negation));
+            assert_eq!(key, vec![0x01, 0x23, 0x45]);
+            assert!(verify_key_with_base(&key, &pubkey, &base));
+            assert!(!verify_key(&key, &pubkey));
+        }
+    }
+}
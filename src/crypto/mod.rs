@@ -30,6 +30,22 @@ pub fn parse_pubkey(hex_str: &str) -> Result<Point> {
     }
 }
 
+/// Parse a list of pubkeys, one per line (blank lines and `#` comments are skipped)
+pub fn parse_pubkey_list(text: &str) -> Result<Vec<Point>> {
+    let mut pubkeys = Vec::new();
+    for (i, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        pubkeys.push(parse_pubkey(line).with_context(|| format!("line {}", i + 1))?);
+    }
+    if pubkeys.is_empty() {
+        anyhow::bail!("No pubkeys listed");
+    }
+    Ok(pubkeys)
+}
+
 /// Parse hex string to U256
 pub fn parse_hex_u256(hex_str: &str) -> Result<U256> {
     let hex_clean = hex_str.trim_start_matches("0x");
@@ -52,8 +68,22 @@ pub fn u256_to_le_bytes(val: &U256) -> [u8; 32] {
     *val
 }
 
+/// `k * base`, through the precomputed generator tables when `base` is G
+pub fn mul_base(base: &Point, k: &Scalar) -> Point {
+    if *base == ProjectivePoint::GENERATOR {
+        ProjectivePoint::mul_by_generator(k)
+    } else {
+        base * k
+    }
+}
+
 /// Verify that private key produces the public key
 pub fn verify_key(private_key: &[u8], public_key: &Point) -> bool {
+    verify_key_with_base(private_key, public_key, &ProjectivePoint::GENERATOR)
+}
+
+/// Verify that `public_key = private_key * base`
+pub fn verify_key_with_base(private_key: &[u8], public_key: &Point, base: &Point) -> bool {
     if private_key.is_empty() || private_key.len() > 32 {
         return false;
     }
@@ -68,8 +98,7 @@ pub fn verify_key(private_key: &[u8], public_key: &Point) -> bool {
         None => return false,
     };
 
-    let computed = ProjectivePoint::mul_by_generator(&scalar);
-    computed == *public_key
+    mul_base(base, &scalar) == *public_key
 }
 
 /// Compute compressed public key from private key bytes
@@ -95,7 +124,6 @@ pub fn privkey_to_pubkey(private_key: &[u8]) -> Result<Vec<u8>> {
 }
 
 /// Compute Hash160 (RIPEMD160(SHA256(data)))
-#[allow(dead_code)]
 pub fn compute_hash160(data: &[u8]) -> [u8; 20] {
     let hash = hash160::Hash::hash(data);
     let mut result = [0u8; 20];
@@ -112,7 +140,6 @@ pub fn pubkey_to_address(pubkey: &[u8]) -> String {
 }
 
 /// Compute Bitcoin address from Hash160
-#[allow(dead_code)]
 pub fn pubkey_hash_to_address(hash: &[u8; 20]) -> String {
     let pubkey_hash = PubkeyHash::from_slice(hash).expect("Invalid hash160");
     Address::p2pkh(pubkey_hash, Network::Bitcoin).to_string()
@@ -169,3 +196,20 @@ pub fn u256_to_u32_array(val: &U256) -> [u32; 8] {
     }
     result
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_pubkey_list() {
+        let g = "0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798";
+        let text = format!("# puzzle keys\n{g}\n\n  {g}  \n");
+        let pubkeys = parse_pubkey_list(&text).unwrap();
+        assert_eq!(pubkeys, vec![ProjectivePoint::GENERATOR; 2]);
+
+        let err = parse_pubkey_list(&format!("{g}\nnot-a-key\n")).unwrap_err();
+        assert!(format!("{err:#}").contains("line 2"));
+        assert!(parse_pubkey_list("# nothing\n").is_err());
+    }
+}
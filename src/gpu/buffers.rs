@@ -1,27 +1,47 @@
 //! GPU buffer management
 
 use super::{
-    GpuAffinePoint, GpuConfig, GpuContext, GpuDistinguishedPoint, GpuKangaroo, KangarooPipeline,
+    GpuAffinePoint, GpuConfig, GpuContext, GpuDistinguishedPoint, GpuDpCounters, GpuKangaroo,
+    KangarooPipeline,
 };
 use anyhow::Result;
 use wgpu::{BindGroup, Buffer, BufferUsages};
 
+/// Number of DP buffer sets, so one dispatch can run while the previous one is read
+pub const DP_SLOTS: usize = 2;
+
+/// Bytes of `GpuDpCounters` in front of the DPs in a staging buffer
+pub const DP_HEADER_SIZE: usize = std::mem::size_of::<GpuDpCounters>();
+
+/// DP output of one dispatch: the kernel's DP buffer and counters, the staging
+/// buffer they are copied to, the restart pool its kangaroos draw from and the
+/// bind group tying them together
+pub struct DpSlot {
+    pub dp_buffer: Buffer,
+    pub dp_count_buffer: Buffer,
+    pub staging_buffer: Buffer,
+    /// Fresh kangaroos handed out on DPs (Gaudry–Schost); refilled by the host
+    /// after each dispatch of this slot
+    pub restart_pool: Buffer,
+    pub bind_group: BindGroup,
+    /// DPs the buffer holds
+    pub capacity: u32,
+}
+
 /// GPU buffer collection
 pub struct GpuBuffers {
     pub config_buffer: Buffer,
-    #[allow(dead_code)]
     jump_points_buffer: Buffer,
-    #[allow(dead_code)]
     jump_distances_buffer: Buffer,
     pub kangaroos_buffer: Buffer,
-    pub dp_buffer: Buffer,
-    pub dp_count_buffer: Buffer,
-    pub staging_buffer: Buffer,
-    pub bind_group: BindGroup,
+    /// Readback of the herd (checkpoints, reseeding)
+    pub kangaroo_staging: Buffer,
+    pub slots: [DpSlot; DP_SLOTS],
 }
 
 impl GpuBuffers {
     /// Create GPU buffers
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         ctx: &GpuContext,
         pipeline: &KangarooPipeline,
@@ -30,6 +50,7 @@ impl GpuBuffers {
         jump_distances: &[[u32; 8]],
         num_kangaroos: u32,
         max_dps: u32,
+        restart_pool_size: u32,
     ) -> Result<Self> {
         // Config buffer (uniform)
         let config_buffer = ctx.create_buffer_init(
@@ -56,73 +77,141 @@ impl GpuBuffers {
             num_kangaroos as u64,
         );
 
-        // DP buffer
+        let kangaroo_staging = ctx.create_buffer::<GpuKangaroo>(
+            "Kangaroo Staging Buffer",
+            BufferUsages::MAP_READ | BufferUsages::COPY_DST,
+            num_kangaroos as u64,
+        );
+
+        let slots: [DpSlot; DP_SLOTS] = std::array::from_fn(|_| {
+            // Bindings cannot be empty, so a search without restarts gets one entry
+            let restart_pool = ctx.create_buffer::<GpuKangaroo>(
+                "Restart Pool Buffer",
+                BufferUsages::STORAGE | BufferUsages::COPY_DST,
+                u64::from(restart_pool_size.max(1)),
+            );
+            DpSlot::new(
+                ctx,
+                pipeline,
+                [
+                    &config_buffer,
+                    &jump_points_buffer,
+                    &jump_distances_buffer,
+                    &kangaroos_buffer,
+                ],
+                max_dps,
+                restart_pool,
+            )
+        });
+
+        Ok(Self {
+            config_buffer,
+            jump_points_buffer,
+            jump_distances_buffer,
+            kangaroos_buffer,
+            kangaroo_staging,
+            slots,
+        })
+    }
+
+    /// Replace a slot's DP buffers with ones holding `capacity` DPs (its
+    /// restart pool is kept). The slot must not be in use by a dispatch in flight.
+    pub fn resize_slot(
+        &mut self,
+        ctx: &GpuContext,
+        pipeline: &KangarooPipeline,
+        slot: usize,
+        capacity: u32,
+    ) {
+        self.slots[slot] = DpSlot::new(
+            ctx,
+            pipeline,
+            [
+                &self.config_buffer,
+                &self.jump_points_buffer,
+                &self.jump_distances_buffer,
+                &self.kangaroos_buffer,
+            ],
+            capacity,
+            self.slots[slot].restart_pool.clone(),
+        );
+    }
+
+    /// Put fresh kangaroos at the front of a slot's restart pool, replacing
+    /// the entries its last dispatch handed out
+    pub fn write_restart_pool(&self, ctx: &GpuContext, slot: usize, kangaroos: &[GpuKangaroo]) {
+        ctx.queue.write_buffer(
+            &self.slots[slot].restart_pool,
+            0,
+            bytemuck::cast_slice(kangaroos),
+        );
+    }
+}
+
+impl DpSlot {
+    /// `shared` holds the buffers of bindings 0-3
+    fn new(
+        ctx: &GpuContext,
+        pipeline: &KangarooPipeline,
+        shared: [&Buffer; 4],
+        capacity: u32,
+        restart_pool: Buffer,
+    ) -> Self {
+        // DP buffer; the kernel reads its capacity with arrayLength
         let dp_buffer = ctx.create_buffer::<GpuDistinguishedPoint>(
             "DP Buffer",
             BufferUsages::STORAGE | BufferUsages::COPY_SRC,
-            max_dps as u64,
+            capacity as u64,
         );
 
-        // DP count buffer (atomic u32)
+        // DP counters (atomic)
         let dp_count_buffer = ctx.create_buffer_init(
             "DP Count Buffer",
             BufferUsages::STORAGE | BufferUsages::COPY_SRC | BufferUsages::COPY_DST,
-            &[0u32],
+            &[GpuDpCounters::default()],
         );
 
-        // Staging buffer for readback
-        // Must be large enough to hold either kangaroos (for normalization) or DPs
-        let kangaroos_size = (num_kangaroos as usize) * std::mem::size_of::<GpuKangaroo>();
-        let dp_size = (max_dps as usize) * std::mem::size_of::<GpuDistinguishedPoint>();
-        let staging_size = std::cmp::max(kangaroos_size, dp_size) as u64 + 4;
-
+        // Staging buffer for readback: counters followed by the DPs
+        let dp_size = (capacity as usize) * std::mem::size_of::<GpuDistinguishedPoint>();
         let staging_buffer = ctx.create_buffer::<u8>(
-            "Staging Buffer",
+            "DP Staging Buffer",
             BufferUsages::MAP_READ | BufferUsages::COPY_DST,
-            staging_size,
+            (DP_HEADER_SIZE + dp_size) as u64,
         );
 
-        // Create bind group
+        let mut entries: Vec<wgpu::BindGroupEntry> = shared
+            .iter()
+            .enumerate()
+            .map(|(binding, buffer)| wgpu::BindGroupEntry {
+                binding: binding as u32,
+                resource: buffer.as_entire_binding(),
+            })
+            .collect();
+        entries.push(wgpu::BindGroupEntry {
+            binding: 4,
+            resource: dp_buffer.as_entire_binding(),
+        });
+        entries.push(wgpu::BindGroupEntry {
+            binding: 5,
+            resource: dp_count_buffer.as_entire_binding(),
+        });
+        entries.push(wgpu::BindGroupEntry {
+            binding: 6,
+            resource: restart_pool.as_entire_binding(),
+        });
         let bind_group = ctx.device.create_bind_group(&wgpu::BindGroupDescriptor {
             label: Some("Kangaroo Bind Group"),
             layout: &pipeline.bind_group_layout,
-            entries: &[
-                wgpu::BindGroupEntry {
-                    binding: 0,
-                    resource: config_buffer.as_entire_binding(),
-                },
-                wgpu::BindGroupEntry {
-                    binding: 1,
-                    resource: jump_points_buffer.as_entire_binding(),
-                },
-                wgpu::BindGroupEntry {
-                    binding: 2,
-                    resource: jump_distances_buffer.as_entire_binding(),
-                },
-                wgpu::BindGroupEntry {
-                    binding: 3,
-                    resource: kangaroos_buffer.as_entire_binding(),
-                },
-                wgpu::BindGroupEntry {
-                    binding: 4,
-                    resource: dp_buffer.as_entire_binding(),
-                },
-                wgpu::BindGroupEntry {
-                    binding: 5,
-                    resource: dp_count_buffer.as_entire_binding(),
-                },
-            ],
+            entries: &entries,
         });
 
-        Ok(Self {
-            config_buffer,
-            jump_points_buffer,
-            jump_distances_buffer,
-            kangaroos_buffer,
+        Self {
             dp_buffer,
             dp_count_buffer,
             staging_buffer,
+            restart_pool,
             bind_group,
-        })
+            capacity,
+        }
     }
 }
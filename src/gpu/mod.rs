@@ -2,10 +2,15 @@
 
 mod buffers;
 mod pipeline;
+mod scan;
 
 pub use crate::gpu_crypto::{GpuAffinePoint, GpuContext};
-pub use buffers::GpuBuffers;
+pub use buffers::{GpuBuffers, DP_HEADER_SIZE, DP_SLOTS};
 pub use pipeline::KangarooPipeline;
+pub use scan::{
+    GpuScanConfig, GpuScanHit, ScanBuffers, ScanPipeline, HIT_HEADER_SIZE, SCAN_BATCH,
+    SCAN_FORM_COMPRESSED, SCAN_FORM_UNCOMPRESSED, SCAN_HIT_CAPACITY,
+};
 
 use bytemuck::{Pod, Zeroable};
 
@@ -17,9 +22,40 @@ pub struct GpuConfig {
     pub num_kangaroos: u32,
     pub steps_per_call: u32,
     pub jump_table_size: u32,
-    pub _padding: u32,
+    /// `CONFIG_*` bits
+    pub flags: u32,
+    /// Park a kangaroo after this many steps without a DP (0 disables stuck and cycle checks)
+    pub stuck_steps: u32,
+    pub _padding: [u32; 3],
 }
 
+/// Walk on equivalence classes {P, -P} (canonical y, signed distances)
+pub const CONFIG_NEGATION_MAP: u32 = 1;
+/// Gaudry–Schost: after each DP, continue from the next entry of the restart pool
+pub const CONFIG_RESTART_ON_DP: u32 = 2;
+
+/// `GpuKangaroo::last_jump` before the first jump
+pub const NO_JUMP: u32 = u32::MAX;
+
+/// `GpuKangaroo::is_active` of a kangaroo the kernel stopped (cycling or no DPs for too long)
+pub const KANGAROO_PARKED: u32 = 2;
+
+/// `GpuKangaroo::ktype` of a tame kangaroo (started at a known multiple of G)
+pub const KTYPE_TAME: u32 = 0;
+/// `GpuKangaroo::ktype` of a wild kangaroo (started from the pubkey)
+pub const KTYPE_WILD: u32 = 1;
+/// `GpuKangaroo::ktype` of a wild kangaroo started from the negated pubkey
+/// (three- and four-kangaroo herds)
+pub const KTYPE_MIRRORED_WILD: u32 = 2;
+
+/// `GpuDistinguishedPoint::kind` of a real distinguished point
+pub const DP_KIND_POINT: u32 = 0;
+/// `GpuDistinguishedPoint::kind` of a report that `kangaroo_id` was parked and needs reseeding
+pub const DP_KIND_STUCK: u32 = 1;
+/// `GpuDistinguishedPoint::kind` of a real DP whose kangaroo found the restart
+/// pool empty and was parked until the host restarts it
+pub const DP_KIND_RESTART: u32 = 2;
+
 #[repr(C)]
 #[derive(Clone, Copy, Debug, Pod, Zeroable)]
 pub struct GpuKangaroo {
@@ -28,7 +64,15 @@ pub struct GpuKangaroo {
     pub dist: [u32; 8],
     pub ktype: u32,
     pub is_active: u32,
-    pub _padding: [u32; 6],
+    /// Jump index taken last (negation map only, used to break 2-cycles)
+    pub last_jump: u32,
+    /// Steps since the last DP (reset by the kernel on every DP)
+    pub steps_since_dp: u32,
+    /// Brent cycle detection: step counter and low x limbs saved at its last power of two
+    pub cycle_steps: u32,
+    pub cycle_x: [u32; 2],
+    /// Index of the pubkey a wild kangaroo walks from (0 for tames and single-target runs)
+    pub target: u32,
 }
 
 #[repr(C)]
@@ -38,5 +82,23 @@ pub struct GpuDistinguishedPoint {
     pub dist: [u32; 8],
     pub ktype: u32,
     pub kangaroo_id: u32,
-    pub _padding: [u32; 6],
+    /// `DP_KIND_*`
+    pub kind: u32,
+    /// `GpuKangaroo::target` of the kangaroo that found it
+    pub target: u32,
+    pub _padding: [u32; 4],
+}
+
+/// DP counters of one dispatch (binding 5), read back in front of the DPs
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, Pod, Zeroable)]
+pub struct GpuDpCounters {
+    /// DP stores attempted this dispatch; only the first `capacity` were written
+    pub count: u32,
+    /// Stores that found the buffer full; those kangaroos wait on their DP until
+    /// the next dispatch
+    pub overflow: u32,
+    /// Restart pool entries claimed this dispatch; only the first `pool size`
+    /// were handed out
+    pub restarts: u32,
 }
@@ -0,0 +1,203 @@
+//! Hash160 scan pipeline and buffers
+
+use super::{GpuAffinePoint, GpuContext};
+use anyhow::Result;
+use bytemuck::{Pod, Zeroable};
+use std::sync::Arc;
+use wgpu::{BindGroup, BindGroupLayout, Buffer, BufferUsages, ComputePipeline};
+
+/// Keys each thread steps through per batch (one inversion each)
+pub const SCAN_BATCH: u32 = 32;
+
+/// `GpuScanConfig::forms` / `GpuScanHit::form` bit of the 33-byte compressed key
+pub const SCAN_FORM_COMPRESSED: u32 = 1;
+/// `GpuScanConfig::forms` / `GpuScanHit::form` bit of the 65-byte uncompressed key
+pub const SCAN_FORM_UNCOMPRESSED: u32 = 2;
+
+/// Hits one dispatch can report; later ones are counted but dropped
+pub const SCAN_HIT_CAPACITY: u32 = 1024;
+
+/// Bytes in front of the hits in the staging buffer: the hit count, padded
+/// to the size of a hit
+pub const HIT_HEADER_SIZE: usize = std::mem::size_of::<GpuScanHit>();
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+pub struct GpuScanConfig {
+    pub num_threads: u32,
+    /// Batches of `SCAN_BATCH` keys per thread and dispatch
+    pub batches_per_call: u32,
+    /// `SCAN_FORM_*` bits
+    pub forms: u32,
+    pub num_targets: u32,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, Pod, Zeroable)]
+pub struct GpuScanHit {
+    pub thread: u32,
+    /// Keys past the thread's point at the start of the dispatch
+    pub offset: u32,
+    /// `SCAN_FORM_*` of the matching serialization
+    pub form: u32,
+    pub _padding: u32,
+}
+
+/// Hash160 scan compute pipeline
+pub struct ScanPipeline {
+    pub pipeline: Arc<ComputePipeline>,
+    pub bind_group_layout: Arc<BindGroupLayout>,
+}
+
+impl ScanPipeline {
+    pub fn new(ctx: &GpuContext) -> Result<Self> {
+        let field = crate::gpu_crypto::shaders::FIELD_WGSL;
+        let curve = crate::gpu_crypto::shaders::CURVE_WGSL;
+        let sha256 = crate::gpu_crypto::shaders::SHA256_WGSL;
+        let ripemd160 = crate::gpu_crypto::shaders::RIPEMD160_WGSL;
+        let scan = include_str!("../shaders/hash160_scan.wgsl");
+
+        let shader =
+            ctx.create_shader_module("Scan Shader", &[field, curve, sha256, ripemd160, scan]);
+
+        let storage = |binding: u32, read_only: bool| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::COMPUTE,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Storage { read_only },
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        };
+        let bind_group_layout =
+            ctx.device
+                .create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                    label: Some("Scan Bind Group Layout"),
+                    entries: &[
+                        // Config (uniform)
+                        wgpu::BindGroupLayoutEntry {
+                            binding: 0,
+                            visibility: wgpu::ShaderStages::COMPUTE,
+                            ty: wgpu::BindingType::Buffer {
+                                ty: wgpu::BufferBindingType::Uniform,
+                                has_dynamic_offset: false,
+                                min_binding_size: None,
+                            },
+                            count: None,
+                        },
+                        // Multiples of G
+                        storage(1, true),
+                        // Thread points
+                        storage(2, false),
+                        // Sorted target hashes
+                        storage(3, true),
+                        // Hits and hit count (atomic)
+                        storage(4, false),
+                        storage(5, false),
+                    ],
+                });
+
+        let pipeline_layout = ctx
+            .device
+            .create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("Scan Pipeline Layout"),
+                bind_group_layouts: &[&bind_group_layout],
+                immediate_size: 0,
+            });
+        let pipeline = ctx
+            .device
+            .create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                label: Some("Scan Compute Pipeline"),
+                layout: Some(&pipeline_layout),
+                module: &shader,
+                entry_point: Some("main"),
+                compilation_options: Default::default(),
+                cache: None,
+            });
+
+        Ok(Self {
+            pipeline: Arc::new(pipeline),
+            bind_group_layout: Arc::new(bind_group_layout),
+        })
+    }
+}
+
+/// Buffers of one scan; the thread points stay on the GPU between dispatches
+pub struct ScanBuffers {
+    pub config_buffer: Buffer,
+    pub hits_buffer: Buffer,
+    pub hit_count_buffer: Buffer,
+    /// Readback: hit count followed by the hits
+    pub staging_buffer: Buffer,
+    pub bind_group: BindGroup,
+}
+
+impl ScanBuffers {
+    /// `g_multiples` holds 1·G .. `SCAN_BATCH`·G; `targets` must be sorted and non-empty
+    pub fn new(
+        ctx: &GpuContext,
+        pipeline: &ScanPipeline,
+        config: &GpuScanConfig,
+        g_multiples: &[GpuAffinePoint],
+        points: &[GpuAffinePoint],
+        targets: &[[u32; 5]],
+    ) -> Result<Self> {
+        let config_buffer = ctx.create_buffer_init(
+            "Scan Config Buffer",
+            BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+            &[*config],
+        );
+        let g_multiples_buffer =
+            ctx.create_buffer_init("G Multiples Buffer", BufferUsages::STORAGE, g_multiples);
+        let points_buffer =
+            ctx.create_buffer_init("Scan Points Buffer", BufferUsages::STORAGE, points);
+        let targets_buffer =
+            ctx.create_buffer_init("Scan Targets Buffer", BufferUsages::STORAGE, targets);
+        let hits_buffer = ctx.create_buffer::<GpuScanHit>(
+            "Scan Hits Buffer",
+            BufferUsages::STORAGE | BufferUsages::COPY_SRC,
+            u64::from(SCAN_HIT_CAPACITY),
+        );
+        let hit_count_buffer = ctx.create_buffer_init(
+            "Scan Hit Count Buffer",
+            BufferUsages::STORAGE | BufferUsages::COPY_SRC | BufferUsages::COPY_DST,
+            &[0u32],
+        );
+        let staging_buffer = ctx.create_buffer::<u8>(
+            "Scan Staging Buffer",
+            BufferUsages::MAP_READ | BufferUsages::COPY_DST,
+            (HIT_HEADER_SIZE + SCAN_HIT_CAPACITY as usize * std::mem::size_of::<GpuScanHit>())
+                as u64,
+        );
+
+        let entries: Vec<wgpu::BindGroupEntry> = [
+            &config_buffer,
+            &g_multiples_buffer,
+            &points_buffer,
+            &targets_buffer,
+            &hits_buffer,
+            &hit_count_buffer,
+        ]
+        .iter()
+        .enumerate()
+        .map(|(binding, buffer)| wgpu::BindGroupEntry {
+            binding: binding as u32,
+            resource: buffer.as_entire_binding(),
+        })
+        .collect();
+        let bind_group = ctx.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Scan Bind Group"),
+            layout: &pipeline.bind_group_layout,
+            entries: &entries,
+        });
+
+        Ok(Self {
+            config_buffer,
+            hits_buffer,
+            hit_count_buffer,
+            staging_buffer,
+            bind_group,
+        })
+    }
+}
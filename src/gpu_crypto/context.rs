@@ -54,6 +54,60 @@ impl GpuBackend {
             GpuBackend::Gl => "OpenGL",
         }
     }
+
+    /// Backend a created context actually runs on
+    fn from_wgpu(backend: wgpu::Backend) -> Option<GpuBackend> {
+        match backend {
+            wgpu::Backend::Vulkan => Some(GpuBackend::Vulkan),
+            wgpu::Backend::Dx12 => Some(GpuBackend::Dx12),
+            wgpu::Backend::Metal => Some(GpuBackend::Metal),
+            wgpu::Backend::Gl => Some(GpuBackend::Gl),
+            _ => None,
+        }
+    }
+}
+
+/// Which GPUs to run on: a list of device indices (`0,1,3`) or `all`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GpuSelection {
+    All,
+    Devices(Vec<u32>),
+}
+
+impl GpuSelection {
+    /// Index of the first selected device
+    pub fn first(&self) -> u32 {
+        match self {
+            GpuSelection::All => 0,
+            GpuSelection::Devices(indices) => indices[0],
+        }
+    }
+
+    /// True if this can only ever mean one device
+    pub fn is_single(&self) -> bool {
+        matches!(self, GpuSelection::Devices(indices) if indices.len() == 1)
+    }
+}
+
+impl std::str::FromStr for GpuSelection {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        if s.trim().eq_ignore_ascii_case("all") {
+            return Ok(GpuSelection::All);
+        }
+        let mut indices = Vec::new();
+        for part in s.split(',') {
+            let index = part.trim().parse::<u32>().map_err(|_| {
+                format!("invalid GPU index '{}' (expected e.g. 0,1,3 or all)", part)
+            })?;
+            if indices.contains(&index) {
+                return Err(format!("GPU {} listed twice", index));
+            }
+            indices.push(index);
+        }
+        Ok(GpuSelection::Devices(indices))
+    }
 }
 
 impl std::fmt::Display for GpuBackend {
@@ -117,6 +171,38 @@ impl GpuContext {
         Err(anyhow!("No GPU backends available"))
     }
 
+    /// Create one context per selected device.
+    ///
+    /// The first device decides the backend (and whether software renderers
+    /// count); the others are opened on that same backend so an adapter is
+    /// never picked up twice through different APIs.
+    pub async fn open_many(selection: &GpuSelection, backend: GpuBackend) -> Result<Vec<Self>> {
+        let first = Self::new(selection.first(), backend).await?;
+        let chosen = GpuBackend::from_wgpu(first.backend()).unwrap_or(backend);
+        let hardware_only = !Self::is_software_adapter(&first.adapter_info);
+
+        let mut contexts = vec![first];
+        match selection {
+            GpuSelection::All => {
+                for index in 1.. {
+                    match Self::try_backend(index, chosen, hardware_only).await {
+                        Ok(ctx) => contexts.push(ctx),
+                        Err(_) => break,
+                    }
+                }
+            }
+            GpuSelection::Devices(indices) => {
+                for &index in &indices[1..] {
+                    let ctx = Self::try_backend(index, chosen, hardware_only)
+                        .await
+                        .with_context(|| format!("GPU {}", index))?;
+                    contexts.push(ctx);
+                }
+            }
+        }
+        Ok(contexts)
+    }
+
     /// Check if adapter is a software renderer
     fn is_software_adapter(info: &wgpu::AdapterInfo) -> bool {
         if info.device_type == wgpu::DeviceType::Cpu {
@@ -319,3 +405,18 @@ impl GpuContext {
         Ok(result)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_gpu_selection() {
+        assert_eq!("all".parse(), Ok(GpuSelection::All));
+        assert_eq!("2".parse(), Ok(GpuSelection::Devices(vec![2])));
+        assert_eq!(" 0, 1,3".parse(), Ok(GpuSelection::Devices(vec![0, 1, 3])));
+        assert!("0,x".parse::<GpuSelection>().is_err());
+        assert!("1,1".parse::<GpuSelection>().is_err());
+        assert!("".parse::<GpuSelection>().is_err());
+    }
+}
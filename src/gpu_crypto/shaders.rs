@@ -1,2 +1,4 @@
 pub const FIELD_WGSL: &str = include_str!("shaders/field.wgsl");
 pub const CURVE_WGSL: &str = include_str!("shaders/curve.wgsl");
+pub const SHA256_WGSL: &str = include_str!("shaders/sha256.wgsl");
+pub const RIPEMD160_WGSL: &str = include_str!("shaders/ripemd160.wgsl");
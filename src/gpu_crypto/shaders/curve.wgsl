@@ -207,3 +207,41 @@ struct AffinePoint {
     x: array<u32, 8>,
     y: array<u32, 8>
 }
+
+// -----------------------------------------------------------------------------
+// Affine point addition: R = P + Q (both affine)
+// Returns (x3, y3) given (x1, y1), (x2, y2), and precomputed inv = 1/(x2-x1)
+// 
+// Formula:
+//   λ = (y2 - y1) * inv
+//   x3 = λ² - x1 - x2
+//   y3 = λ * (x1 - x3) - y1
+//
+// Cost: 2M + 1S (with precomputed inverse)
+// Compare to Jacobian mixed add: 8M + 4S
+// -----------------------------------------------------------------------------
+
+fn affine_add_with_inv(
+    x1: array<u32, 8>,
+    y1: array<u32, 8>,
+    x2: array<u32, 8>,
+    y2: array<u32, 8>,
+    dx_inv: array<u32, 8>
+) -> AffinePoint {
+    // λ = (y2 - y1) / (x2 - x1) = (y2 - y1) * dx_inv
+    let dy = fe_sub(y2, y1);
+    let lambda = fe_mul(dy, dx_inv);
+    
+    // x3 = λ² - x1 - x2
+    let lambda_sq = fe_square(lambda);
+    let x3 = fe_sub(fe_sub(lambda_sq, x1), x2);
+    
+    // y3 = λ * (x1 - x3) - y1
+    let x1_minus_x3 = fe_sub(x1, x3);
+    let y3 = fe_sub(fe_mul(lambda, x1_minus_x3), y1);
+    
+    var result: AffinePoint;
+    result.x = x3;
+    result.y = y3;
+    return result;
+}
@@ -0,0 +1,109 @@
+// =============================================================================
+// RIPEMD-160 Compression Function
+// =============================================================================
+// Dobbertin, Bosselaers & Preneel. Message words and state words are
+// little-endian u32 values: the state after the last block, written out word
+// by word in little-endian byte order, is the 20-byte digest.
+
+// Message word selected by each step (left line)
+var<private> RMD_R: array<u32, 80> = array<u32, 80>(
+    0u, 1u, 2u, 3u, 4u, 5u, 6u, 7u, 8u, 9u, 10u, 11u, 12u, 13u, 14u, 15u,
+    7u, 4u, 13u, 1u, 10u, 6u, 15u, 3u, 12u, 0u, 9u, 5u, 2u, 14u, 11u, 8u,
+    3u, 10u, 14u, 4u, 9u, 15u, 8u, 1u, 2u, 7u, 0u, 6u, 13u, 11u, 5u, 12u,
+    1u, 9u, 11u, 10u, 0u, 8u, 12u, 4u, 13u, 3u, 7u, 15u, 14u, 5u, 6u, 2u,
+    4u, 0u, 5u, 9u, 7u, 12u, 2u, 10u, 14u, 1u, 3u, 8u, 11u, 6u, 15u, 13u,
+);
+
+// Message word selected by each step (right line)
+var<private> RMD_RP: array<u32, 80> = array<u32, 80>(
+    5u, 14u, 7u, 0u, 9u, 2u, 11u, 4u, 13u, 6u, 15u, 8u, 1u, 10u, 3u, 12u,
+    6u, 11u, 3u, 7u, 0u, 13u, 5u, 10u, 14u, 15u, 8u, 12u, 4u, 9u, 1u, 2u,
+    15u, 5u, 1u, 3u, 7u, 14u, 6u, 9u, 11u, 8u, 12u, 2u, 10u, 0u, 4u, 13u,
+    8u, 6u, 4u, 1u, 3u, 11u, 15u, 0u, 5u, 12u, 2u, 13u, 9u, 7u, 10u, 14u,
+    12u, 15u, 10u, 4u, 1u, 5u, 8u, 7u, 6u, 2u, 13u, 14u, 0u, 3u, 9u, 11u,
+);
+
+// Rotation applied at each step (left line)
+var<private> RMD_S: array<u32, 80> = array<u32, 80>(
+    11u, 14u, 15u, 12u, 5u, 8u, 7u, 9u, 11u, 13u, 14u, 15u, 6u, 7u, 9u, 8u,
+    7u, 6u, 8u, 13u, 11u, 9u, 7u, 15u, 7u, 12u, 15u, 9u, 11u, 7u, 13u, 12u,
+    11u, 13u, 6u, 7u, 14u, 9u, 13u, 15u, 14u, 8u, 13u, 6u, 5u, 12u, 7u, 5u,
+    11u, 12u, 14u, 15u, 14u, 15u, 9u, 8u, 9u, 14u, 5u, 6u, 8u, 6u, 5u, 12u,
+    9u, 15u, 5u, 11u, 6u, 8u, 13u, 12u, 5u, 12u, 13u, 14u, 11u, 8u, 5u, 6u,
+);
+
+// Rotation applied at each step (right line)
+var<private> RMD_SP: array<u32, 80> = array<u32, 80>(
+    8u, 9u, 9u, 11u, 13u, 15u, 15u, 5u, 7u, 7u, 8u, 11u, 14u, 14u, 12u, 6u,
+    9u, 13u, 15u, 7u, 12u, 8u, 9u, 11u, 7u, 7u, 12u, 7u, 6u, 15u, 13u, 11u,
+    9u, 7u, 15u, 11u, 8u, 6u, 6u, 14u, 12u, 13u, 5u, 14u, 13u, 13u, 7u, 5u,
+    15u, 5u, 8u, 11u, 14u, 14u, 6u, 14u, 6u, 9u, 12u, 9u, 12u, 5u, 15u, 8u,
+    8u, 5u, 12u, 9u, 12u, 5u, 14u, 6u, 8u, 13u, 6u, 5u, 15u, 13u, 11u, 11u,
+);
+
+fn ripemd160_iv() -> array<u32, 5> {
+    return array<u32, 5>(0x67452301u, 0xefcdab89u, 0x98badcfeu, 0x10325476u, 0xc3d2e1f0u);
+}
+
+fn rotl32(x: u32, n: u32) -> u32 {
+    return (x << n) | (x >> (32u - n));
+}
+
+// Boolean function of round j / 16 (the right line runs them in reverse)
+fn ripemd160_f(rnd: u32, x: u32, y: u32, z: u32) -> u32 {
+    switch rnd {
+        case 0u: { return x ^ y ^ z; }
+        case 1u: { return (x & y) | (~x & z); }
+        case 2u: { return (x | ~y) ^ z; }
+        case 3u: { return (x & z) | (y & ~z); }
+        default: { return x ^ (y | ~z); }
+    }
+}
+
+fn ripemd160_k(rnd: u32) -> u32 {
+    switch rnd {
+        case 0u: { return 0x00000000u; }
+        case 1u: { return 0x5a827999u; }
+        case 2u: { return 0x6ed9eba1u; }
+        case 3u: { return 0x8f1bbcdcu; }
+        default: { return 0xa953fd4eu; }
+    }
+}
+
+fn ripemd160_kp(rnd: u32) -> u32 {
+    switch rnd {
+        case 0u: { return 0x50a28be6u; }
+        case 1u: { return 0x5c4dd124u; }
+        case 2u: { return 0x6d703ef3u; }
+        case 3u: { return 0x7a6d76e9u; }
+        default: { return 0x00000000u; }
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Process one 64-byte block: returns the updated chaining state
+// -----------------------------------------------------------------------------
+fn ripemd160_compress(state: array<u32, 5>, block: array<u32, 16>) -> array<u32, 5> {
+    var x = block;
+
+    var al = state[0]; var bl = state[1]; var cl = state[2]; var dl = state[3]; var el = state[4];
+    var ar = state[0]; var br = state[1]; var cr = state[2]; var dr = state[3]; var er = state[4];
+
+    for (var j = 0u; j < 80u; j = j + 1u) {
+        let rnd = j / 16u;
+
+        let tl = rotl32(al + ripemd160_f(rnd, bl, cl, dl) + x[RMD_R[j]] + ripemd160_k(rnd), RMD_S[j]) + el;
+        al = el; el = dl; dl = rotl32(cl, 10u); cl = bl; bl = tl;
+
+        let tr = rotl32(ar + ripemd160_f(4u - rnd, br, cr, dr) + x[RMD_RP[j]] + ripemd160_kp(rnd), RMD_SP[j]) + er;
+        ar = er; er = dr; dr = rotl32(cr, 10u); cr = br; br = tr;
+    }
+
+    return array<u32, 5>(
+        state[1] + cl + dr,
+        state[2] + dl + er,
+        state[3] + el + ar,
+        state[4] + al + br,
+        state[0] + bl + cr
+    );
+}
@@ -0,0 +1,62 @@
+// =============================================================================
+// SHA-256 Compression Function
+// =============================================================================
+// FIPS 180-4. Message words and state words are big-endian u32 values, so a
+// caller hashing bytes packs them MSB-first and appends its own padding.
+
+var<private> SHA256_K: array<u32, 64> = array<u32, 64>(
+    0x428a2f98u, 0x71374491u, 0xb5c0fbcfu, 0xe9b5dba5u, 0x3956c25bu, 0x59f111f1u, 0x923f82a4u, 0xab1c5ed5u,
+    0xd807aa98u, 0x12835b01u, 0x243185beu, 0x550c7dc3u, 0x72be5d74u, 0x80deb1feu, 0x9bdc06a7u, 0xc19bf174u,
+    0xe49b69c1u, 0xefbe4786u, 0x0fc19dc6u, 0x240ca1ccu, 0x2de92c6fu, 0x4a7484aau, 0x5cb0a9dcu, 0x76f988dau,
+    0x983e5152u, 0xa831c66du, 0xb00327c8u, 0xbf597fc7u, 0xc6e00bf3u, 0xd5a79147u, 0x06ca6351u, 0x14292967u,
+    0x27b70a85u, 0x2e1b2138u, 0x4d2c6dfcu, 0x53380d13u, 0x650a7354u, 0x766a0abbu, 0x81c2c92eu, 0x92722c85u,
+    0xa2bfe8a1u, 0xa81a664bu, 0xc24b8b70u, 0xc76c51a3u, 0xd192e819u, 0xd6990624u, 0xf40e3585u, 0x106aa070u,
+    0x19a4c116u, 0x1e376c08u, 0x2748774cu, 0x34b0bcb5u, 0x391c0cb3u, 0x4ed8aa4au, 0x5b9cca4fu, 0x682e6ff3u,
+    0x748f82eeu, 0x78a5636fu, 0x84c87814u, 0x8cc70208u, 0x90befffau, 0xa4506cebu, 0xbef9a3f7u, 0xc67178f2u,
+);
+
+fn sha256_iv() -> array<u32, 8> {
+    return array<u32, 8>(
+        0x6a09e667u, 0xbb67ae85u, 0x3c6ef372u, 0xa54ff53au,
+        0x510e527fu, 0x9b05688cu, 0x1f83d9abu, 0x5be0cd19u
+    );
+}
+
+fn rotr32(x: u32, n: u32) -> u32 {
+    return (x >> n) | (x << (32u - n));
+}
+
+// -----------------------------------------------------------------------------
+// Process one 64-byte block: returns the updated chaining state
+// -----------------------------------------------------------------------------
+fn sha256_compress(state: array<u32, 8>, block: array<u32, 16>) -> array<u32, 8> {
+    var m = block;
+    var w: array<u32, 64>;
+    for (var i = 0u; i < 16u; i = i + 1u) {
+        w[i] = m[i];
+    }
+    for (var i = 16u; i < 64u; i = i + 1u) {
+        let s0 = rotr32(w[i - 15u], 7u) ^ rotr32(w[i - 15u], 18u) ^ (w[i - 15u] >> 3u);
+        let s1 = rotr32(w[i - 2u], 17u) ^ rotr32(w[i - 2u], 19u) ^ (w[i - 2u] >> 10u);
+        w[i] = w[i - 16u] + s0 + w[i - 7u] + s1;
+    }
+
+    var a = state[0]; var b = state[1]; var c = state[2]; var d = state[3];
+    var e = state[4]; var f = state[5]; var g = state[6]; var h = state[7];
+
+    for (var i = 0u; i < 64u; i = i + 1u) {
+        let s1 = rotr32(e, 6u) ^ rotr32(e, 11u) ^ rotr32(e, 25u);
+        let ch = (e & f) ^ (~e & g);
+        let t1 = h + s1 + ch + SHA256_K[i] + w[i];
+        let s0 = rotr32(a, 2u) ^ rotr32(a, 13u) ^ rotr32(a, 22u);
+        let maj = (a & b) ^ (a & c) ^ (b & c);
+        let t2 = s0 + maj;
+        h = g; g = f; f = e; e = d + t1;
+        d = c; c = b; b = a; a = t1 + t2;
+    }
+
+    return array<u32, 8>(
+        state[0] + a, state[1] + b, state[2] + c, state[3] + d,
+        state[4] + e, state[5] + f, state[6] + g, state[7] + h
+    );
+}
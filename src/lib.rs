@@ -5,6 +5,7 @@
 //!
 //! Supports AMD, NVIDIA, Intel GPUs via wgpu (Vulkan/Metal/DX12).
 
+mod checkpoint;
 mod cli;
 mod convert;
 mod cpu;
@@ -12,43 +13,88 @@ mod crypto;
 mod gpu;
 mod gpu_crypto;
 mod math;
+mod multi_gpu;
+mod net;
 mod provider;
+mod range;
+mod scan;
 mod solver;
-
-pub use cpu::CpuKangarooSolver;
-pub use crypto::{full_verify, parse_hex_u256, parse_pubkey, verify_key, Point};
-pub use gpu_crypto::{GpuBackend, GpuContext};
-pub use solver::KangarooSolver;
+mod stats;
+mod tamedb;
+mod template;
+mod workfile;
+
+pub use checkpoint::Checkpoint;
+pub use cpu::gaudry_schost::SetOverlap;
+pub use cpu::init::Herds;
+pub use cpu::{BsgsSolver, CpuKangarooSolver};
+pub use crypto::{
+    full_verify, parse_hex_u256, parse_pubkey, verify_key, verify_key_with_base, Point,
+};
+pub use gpu_crypto::{GpuBackend, GpuContext, GpuSelection};
+pub use net::{DpClient, DpServer, SourceStats, ValidationPolicy};
+pub use range::KeyRange;
+pub use scan::{AddressForm, Hash160Scanner, ScanFound, ScanProgress, ScanTargets};
+pub use solver::{KangarooSolver, SolverOptions};
+pub use stats::{RateMeter, SearchModel, SearchStats};
+pub use tamedb::TameDb;
+pub use template::{KeyTemplate, TemplateSearch};
+pub use workfile::{read_work_file, WorkFileWriter, WorkHeader};
 
 use anyhow::anyhow;
-use clap::Parser;
-use indicatif::ProgressBar;
-#[cfg(feature = "boha")]
-use num_bigint::BigUint;
+use clap::{Parser, Subcommand, ValueEnum};
+use cpu::jumps::{JumpDistribution, JumpMean, JumpTableSpec};
+use k256::elliptic_curve::bigint::Encoding;
+use k256::elliptic_curve::sec1::ToEncodedPoint;
+use range::RangeSpec;
 use serde::Serialize;
-use std::time::Instant;
-use tracing::{error, info};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tracing::{error, info, warn};
 
 /// Pollard's Kangaroo ECDLP solver for secp256k1
 ///
 /// Finds private key k such that P = k*G, given that k is in range [start, start + 2^range_bits]
+/// or in an exact interval [a, b]
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
 pub struct Args {
     /// Public key to solve (compressed hex, 33 bytes)
-    #[arg(short, long)]
+    #[arg(short, long, global = true)]
     pubkey: Option<String>,
 
     /// Start of search range (hex, without 0x prefix)
-    #[arg(short, long)]
+    #[arg(short, long, global = true)]
     start: Option<String>,
 
-    /// Bit range to search (key is in [start, start + 2^range])
-    #[arg(short, long)]
-    range: Option<u32>,
+    /// Bit range to search (key is in [start, start + 2^range]), or an inclusive
+    /// interval a:b (decimal or 0x-prefixed hex)
+    #[arg(short, long, global = true)]
+    range: Option<RangeSpec>,
+
+    /// Centre of the search interval (decimal or 0x-prefixed hex)
+    #[arg(long, global = true, requires = "radius", conflicts_with_all = ["start", "range"])]
+    center: Option<String>,
+
+    /// Keys searched on each side of --center (decimal or 0x-prefixed hex)
+    #[arg(long, global = true, requires = "center")]
+    radius: Option<String>,
+
+    /// Solve every pubkey in this file (one compressed hex per line) over the same range
+    #[arg(long)]
+    pubkey_file: Option<PathBuf>,
+
+    /// Solve P = k·Q for this base point Q (compressed hex) instead of P = k·G
+    #[arg(long)]
+    base: Option<String>,
+
+    /// Key in hex with ? for unknown digits (e.g. 0x1f3a????c0), instead of a range
+    #[arg(long, conflicts_with_all = ["start", "range", "center", "radius", "pubkey_file"])]
+    key_template: Option<KeyTemplate>,
 
     /// Data provider target (e.g., boha:b1000/135)
-    #[arg(short, long)]
+    #[arg(short, long, global = true)]
     target: Option<String>,
 
     /// List available puzzles from providers
@@ -56,27 +102,27 @@ pub struct Args {
     list_providers: bool,
 
     /// Distinguished point bits (auto-calculated if not set)
-    #[arg(short, long)]
+    #[arg(short, long, global = true)]
     dp_bits: Option<u32>,
 
     /// Number of kangaroos (default: auto based on GPU)
     #[arg(short, long)]
     kangaroos: Option<u32>,
 
-    /// GPU device index
-    #[arg(long, default_value = "0")]
-    gpu: u32,
+    /// GPU device indices (e.g. 0,1,3) or "all"; several GPUs share one DP table (default: 0)
+    #[arg(long)]
+    gpu: Option<gpu_crypto::GpuSelection>,
 
     /// GPU backend to use
     #[arg(long, value_enum, default_value = "auto")]
     backend: gpu_crypto::GpuBackend,
 
     /// Output file for result (hex private key)
-    #[arg(short, long)]
+    #[arg(short, long, global = true)]
     output: Option<String>,
 
     /// Quiet mode - minimal output, just print found key
-    #[arg(short, long)]
+    #[arg(short, long, global = true)]
     quiet: bool,
 
     /// Maximum operations before giving up (0 = unlimited)
@@ -87,6 +133,10 @@ pub struct Args {
     #[arg(long)]
     cpu: bool,
 
+    /// CPU worker threads joining the GPU search (default: none, or all cores if no GPU is usable)
+    #[arg(long)]
+    cpu_threads: Option<usize>,
+
     /// Output benchmark results in JSON format to stdout
     #[arg(long)]
     json: bool,
@@ -94,6 +144,206 @@ pub struct Args {
     /// Run benchmark suite and print results
     #[arg(long)]
     benchmark: bool,
+
+    /// Periodically save herd and DP table to this file (GPU solver only)
+    #[arg(long)]
+    checkpoint: Option<PathBuf>,
+
+    /// Seconds between checkpoints
+    #[arg(long, default_value = "300")]
+    checkpoint_interval: u64,
+
+    /// Resume a previous run from a checkpoint file
+    #[arg(long)]
+    resume: Option<PathBuf>,
+
+    /// Walk wild kangaroos only, against the tame DPs saved by `kangaroo precompute` (GPU solver only)
+    #[arg(long)]
+    tame_db: Option<PathBuf>,
+
+    /// Append every distinguished point to this work file (GPU solver only)
+    #[arg(long, global = true)]
+    work_file: Option<PathBuf>,
+
+    /// Walk on {P, -P} equivalence classes (~1.41x fewer operations, GPU solver only)
+    #[arg(long, global = true)]
+    negation: bool,
+
+    /// Herd composition: van Oorschot–Wiener tame/wild herds, three or four
+    /// kangaroos with wilds from P and -P, Gaudry–Schost restarts, or a
+    /// baby-step giant-step search on the CPU (default: bsgs up to 48 bits, vow above)
+    #[arg(long, global = true, value_enum)]
+    algorithm: Option<Algorithm>,
+
+    /// Gaudry–Schost tame set width in percent of the range, centred on its middle (default: 100)
+    #[arg(long, global = true)]
+    tame_set: Option<u32>,
+
+    /// Gaudry–Schost wild set width in percent of the range, centred on the key (default: 50)
+    #[arg(long, global = true)]
+    wild_set: Option<u32>,
+
+    /// Memory for the baby-step table of --algorithm bsgs, in MiB (default: 1024)
+    #[arg(long)]
+    bsgs_memory: Option<u64>,
+
+    /// Jump table entries, 16 - 4096 (default: 256)
+    #[arg(long, global = true)]
+    jump_table_size: Option<u32>,
+
+    /// Mean jump: "sqrt" for sqrt(N)/2 or a bit count for 2^bits (default: m*sqrt(N)/4 for m kangaroos)
+    #[arg(long, global = true)]
+    jump_mean: Option<JumpMean>,
+
+    /// Spread of the jump distances (default: random)
+    #[arg(long, global = true, value_enum)]
+    jump_distribution: Option<JumpDistribution>,
+
+    /// Seed for the jump distances (default: 0)
+    #[arg(long, global = true)]
+    jump_seed: Option<u64>,
+
+    /// Herd seed, to replay a run (default: drawn from the OS RNG, logged and saved in checkpoints)
+    #[arg(long)]
+    seed: Option<u64>,
+
+    /// Run as a worker of a distributed search (host:port of `kangaroo server`)
+    #[arg(long)]
+    server: Option<String>,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Inspect, merge and check distinguished point work files
+    Work {
+        #[command(subcommand)]
+        action: WorkCommand,
+    },
+    /// Collect DPs from remote workers for one target (uses --pubkey/--target/--range/--dp-bits)
+    Server {
+        /// Address to listen on
+        #[arg(long, default_value = "0.0.0.0:7878")]
+        listen: String,
+
+        /// Fraction of incoming DPs recomputed on the server (0.0 - 1.0)
+        #[arg(long, default_value = "0.01")]
+        verify_fraction: f64,
+
+        /// Failed DP checks after which a source address is banned
+        #[arg(long, default_value = "3")]
+        ban_after: u32,
+    },
+    /// Print the jump table for --range/--target and the --jump-* options
+    Jumptable {
+        /// Print the mean, min/max and a histogram of distance sizes instead of the entries
+        #[arg(long)]
+        stats: bool,
+
+        /// Total kangaroos the default mean is scaled for
+        #[arg(long, default_value = "65536")]
+        kangaroos: u64,
+    },
+    /// Save tame DPs over [0, 2^width-bits) for --tame-db searches (uses --dp-bits and -o)
+    Precompute {
+        /// Width of the ranges the database serves, in bits
+        #[arg(long)]
+        width_bits: u32,
+
+        /// Tame DPs to collect (default: 2^((width - dp bits) / 2), at most 2^20)
+        #[arg(long)]
+        dps: Option<u64>,
+    },
+    /// Hash every key of --range/--target and look for hash160s, for puzzles without a public key
+    Scan(ScanArgs),
+}
+
+/// Options of `kangaroo scan`
+#[derive(clap::Args, Debug)]
+struct ScanArgs {
+    /// Hash160 to look for (40 hex digits); repeatable
+    #[arg(long)]
+    hash160: Vec<String>,
+
+    /// P2PKH address to look for; repeatable (default: the --target puzzle's)
+    #[arg(long)]
+    address: Vec<String>,
+
+    /// Hash160s or addresses to look for, one per line
+    #[arg(long)]
+    targets_file: Option<PathBuf>,
+
+    /// Public key serialization hashed for each key
+    #[arg(long, value_enum, default_value = "compressed")]
+    form: AddressForm,
+
+    /// Save progress here every --checkpoint-interval seconds and on exit;
+    /// an existing file is resumed
+    #[arg(long)]
+    progress: Option<PathBuf>,
+
+    /// GPU threads, each hashing its own run of keys (default: auto based on GPU)
+    #[arg(long)]
+    threads: Option<u32>,
+}
+
+#[derive(Subcommand, Debug)]
+enum WorkCommand {
+    /// Merge work files from runs against the same pubkey and range
+    Merge {
+        /// Input work files
+        #[arg(required = true, num_args = 2..)]
+        inputs: Vec<PathBuf>,
+
+        /// Merged output file
+        #[arg(short, long)]
+        output: PathBuf,
+    },
+    /// Show DP counts by type, DP bits, range and target
+    Info {
+        /// Work file to inspect
+        file: PathBuf,
+    },
+    /// Replay work files through the DP table and look for a tame/wild collision
+    Check {
+        /// Work files (all must target the same pubkey and range)
+        #[arg(required = true)]
+        files: Vec<PathBuf>,
+    },
+}
+
+/// `--algorithm` values
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum Algorithm {
+    /// van Oorschot–Wiener: half tame, half wild (~2·√N)
+    Vow,
+    /// Tames, wilds from P and wilds from -P in thirds (~1.82·√N)
+    Three,
+    /// Half tame, a quarter each of wilds from P and from -P (~1.71·√N)
+    Four,
+    /// Tame and wild walks that restart at a random point of their set after
+    /// each DP (~1.93·√N with the default sets)
+    GaudrySchost,
+    /// Baby-step giant-step on the CPU: every key checked, so a miss proves the
+    /// key is not in the range
+    Bsgs,
+}
+
+impl Algorithm {
+    fn herds(self) -> Herds {
+        match self {
+            Algorithm::Vow | Algorithm::GaudrySchost | Algorithm::Bsgs => Herds::Vow,
+            Algorithm::Three => Herds::Three,
+            Algorithm::Four => Herds::Four,
+        }
+    }
+
+    fn name(self) -> String {
+        self.to_possible_value()
+            .map_or_else(String::new, |value| value.get_name().to_string())
+    }
 }
 
 #[derive(Serialize)]
@@ -111,6 +361,9 @@ struct Metadata {
     algorithm: String,
     total_ops: u64,
     time_seconds: f64,
+    /// Herd seed (GPU and hybrid runs)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    seed: Option<u64>,
 }
 
 pub fn run_from_args<I, S>(args: I) -> anyhow::Result<()>
@@ -124,8 +377,7 @@ where
 
 struct ResolvedParams {
     pubkey_str: String,
-    start_str: String,
-    range_bits: u32,
+    range: KeyRange,
 }
 
 fn resolve_params(args: &Args) -> anyhow::Result<ResolvedParams> {
@@ -135,166 +387,181 @@ fn resolve_params(args: &Args) -> anyhow::Result<ResolvedParams> {
         None
     };
 
-    let (pubkey_str, start_str, range_bits) = match provider_result {
+    let (pubkey_str, range) = match provider_result {
         Some(ref pr) => {
             let pubkey_str = match (&args.pubkey, &pr.pubkey) {
                 (Some(p), _) => p.clone(),
                 (None, Some(p)) => p.clone(),
                 (None, None) => {
                     return Err(anyhow!(
-                        "Puzzle '{}' has no public key. Cannot solve without pubkey.",
+                        "Puzzle '{}' has no public key. Cannot solve without pubkey; `kangaroo scan` searches by address.",
                         pr.id
                     ))
                 }
             };
 
-            let start_str = args
-                .start
-                .clone()
-                .or_else(|| pr.start.clone())
-                .unwrap_or_else(|| "0".to_string());
-
-            let range_bits = match args.range {
-                Some(user_range) => {
-                    // User provided explicit range - validate it
-                    validate_search_bounds(&start_str, user_range, pr)?;
-                    user_range
-                }
-                None => {
-                    // No user range - calculate from provider bounds if available
-                    calculate_range_bits_from_provider(&start_str, pr)?
-                }
-            };
-
-            (pubkey_str, start_str, range_bits)
+            (pubkey_str, provider_search_range(args, pr)?)
         }
         None => {
-            let pubkey_str = args
-                .pubkey
-                .clone()
-                .ok_or_else(|| anyhow!("--pubkey is required when not using --target"))?;
-            let start_str = args.start.clone().unwrap_or_else(|| "0".to_string());
-            let range_bits = args.range.unwrap_or(32);
-            (pubkey_str, start_str, range_bits)
+            let pubkey_str = args.pubkey.clone().ok_or_else(|| {
+                anyhow!("--pubkey is required when not using --target or --pubkey-file")
+            })?;
+            (pubkey_str, requested_or_default_range(args)?)
         }
     };
 
-    Ok(ResolvedParams {
-        pubkey_str,
-        start_str,
-        range_bits,
-    })
+    Ok(ResolvedParams { pubkey_str, range })
 }
 
-#[cfg(feature = "boha")]
-fn calculate_range_bits_from_provider(
-    start: &str,
-    provider: &provider::ProviderResult,
-) -> anyhow::Result<u32> {
-    let (Some(ref provider_start), Some(ref provider_end)) = (&provider.start, &provider.end)
-    else {
-        return provider.range_bits.ok_or_else(|| {
-            anyhow!(
-                "Provider '{}' has no range information. Use --range to specify search range.",
-                provider.id
-            )
-        });
-    };
+/// The range to search for a provider's puzzle: the requested one, checked
+/// against the puzzle's bounds, or the bounds themselves
+fn provider_search_range(args: &Args, pr: &provider::ProviderResult) -> anyhow::Result<KeyRange> {
+    Ok(
+        match (
+            requested_range(args, pr.start.as_deref())?,
+            provider_range(pr)?,
+        ) {
+            (Some(range), Some(bounds)) => {
+                // User provided explicit range - validate it
+                validate_search_bounds(&range, &bounds, &pr.id)?;
+                range
+            }
+            (Some(range), None) => range,
+            // The puzzle's own bounds, from --start if given
+            (None, Some(bounds)) => match args.start {
+                Some(ref start) => {
+                    let range = KeyRange::new(crypto::parse_hex_u256(start)?, bounds.end())
+                        .map_err(|_| {
+                            anyhow!(
+                                "Start 0x{} exceeds puzzle '{}' maximum {}",
+                                start,
+                                pr.id,
+                                range::format_key(&bounds.end())
+                            )
+                        })?;
+                    validate_search_bounds(&range, &bounds, &pr.id)?;
+                    range
+                }
+                None => bounds,
+            },
+            (None, None) => {
+                let bits = pr.range_bits.ok_or_else(|| {
+                    anyhow!(
+                    "Provider '{}' has no range information. Use --range to specify search range.",
+                    pr.id
+                )
+                })?;
+                let start = args.start.as_deref().or(pr.start.as_deref()).unwrap_or("0");
+                KeyRange::from_bits(crypto::parse_hex_u256(start)?, bits)
+            }
+        },
+    )
+}
 
-    let start_val = BigUint::parse_bytes(start.as_bytes(), 16)
-        .ok_or_else(|| anyhow!("Invalid hex start value: {}", start))?;
-    let provider_start_val = BigUint::parse_bytes(provider_start.as_bytes(), 16)
-        .ok_or_else(|| anyhow!("Invalid provider start hex"))?;
-    let provider_end_val = BigUint::parse_bytes(provider_end.as_bytes(), 16)
-        .ok_or_else(|| anyhow!("Invalid provider end hex"))?;
+/// The range asked for with --center/--radius or --range (bit ranges start at
+/// --start, else at `default_start`), or None
+fn requested_range(args: &Args, default_start: Option<&str>) -> anyhow::Result<Option<KeyRange>> {
+    if let (Some(center), Some(radius)) = (&args.center, &args.radius) {
+        return KeyRange::from_center(range::parse_key(center)?, range::parse_key(radius)?)
+            .map(Some);
+    }
+    match args.range {
+        Some(RangeSpec::Interval(range)) => {
+            if args.start.is_some() {
+                return Err(anyhow!("--start cannot be combined with an a:b --range"));
+            }
+            Ok(Some(range))
+        }
+        Some(RangeSpec::Bits(bits)) => {
+            let start = args.start.as_deref().or(default_start).unwrap_or("0");
+            Ok(Some(KeyRange::from_bits(
+                crypto::parse_hex_u256(start)?,
+                bits,
+            )))
+        }
+        None => Ok(None),
+    }
+}
 
-    if start_val < provider_start_val {
-        return Err(anyhow!(
-            "Start 0x{} is below puzzle '{}' minimum 0x{}",
-            start,
-            provider.id,
-            provider_start
-        ));
+/// The requested range, or 32 bits from --start (default 0)
+fn requested_or_default_range(args: &Args) -> anyhow::Result<KeyRange> {
+    match requested_range(args, None)? {
+        Some(range) => Ok(range),
+        None => {
+            let start = args.start.as_deref().unwrap_or("0");
+            Ok(KeyRange::from_bits(crypto::parse_hex_u256(start)?, 32))
+        }
+    }
+}
+
+/// Take run parameters from a checkpoint or server, rejecting conflicting CLI targets
+fn resolve_fixed_params(
+    args: &Args,
+    fixed: &WorkHeader,
+    source: &str,
+) -> anyhow::Result<ResolvedParams> {
+    let resolved = ResolvedParams {
+        pubkey_str: hex::encode(fixed.pubkey.to_affine().to_encoded_point(true).as_bytes()),
+        range: fixed.range,
+    };
+
+    if args.pubkey.is_some() || args.target.is_some() {
+        let requested = resolve_params(args)?;
+        let pubkey = crypto::parse_pubkey(&requested.pubkey_str)?;
+        let range_given = args.start.is_some()
+            || args.range.is_some()
+            || args.center.is_some()
+            || args.target.is_some();
+        if pubkey != fixed.pubkey || (range_given && requested.range != fixed.range) {
+            return Err(anyhow!(
+                "{} is for a different target or range; drop --pubkey/--target/--start/--range/--center",
+                source
+            ));
+        }
     }
 
-    if start_val > provider_end_val {
+    if args.negation && !fixed.negation {
         return Err(anyhow!(
-            "Start 0x{} exceeds puzzle '{}' maximum 0x{}",
-            start,
-            provider.id,
-            provider_end
+            "{} does not use the negation map; drop --negation",
+            source
         ));
     }
 
-    let range_size = &provider_end_val - &start_val + BigUint::from(1u32);
-    let bits = range_size.bits() as u32;
-
-    Ok(bits)
-}
-
-#[cfg(not(feature = "boha"))]
-fn calculate_range_bits_from_provider(
-    _start: &str,
-    provider: &provider::ProviderResult,
-) -> anyhow::Result<u32> {
-    provider.range_bits.ok_or_else(|| {
-        anyhow!(
-            "Provider '{}' has no range information. Use --range to specify search range.",
-            provider.id
-        )
-    })
+    Ok(resolved)
 }
 
-#[cfg(feature = "boha")]
-fn validate_search_bounds(
-    start: &str,
-    range_bits: u32,
-    provider: &provider::ProviderResult,
-) -> anyhow::Result<()> {
-    let (Some(ref provider_start), Some(ref provider_end)) = (&provider.start, &provider.end)
-    else {
-        return Ok(());
+/// The puzzle's key interval, if the provider knows it
+fn provider_range(provider: &provider::ProviderResult) -> anyhow::Result<Option<KeyRange>> {
+    let (Some(ref start), Some(ref end)) = (&provider.start, &provider.end) else {
+        return Ok(None);
     };
+    let start = crypto::parse_hex_u256(start).map_err(|_| anyhow!("Invalid provider start hex"))?;
+    let end = crypto::parse_hex_u256(end).map_err(|_| anyhow!("Invalid provider end hex"))?;
+    KeyRange::new(start, end).map(Some)
+}
 
-    let start_val = BigUint::parse_bytes(start.as_bytes(), 16)
-        .ok_or_else(|| anyhow!("Invalid hex start value: {}", start))?;
-    let provider_start_val = BigUint::parse_bytes(provider_start.as_bytes(), 16)
-        .ok_or_else(|| anyhow!("Invalid provider start hex"))?;
-    let provider_end_val = BigUint::parse_bytes(provider_end.as_bytes(), 16)
-        .ok_or_else(|| anyhow!("Invalid provider end hex"))?;
-
-    if start_val < provider_start_val {
+fn validate_search_bounds(range: &KeyRange, bounds: &KeyRange, id: &str) -> anyhow::Result<()> {
+    if range::compare_keys(&range.start(), &bounds.start()).is_lt() {
         return Err(anyhow!(
-            "Start 0x{} is below puzzle '{}' minimum 0x{}",
-            start,
-            provider.id,
-            provider_start
+            "Start {} is below puzzle '{}' minimum {}",
+            range::format_key(&range.start()),
+            id,
+            range::format_key(&bounds.start())
         ));
     }
 
-    let search_end = &start_val + (BigUint::from(1u64) << range_bits);
-    if search_end > provider_end_val {
+    if !bounds.contains(range) {
         return Err(anyhow!(
-            "Search range [0x{}..0x{:x}] exceeds puzzle '{}' maximum 0x{}",
-            start,
-            search_end,
-            provider.id,
-            provider_end
+            "Search range {} exceeds puzzle '{}' maximum {}",
+            range,
+            id,
+            range::format_key(&bounds.end())
         ));
     }
 
     Ok(())
 }
 
-#[cfg(not(feature = "boha"))]
-fn validate_search_bounds(
-    _start: &str,
-    _range_bits: u32,
-    _provider: &provider::ProviderResult,
-) -> anyhow::Result<()> {
-    Ok(())
-}
-
 fn print_providers_list() {
     let providers = provider::supported_providers();
     if providers.is_empty() {
@@ -324,6 +591,234 @@ fn print_providers_list() {
     }
 }
 
+/// The herd composition asked for by --algorithm
+fn requested_herds(args: &Args) -> anyhow::Result<Herds> {
+    if args.algorithm == Some(Algorithm::Bsgs) {
+        return Err(anyhow!(
+            "--algorithm bsgs is not a kangaroo search; it cannot be benchmarked or served"
+        ));
+    }
+    let herds = args.algorithm.map_or(Herds::Vow, Algorithm::herds);
+    if herds != Herds::Vow && args.negation {
+        return Err(anyhow!(
+            "--algorithm three/four already use -P; drop --negation"
+        ));
+    }
+    Ok(herds)
+}
+
+/// The Gaudry–Schost sets asked for by --algorithm gaudry-schost, --tame-set and --wild-set
+fn requested_sets(args: &Args) -> anyhow::Result<Option<SetOverlap>> {
+    if args.algorithm != Some(Algorithm::GaudrySchost) {
+        return Ok(None);
+    }
+    if args.negation {
+        return Err(anyhow!(
+            "--algorithm gaudry-schost does not support --negation"
+        ));
+    }
+    let sets = SetOverlap {
+        tame_percent: args
+            .tame_set
+            .unwrap_or(cpu::gaudry_schost::DEFAULT_TAME_SET),
+        wild_percent: args
+            .wild_set
+            .unwrap_or(cpu::gaudry_schost::DEFAULT_WILD_SET),
+    };
+    sets.validate()?;
+    Ok(Some(sets))
+}
+
+/// `metadata.algorithm` of --json results
+fn json_algorithm(args: &Args) -> String {
+    match args.algorithm {
+        Some(Algorithm::GaudrySchost) => "gaudry_schost".to_string(),
+        _ => "pollard_kangaroo".to_string(),
+    }
+}
+
+/// The base point asked for by --base, G by default
+fn requested_base(args: &Args) -> anyhow::Result<Point> {
+    match args.base {
+        Some(ref base) => {
+            crypto::parse_pubkey(base).map_err(|e| anyhow!("Invalid --base point: {:#}", e))
+        }
+        None => Ok(Point::GENERATOR),
+    }
+}
+
+/// Whether to search with baby-step giant-step: asked for, or picked for ranges
+/// up to 2^48 when no kangaroo option was given
+fn use_bsgs(args: &Args, range: &KeyRange) -> bool {
+    match args.algorithm {
+        Some(algorithm) => algorithm == Algorithm::Bsgs,
+        None => range.bits() <= cpu::bsgs::AUTO_MAX_BITS && !bsgs_excluded_options_given(args),
+    }
+}
+
+/// Options a baby-step giant-step search cannot honour: the kangaroo options,
+/// a GPU choice and an operation limit
+fn bsgs_excluded_options_given(args: &Args) -> bool {
+    kangaroo_options_given(args) || args.gpu.is_some() || args.max_ops != 0
+}
+
+/// The GPUs asked for by --gpu
+fn gpu_selection(args: &Args) -> gpu_crypto::GpuSelection {
+    args.gpu
+        .clone()
+        .unwrap_or(gpu_crypto::GpuSelection::Devices(vec![0]))
+}
+
+/// Options that only mean something to a kangaroo search
+fn kangaroo_options_given(args: &Args) -> bool {
+    args.cpu
+        || args.cpu_threads.is_some()
+        || args.kangaroos.is_some()
+        || args.dp_bits.is_some()
+        || args.negation
+        || args.seed.is_some()
+        || args.base.is_some()
+        || args.resume.is_some()
+        || args.checkpoint.is_some()
+        || args.work_file.is_some()
+        || args.server.is_some()
+        || args.tame_db.is_some()
+        || jump_options_given(args)
+}
+
+fn jump_options_given(args: &Args) -> bool {
+    args.jump_table_size.is_some()
+        || args.jump_mean.is_some()
+        || args.jump_distribution.is_some()
+        || args.jump_seed.is_some()
+}
+
+/// The jump table asked for by the --jump-* options, for `kangaroos` walking in total
+fn jump_spec(args: &Args, kangaroos: u64) -> JumpTableSpec {
+    let default = JumpTableSpec::default();
+    JumpTableSpec {
+        size: args.jump_table_size.unwrap_or(default.size),
+        mean: args.jump_mean.unwrap_or(JumpMean::Herd(kangaroos)),
+        distribution: args.jump_distribution.unwrap_or(default.distribution),
+        seed: args.jump_seed.unwrap_or(default.seed),
+    }
+}
+
+fn run_jumptable_command(args: &Args, stats: bool, kangaroos: u64) -> anyhow::Result<()> {
+    let range = if args.target.is_some() {
+        resolve_params(args)?.range
+    } else {
+        requested_or_default_range(args)?
+    };
+    let spec = jump_spec(args, kangaroos);
+    let (_, distances) = cpu::jumps::generate_jump_table(&range, &spec)?;
+
+    println!("Range:        {} ({} bits)", range, range.bits());
+    println!("Entries:      {}", spec.size);
+    if let Some(name) = spec.distribution.to_possible_value() {
+        println!("Distribution: {}", name.get_name());
+    }
+    println!(
+        "Target mean:  {}",
+        range::format_key(&spec.target_mean(&range).to_le_bytes())
+    );
+
+    if !stats {
+        for (index, distance) in distances.iter().enumerate() {
+            println!(
+                "{:5} {}",
+                index,
+                range::format_key(bytemuck::cast_ref(distance))
+            );
+        }
+        return Ok(());
+    }
+
+    let stats = cpu::jumps::JumpStats::of(&distances);
+    println!("Mean:         2^{:.2}", stats.mean.log2());
+    println!(
+        "Min:          {}",
+        range::format_key(&stats.min.to_le_bytes())
+    );
+    println!(
+        "Max:          {}",
+        range::format_key(&stats.max.to_le_bytes())
+    );
+    println!("Bits  Entries");
+    let widest = stats.histogram.iter().map(|&(_, n)| n).max().unwrap_or(1);
+    for (bits, count) in &stats.histogram {
+        println!(
+            "{:4}  {:7} {}",
+            bits,
+            count,
+            "#".repeat((count * 40).div_ceil(widest))
+        );
+    }
+    Ok(())
+}
+
+fn run_work_command(action: &WorkCommand) -> anyhow::Result<()> {
+    match action {
+        WorkCommand::Merge { inputs, output } => {
+            let paths: Vec<&std::path::Path> = inputs.iter().map(PathBuf::as_path).collect();
+            let stats = workfile::merge_work_files(&paths, output)?;
+            println!(
+                "Merged {} files: {} DPs read, {} written ({} duplicates dropped), DP bits {} -> {}",
+                inputs.len(),
+                stats.total_in,
+                stats.written,
+                stats.total_in - stats.written,
+                stats.header.dp_bits,
+                output.display()
+            );
+        }
+        WorkCommand::Info { file } => {
+            let (header, dps) = workfile::read_work_file(file)?;
+            let tame = dps.iter().filter(|dp| dp.ktype == 0).count();
+            let wild = dps.len() - tame;
+            println!("File:      {}", file.display());
+            println!(
+                "Target:    {}",
+                hex::encode(header.pubkey.to_affine().to_encoded_point(true).as_bytes())
+            );
+            println!("Range:     {} ({} bits)", header.range, header.range.bits());
+            println!("DP bits:   {}", header.dp_bits);
+            println!("DPs:       {} ({} tame, {} wild)", dps.len(), tame, wild);
+        }
+        WorkCommand::Check { files } => {
+            let (header, mut dps) = workfile::read_work_file(&files[0])?;
+            for path in &files[1..] {
+                let (other, more) = workfile::read_work_file(path)?;
+                if !header.same_search(&other) {
+                    return Err(anyhow!(
+                        "{} targets a different pubkey or range than {}",
+                        path.display(),
+                        files[0].display()
+                    ));
+                }
+                dps.extend(more);
+            }
+
+            match workfile::check_for_collision(&header, &dps) {
+                Some(key) if crypto::verify_key(&key, &header.pubkey) => {
+                    println!("Collision found in {} DPs", dps.len());
+                    println!("Private key: 0x{}", hex::encode(&key));
+                }
+                Some(key) => {
+                    return Err(anyhow!(
+                        "Collision produced 0x{} which does not match the target pubkey",
+                        hex::encode(&key)
+                    ));
+                }
+                None => {
+                    println!("No tame/wild collision in {} DPs", dps.len());
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
 struct BenchmarkCase {
     name: &'static str,
     pubkey: &'static str,
@@ -352,19 +847,42 @@ const BENCHMARK_CASES: &[BenchmarkCase] = &[
     },
 ];
 
-fn run_benchmark(gpu_index: u32, backend: gpu_crypto::GpuBackend) -> anyhow::Result<()> {
+/// Benchmark suite; `--algorithm` adds a pipelined run of that algorithm to
+/// every case, next to the default walk
+fn run_benchmark(args: &Args) -> anyhow::Result<()> {
     println!("Kangaroo Benchmark Suite");
     println!("========================\n");
 
-    let gpu_context = pollster::block_on(gpu_crypto::GpuContext::new(gpu_index, backend))?;
+    let gpu_context = pollster::block_on(gpu_crypto::GpuContext::new(
+        gpu_selection(args).first(),
+        args.backend,
+    ))?;
     println!("GPU: {}", gpu_context.device_name());
     println!("Compute units: {}\n", gpu_context.compute_units());
 
+    let mut runs = vec![
+        (Algorithm::Vow, "sync", SolverOptions::default()),
+        (Algorithm::Vow, "pipelined", SolverOptions::default()),
+    ];
+    if let Some(algorithm) = args.algorithm.filter(|&a| a != Algorithm::Vow) {
+        let options = SolverOptions {
+            negation: args.negation,
+            herds: requested_herds(args)?,
+            gaudry_schost: requested_sets(args)?,
+            ..SolverOptions::default()
+        };
+        runs.push((algorithm, "pipelined", options));
+    }
+    for (_, mode, options) in &mut runs {
+        // Blocking readback after every dispatch vs. overlapping it with the next one
+        options.pipelined = *mode == "pipelined";
+    }
+
     println!(
-        "{:<10} {:>12} {:>12} {:>14}",
-        "Range", "Time", "Ops", "Rate"
+        "{:<10} {:<14} {:<10} {:>12} {:>12} {:>8} {:>14}",
+        "Range", "Algorithm", "Dispatch", "Time", "Ops", "Ops/√N", "Rate"
     );
-    println!("{}", "-".repeat(52));
+    println!("{}", "-".repeat(86));
 
     let num_k = gpu_context.optimal_kangaroos();
 
@@ -372,36 +890,43 @@ fn run_benchmark(gpu_index: u32, backend: gpu_crypto::GpuBackend) -> anyhow::Res
         let pubkey = crypto::parse_pubkey(case.pubkey)?;
         let start = crypto::parse_hex_u256(case.start)?;
 
-        let dp_bits = (case.range_bits / 2)
-            .saturating_sub((num_k as f64).log2() as u32 / 2)
-            .clamp(8, 40);
-
-        let mut solver = solver::KangarooSolver::new(
-            gpu_context.clone(),
-            pubkey,
-            start,
-            case.range_bits,
-            dp_bits,
-            num_k,
-        )?;
-
-        let start_time = Instant::now();
-        loop {
-            if solver.step()?.is_some() {
-                break;
+        let dp_bits = solver::auto_dp_bits(case.range_bits, u64::from(num_k));
+
+        for (algorithm, mode, options) in &runs {
+            let mut solver = solver::KangarooSolver::new_with_options(
+                gpu_context.clone(),
+                pubkey,
+                start,
+                case.range_bits,
+                dp_bits,
+                num_k,
+                options.clone(),
+            )?;
+
+            let start_time = Instant::now();
+            loop {
+                if solver.step()?.is_some() {
+                    break;
+                }
             }
+            let duration = start_time.elapsed();
+            let total_ops = solver.total_operations();
+            let rate = total_ops as f64 / duration.as_secs_f64();
+
+            // Work in units of √N, comparable across algorithms and ranges
+            let sqrt_n = 2f64.powf(f64::from(case.range_bits) / 2.0);
+
+            println!(
+                "{:<10} {:<14} {:<10} {:>10.2}s {:>12} {:>8.2} {:>12.2}M/s",
+                case.name,
+                algorithm.name(),
+                mode,
+                duration.as_secs_f64(),
+                total_ops,
+                total_ops as f64 / sqrt_n,
+                rate / 1_000_000.0
+            );
         }
-        let duration = start_time.elapsed();
-        let total_ops = solver.total_operations();
-        let rate = total_ops as f64 / duration.as_secs_f64();
-
-        println!(
-            "{:<10} {:>10.2}s {:>12} {:>12.2}M/s",
-            case.name,
-            duration.as_secs_f64(),
-            total_ops,
-            rate / 1_000_000.0
-        );
     }
 
     println!("\n[Copy above results to BENCHMARK.md]");
@@ -416,221 +941,1421 @@ pub fn run(args: Args) -> anyhow::Result<()> {
         return Ok(());
     }
 
-    if args.benchmark {
-        return run_benchmark(args.gpu, args.backend);
+    if (args.tame_set.is_some() || args.wild_set.is_some())
+        && args.algorithm != Some(Algorithm::GaudrySchost)
+    {
+        return Err(anyhow!(
+            "--tame-set and --wild-set need --algorithm gaudry-schost"
+        ));
     }
 
-    let params = resolve_params(&args)?;
+    // Checkpoints, work files, servers, tame databases and subcommands all assume G
+    if args.base.is_some()
+        && (args.benchmark
+            || args.command.is_some()
+            || args.resume.is_some()
+            || args.checkpoint.is_some()
+            || args.work_file.is_some()
+            || args.server.is_some()
+            || args.tame_db.is_some())
+    {
+        return Err(anyhow!(
+            "--base cannot be combined with subcommands, --benchmark, --resume, --checkpoint, --work-file, --server or --tame-db"
+        ));
+    }
 
-    if !args.quiet && !args.json {
-        info!("Kangaroo ECDLP Solver");
-        info!("=====================");
-        if let Some(ref target) = args.target {
-            info!("Target: {}", target);
-        }
-        info!("Pubkey: {}", params.pubkey_str);
-        info!(
-            "Search range: {} bits from 0x{}",
-            params.range_bits, params.start_str
-        );
+    // Checkpoints and servers would record the transformed target, not the key
+    if args.key_template.is_some()
+        && (args.benchmark
+            || args.command.is_some()
+            || args.resume.is_some()
+            || args.checkpoint.is_some()
+            || args.work_file.is_some()
+            || args.server.is_some())
+    {
+        return Err(anyhow!(
+            "--key-template cannot be combined with subcommands, --benchmark, --resume, --checkpoint, --work-file or --server"
+        ));
     }
 
-    let pubkey = crypto::parse_pubkey(&params.pubkey_str)?;
-    let start = crypto::parse_hex_u256(&params.start_str)?;
-    let range_bits = params.range_bits;
+    if args.benchmark {
+        return run_benchmark(&args);
+    }
 
-    if args.cpu {
-        if !args.quiet && !args.json {
-            info!("Mode: CPU (Software Solver)");
+    match args.command {
+        Some(Command::Work { ref action }) => return run_work_command(action),
+        Some(Command::Server {
+            ref listen,
+            verify_fraction,
+            ban_after,
+        }) => {
+            let validation = net::ValidationPolicy {
+                fraction: verify_fraction.clamp(0.0, 1.0),
+                ban_after: ban_after.max(1),
+            };
+            return run_server(&args, listen, validation);
         }
-
-        let dp_bits = args
-            .dp_bits
-            .unwrap_or_else(|| (range_bits / 2).saturating_sub(2).clamp(8, 20));
-
-        if !args.quiet && !args.json {
-            info!("DP bits: {}", dp_bits);
+        Some(Command::Jumptable { stats, kangaroos }) => {
+            return run_jumptable_command(&args, stats, kangaroos)
+        }
+        Some(Command::Precompute { width_bits, dps }) => {
+            return run_precompute(&args, width_bits, dps)
         }
+        Some(Command::Scan(ref scan)) => return run_scan(&args, scan),
+        None => {}
+    }
 
-        let mut start_be = start;
-        start_be.reverse();
+    if args.algorithm == Some(Algorithm::Bsgs) && bsgs_excluded_options_given(&args) {
+        return Err(anyhow!(
+            "--algorithm bsgs takes no --gpu, --max-ops, --cpu, --cpu-threads, --kangaroos, --dp-bits, --negation, --seed, --base, --resume, --checkpoint, --work-file, --server, --tame-db or --jump-*"
+        ));
+    }
 
-        let mut solver = cpu::CpuKangarooSolver::new(pubkey, start_be, range_bits, dp_bits);
+    // The database fixes the range, DP bits and jump table and holds all the tames
+    if args.tame_db.is_some()
+        && (args.cpu
+            || args.cpu_threads.is_some()
+            || args.negation
+            || args.algorithm.is_some()
+            || args.resume.is_some()
+            || args.checkpoint.is_some()
+            || args.work_file.is_some()
+            || args.server.is_some()
+            || args.pubkey_file.is_some()
+            || args.dp_bits.is_some()
+            || jump_options_given(&args))
+    {
+        return Err(anyhow!(
+            "--tame-db cannot be combined with --cpu, --cpu-threads, --negation, --algorithm, --resume, --checkpoint, --work-file, --server, --pubkey-file, --dp-bits or --jump-*"
+        ));
+    }
 
-        let expected_ops = (1u128 << (range_bits / 2)) as u64;
-        let pb = if args.quiet || args.json {
-            ProgressBar::hidden()
-        } else {
-            let pb = ProgressBar::new(expected_ops);
-            pb.set_style(cli::default_progress_style_with_msg());
-            pb
-        };
+    let resume = match args.resume {
+        Some(ref path) => Some(Checkpoint::load(path)?),
+        None => None,
+    };
 
-        let start_time = Instant::now();
-        let result = solver.solve(std::time::Duration::from_secs(3600));
-        let duration = start_time.elapsed();
+    if args.cpu
+        && (resume.is_some()
+            || args.checkpoint.is_some()
+            || args.work_file.is_some()
+            || args.negation
+            || args.algorithm.is_some()
+            || args.seed.is_some()
+            || jump_options_given(&args))
+    {
+        return Err(anyhow!(
+            "--checkpoint, --resume, --work-file, --negation, --algorithm, --seed and --jump-* are only supported by the GPU solver"
+        ));
+    }
 
-        if let Some(private_key) = result {
-            pb.finish_with_message("FOUND!");
-            let key_hex = hex::encode(&private_key);
-            let key_hex_trimmed = key_hex.trim_start_matches('0');
-            let key_hex_display = if key_hex_trimmed.is_empty() {
-                "0"
-            } else {
-                key_hex_trimmed
-            };
+    // Workers of one search must walk the same table, so they all use the default one
+    if args.server.is_some()
+        && (args.cpu
+            || resume.is_some()
+            || args.checkpoint.is_some()
+            || args.dp_bits.is_some()
+            || args.algorithm.is_some()
+            || jump_options_given(&args))
+    {
+        return Err(anyhow!(
+            "--server cannot be combined with --cpu, --resume, --checkpoint, --dp-bits, --algorithm or --jump-*"
+        ));
+    }
 
-            if args.json {
-                let total_ops = solver.total_ops();
-                let time_seconds = duration.as_secs_f64();
-                let rate = total_ops as f64 / time_seconds;
-
-                let result = BenchmarkResult {
-                    metric: "hash_rate".to_string(),
-                    value: rate,
-                    unit: "ops/s".to_string(),
-                    metadata: Metadata {
-                        device: "cpu".to_string(),
-                        range_bits,
-                        algorithm: "pollard_kangaroo".to_string(),
-                        total_ops,
-                        time_seconds,
-                    },
-                };
-                println!("{}", serde_json::to_string(&result)?);
-            } else if args.quiet {
-                println!("{}", key_hex_display);
-            } else {
-                info!("Private key found: 0x{}", key_hex_display);
-                info!("Verification: SUCCESS");
-                info!("Total operations: {}", solver.total_ops());
-                info!("Time elapsed: {:.2}s", duration.as_secs_f64());
-            }
+    if resume.is_some()
+        && (args.seed.is_some() || args.algorithm.is_some() || jump_options_given(&args))
+    {
+        return Err(anyhow!(
+            "--resume takes the seed, herds and jump table from the checkpoint; drop --seed, --algorithm and --jump-*"
+        ));
+    }
+
+    if let Some(ref path) = args.pubkey_file {
+        return run_multi_target(&args, path);
+    }
 
-            if let Some(ref output) = args.output {
-                std::fs::write(output, &key_hex)?;
+    let client = match args.server {
+        Some(ref addr) => {
+            let client = net::DpClient::connect(addr, &worker_name())?;
+            if !args.quiet && !args.json {
+                info!("Connected to {} as worker {}", addr, client.worker_id());
             }
+            Some(client)
+        }
+        None => None,
+    };
+
+    let params = match (&resume, &client) {
+        (Some(cp), _) => {
+            let fixed = WorkHeader {
+                pubkey: cp.pubkey,
+                range: cp.range,
+                dp_bits: cp.dp_bits,
+                negation: cp.negation,
+                herds: cp.herds,
+                gaudry_schost: cp.gaudry_schost,
+            };
+            resolve_fixed_params(&args, &fixed, "Checkpoint")?
+        }
+        (None, Some(client)) => resolve_fixed_params(&args, client.header(), "Server search")?,
+        (None, None) => resolve_params(&args)?,
+    };
+    let template = match args.key_template {
+        Some(ref template) => Some(template.plan()?),
+        None => None,
+    };
+    let range = template.as_ref().map_or(params.range, |t| t.range);
+
+    if !args.quiet && !args.json {
+        info!("Kangaroo ECDLP Solver");
+        info!("=====================");
+        if let Some(ref target) = args.target {
+            info!("Target: {}", target);
+        }
+        if let Some(ref path) = args.resume {
+            info!("Resuming from: {}", path.display());
+        }
+        info!("Pubkey: {}", params.pubkey_str);
+        if let Some(ref base) = args.base {
+            info!("Base point: {}", base);
+        }
+        if let (Some(ref key_template), Some(ref t)) = (&args.key_template, &template) {
+            info!(
+                "Key template: {} ({} unknown digits, {} targets)",
+                key_template,
+                key_template.unknown_digits(),
+                t.offsets.len()
+            );
+        }
+        info!("Search range: {} ({} bits)", range, range.bits());
+    }
+
+    let pubkey = crypto::parse_pubkey(&params.pubkey_str)?;
+    let base = requested_base(&args)?;
+    if let Some(ref t) = template {
+        if t.offsets.len() > 1 {
+            return run_template_targets(&args, t, &pubkey, &base);
+        }
+    }
+    // A single template target is searched like any key: x in `range`, mapped back when found
+    let pubkey = match template {
+        Some(ref t) => t.targets(&pubkey, &base)[0],
+        None => pubkey,
+    };
+    let template = template.as_ref();
+    let range_bits = range.bits();
+    if use_bsgs(&args, &range) {
+        return run_bsgs(&args, &pubkey, range, template);
+    }
+    let seed = match resume {
+        Some(ref cp) => cp.seed,
+        None => args.seed.unwrap_or_else(cpu::init::random_seed),
+    };
+    let tame_db = match args.tame_db {
+        Some(ref path) => {
+            let db = TameDb::load(path)?;
+            db.covers(&range)?;
+            Some(Arc::new(db))
+        }
+        None => None,
+    };
+
+    if args.cpu {
+        if !args.quiet && !args.json {
+            info!("Mode: CPU (Software Solver)");
+        }
 
-            return Ok(());
+        let dp_bits = args
+            .dp_bits
+            .unwrap_or_else(|| (range_bits / 2).saturating_sub(2).clamp(8, 20));
+
+        if !args.quiet && !args.json {
+            info!("DP bits: {}", dp_bits);
+        }
+
+        let mut solver = cpu::CpuKangarooSolver::with_base(pubkey, base, range, dp_bits);
+
+        // One tame and one wild kangaroo
+        let expected_ops =
+            SearchModel::new(&range, 2, dp_bits, false, Herds::Vow).expected_ops() as u64;
+        let pb = cli::progress_bar(args.quiet || args.json, expected_ops);
+
+        let start_time = Instant::now();
+        let result = solver.solve(std::time::Duration::from_secs(3600));
+        let duration = start_time.elapsed();
+
+        if let Some(private_key) = result {
+            pb.finish_with_message("FOUND!");
+            return report_found_key(
+                &args,
+                &template_key(template, private_key),
+                Metadata {
+                    device: "cpu".to_string(),
+                    range_bits,
+                    algorithm: "pollard_kangaroo".to_string(),
+                    total_ops: solver.total_ops(),
+                    time_seconds: duration.as_secs_f64(),
+                    seed: None,
+                },
+            );
         } else {
             pb.finish_with_message("TIMEOUT");
             return Err(anyhow!("Key not found within timeout"));
         }
     }
 
-    let gpu_context = pollster::block_on(gpu_crypto::GpuContext::new(args.gpu, args.backend))?;
+    let single_device_only = resume.is_some()
+        || args.checkpoint.is_some()
+        || args.work_file.is_some()
+        || client.is_some()
+        || tame_db.is_some();
+    let mut contexts = match pollster::block_on(gpu_crypto::GpuContext::open_many(
+        &gpu_selection(&args),
+        args.backend,
+    )) {
+        Ok(contexts) => contexts,
+        Err(e) if !single_device_only => {
+            warn!("No usable GPU ({:#}); falling back to CPU solving", e);
+            Vec::new()
+        }
+        Err(e) => return Err(e),
+    };
+    let cpu_threads = match args.cpu_threads {
+        Some(threads) => threads,
+        None if contexts.is_empty() => std::thread::available_parallelism()
+            .map(|n| n.get())
+            .unwrap_or(1),
+        None => 0,
+    };
+    if contexts.len() != 1 || cpu_threads > 0 {
+        if single_device_only {
+            return Err(anyhow!(
+                "Several GPUs or CPU workers cannot be combined with --resume, --checkpoint, --work-file, --server or --tame-db"
+            ));
+        }
+        if contexts.is_empty() && cpu_threads == 0 {
+            return Err(anyhow!("No usable GPU and --cpu-threads is 0"));
+        }
+        return run_shared(
+            &args,
+            contexts,
+            cpu_threads,
+            KeySearch {
+                pubkey,
+                base,
+                range,
+                seed,
+                template,
+            },
+        );
+    }
+    let gpu_context = contexts.remove(0);
     let device_name = gpu_context.device_name().to_string();
     if !args.quiet && !args.json {
+        info!("Seed: {}", seed);
         info!("GPU: {}", device_name);
         info!("Compute units: {}", gpu_context.compute_units());
     }
 
-    let num_k = args.kangaroos.unwrap_or(gpu_context.optimal_kangaroos());
-    let dp_bits = args.dp_bits.unwrap_or_else(|| {
-        let auto_dp = (range_bits / 2).saturating_sub((num_k as f64).log2() as u32 / 2);
-        auto_dp.clamp(8, 40)
-    });
+    let (num_k, dp_bits) = match (&resume, &client) {
+        (Some(cp), _) => (cp.num_kangaroos(), cp.dp_bits),
+        (None, Some(client)) => (
+            args.kangaroos.unwrap_or(gpu_context.optimal_kangaroos()),
+            client.header().dp_bits,
+        ),
+        (None, None) => {
+            let num_k = args.kangaroos.unwrap_or(gpu_context.optimal_kangaroos());
+            let dp_bits = match tame_db {
+                Some(ref db) => db.dp_bits,
+                None => args
+                    .dp_bits
+                    .unwrap_or_else(|| solver::auto_dp_bits(range_bits, u64::from(num_k))),
+            };
+            (num_k, dp_bits)
+        }
+    };
 
     if !args.quiet && !args.json {
         info!("DP bits: {}", dp_bits);
         info!("Kangaroos: {}", num_k);
     }
 
-    let mut solver =
-        solver::KangarooSolver::new(gpu_context, pubkey, start, range_bits, dp_bits, num_k)?;
-
-    let expected_ops = (1u128 << (range_bits / 2)) as u64;
-    let pb = if args.quiet || args.json {
-        ProgressBar::hidden()
-    } else {
-        let pb = ProgressBar::new(expected_ops);
-        pb.set_style(cli::default_progress_style());
-        pb
+    let mut solver = match (&resume, client) {
+        (Some(cp), _) => solver::KangarooSolver::from_checkpoint(gpu_context, cp)?,
+        (None, Some(client)) => {
+            // Every worker needs its own herd, otherwise they all walk the same paths
+            let options = SolverOptions {
+                seed: seed ^ u64::from(client.worker_id()).wrapping_add(1),
+                negation: client.header().negation,
+                herds: client.header().herds,
+                gaudry_schost: client.header().gaudry_schost,
+                ..SolverOptions::default()
+            };
+            let mut solver = solver::KangarooSolver::new_in_range(
+                gpu_context,
+                pubkey,
+                range,
+                dp_bits,
+                num_k,
+                options,
+            )?;
+            solver.set_server(client);
+            solver
+        }
+        (None, None) if tame_db.is_some() => {
+            let db = tame_db.clone().unwrap();
+            if !args.quiet && !args.json {
+                info!(
+                    "Tame DB: {} tame DPs over {} bits ({} ops)",
+                    db.dps.len(),
+                    db.range.bits(),
+                    db.total_ops
+                );
+            }
+            // The wilds search the database's range for the shifted key
+            let options = SolverOptions {
+                seed,
+                jumps: db.jumps,
+                tame_db: Some(db.clone()),
+                ..SolverOptions::default()
+            };
+            solver::KangarooSolver::new_in_range(
+                gpu_context,
+                db.shift(&pubkey, &range),
+                db.range,
+                dp_bits,
+                num_k,
+                options,
+            )?
+        }
+        (None, None) => {
+            let options = SolverOptions {
+                seed,
+                negation: args.negation,
+                herds: requested_herds(&args)?,
+                gaudry_schost: requested_sets(&args)?,
+                jumps: jump_spec(&args, u64::from(num_k)),
+                ..SolverOptions::default()
+            };
+            solver::KangarooSolver::new_with_base(
+                gpu_context,
+                pubkey,
+                base,
+                range,
+                dp_bits,
+                num_k,
+                options,
+            )?
+        }
     };
 
     if !args.quiet && !args.json {
-        info!("Starting search...");
+        let header = solver.work_header();
+        if header.negation {
+            info!("Walk: negation map");
+        }
+        if header.herds != Herds::Vow {
+            info!("Herds: {}", header.herds);
+        }
+        if let Some(sets) = header.gaudry_schost {
+            info!("Algorithm: {}", sets);
+        }
     }
 
-    let max_ops = if args.max_ops == 0 {
-        u64::MAX
-    } else {
-        args.max_ops
-    };
+    let quiet = args.quiet || args.json;
+    let search = cli::SearchLoop::new(
+        quiet,
+        solver.search_model().expected_ops() as u64,
+        args.max_ops,
+    );
+    search.pb.set_position(solver.total_operations());
 
-    let start_time = Instant::now();
+    if let Some(ref path) = args.work_file {
+        let writer = workfile::WorkFileWriter::open(path, &solver.work_header())?;
+        solver.set_work_file(writer);
+        if !quiet {
+            info!("Work file: {}", path.display());
+        }
+    }
+
+    let checkpoint_interval = Duration::from_secs(args.checkpoint_interval.max(1));
+    let mut last_checkpoint = Instant::now();
 
-    loop {
+    if !quiet {
+        info!("Starting search...");
+    }
+
+    let start_time = Instant::now();
+    let outcome = search.run(|pb| {
         let result = solver.step()?;
         let total_ops = solver.total_operations();
         pb.set_position(total_ops);
+        if solver.reseeds() + solver.merges() > 0 {
+            pb.set_message(format!(
+                "{} | reseeds: {} merges: {}",
+                solver.stats(),
+                solver.reseeds(),
+                solver.merges()
+            ));
+        } else {
+            pb.set_message(solver.stats().to_string());
+        }
 
         if let Some(private_key) = result {
-            let duration = start_time.elapsed();
-            pb.finish_with_message("FOUND!");
-            let key_hex = hex::encode(&private_key);
-            let key_hex_trimmed = key_hex.trim_start_matches('0');
-            let key_hex_display = if key_hex_trimmed.is_empty() {
-                "0"
-            } else {
-                key_hex_trimmed
+            let private_key = match tame_db {
+                Some(ref db) => db.unshift(&private_key, &range),
+                None => private_key,
             };
+            if crypto::verify_key_with_base(&private_key, &pubkey, &base) {
+                return Ok((total_ops, Some(private_key)));
+            }
+            error!("Verification FAILED - this is a bug!");
+        }
 
-            if !crypto::verify_key(&private_key, &pubkey) {
-                error!("Verification FAILED - this is a bug!");
-                continue;
+        if let Some(ref path) = args.checkpoint {
+            if last_checkpoint.elapsed() >= checkpoint_interval {
+                write_checkpoint(&mut solver, path, quiet)?;
+                last_checkpoint = Instant::now();
             }
+        }
+        Ok((total_ops, None))
+    })?;
+    let duration = start_time.elapsed();
+
+    match outcome {
+        Ok(private_key) => {
+            search.pb.finish_with_message("FOUND!");
+            if !quiet {
+                info!("Reseeded kangaroos: {}", solver.reseeds());
+                info!("Merged kangaroos respawned: {}", solver.merges());
+                info!("Deferred DPs (buffer full): {}", solver.dp_overflows());
+            }
+            report_found_key(
+                &args,
+                &template_key(template, private_key),
+                Metadata {
+                    device: device_name,
+                    range_bits,
+                    algorithm: json_algorithm(&args),
+                    total_ops: solver.total_operations(),
+                    time_seconds: duration.as_secs_f64(),
+                    seed: Some(solver.seed()),
+                },
+            )
+        }
+        Err(stop) => {
+            if let Some(ref path) = args.checkpoint {
+                write_checkpoint(&mut solver, path, quiet)?;
+            }
+            Err(anyhow!("Key not found: {}", stop))
+        }
+    }
+}
 
-            if args.json {
-                let time_seconds = duration.as_secs_f64();
-                let rate = total_ops as f64 / time_seconds;
-
-                let result = BenchmarkResult {
-                    metric: "hash_rate".to_string(),
-                    value: rate,
-                    unit: "ops/s".to_string(),
-                    metadata: Metadata {
-                        device: device_name,
-                        range_bits,
-                        algorithm: "pollard_kangaroo".to_string(),
-                        total_ops,
-                        time_seconds,
-                    },
-                };
-                println!("{}", serde_json::to_string(&result)?);
-            } else if args.quiet {
-                println!("{}", key_hex_display);
-            } else {
-                info!("Private key found: 0x{}", key_hex_display);
-                info!("Verification: SUCCESS");
-                info!("Total operations: {}", total_ops);
-                info!("Time elapsed: {:.2}s", duration.as_secs_f64());
+/// Tame kangaroos only over `[0, 2^width_bits)` until `dps` tame DPs are stored,
+/// then saved to `-o` for --tame-db
+fn run_precompute(args: &Args, width_bits: u32, dps: Option<u64>) -> anyhow::Result<()> {
+    let output = args
+        .output
+        .as_ref()
+        .ok_or_else(|| anyhow!("precompute needs -o/--output for the database"))?;
+    let dp_bits = args
+        .dp_bits
+        .ok_or_else(|| anyhow!("precompute needs --dp-bits"))?;
+    if !(1..=256).contains(&width_bits) {
+        return Err(anyhow!("--width-bits must be 1 - 256"));
+    }
+    if args.cpu || args.cpu_threads.is_some() || args.negation || args.algorithm.is_some() {
+        return Err(anyhow!(
+            "precompute walks plain tame kangaroos on one GPU; drop --cpu, --cpu-threads, --negation and --algorithm"
+        ));
+    }
+    let goal = dps.unwrap_or_else(|| tamedb::default_dps(width_bits, dp_bits));
+    let range = tamedb::precompute_range(width_bits);
+
+    let gpu_context = pollster::block_on(gpu_crypto::GpuContext::new(
+        gpu_selection(args).first(),
+        args.backend,
+    ))?;
+    let num_k = args.kangaroos.unwrap_or(gpu_context.optimal_kangaroos());
+    let seed = args.seed.unwrap_or_else(cpu::init::random_seed);
+    if !args.quiet {
+        info!("Tame precomputation");
+        info!("===================");
+        info!("Range: {} ({} bits)", range, width_bits);
+        info!("DP bits: {}", dp_bits);
+        info!("Tame DPs: {}", goal);
+        info!("Seed: {}", seed);
+        info!("GPU: {}", gpu_context.device_name());
+        info!("Kangaroos: {}", num_k);
+    }
+
+    let options = SolverOptions {
+        seed,
+        jumps: jump_spec(args, u64::from(num_k)),
+        ..SolverOptions::default()
+    };
+    let mut solver =
+        solver::KangarooSolver::precompute(gpu_context, range, dp_bits, num_k, options)?;
+
+    // The bar counts tame DPs, the limit operations
+    let search = cli::SearchLoop::new(args.quiet, goal, args.max_ops);
+    let outcome = search.run(|pb| {
+        solver.step_targets()?;
+        let stats = solver.stats();
+        pb.set_position(stats.tame_dps as u64);
+        pb.set_message(format!("{:.2}M/s", stats.ops_per_sec / 1e6));
+        let done = stats.tame_dps as u64 >= goal;
+        Ok((solver.total_operations(), done.then_some(())))
+    })?;
+    if outcome.is_ok() {
+        search.pb.finish_with_message("DONE");
+    }
+
+    // Whatever was collected is usable, only with more wild steps
+    let db = solver.tame_db()?;
+    db.save(std::path::Path::new(output))?;
+    if !args.quiet {
+        info!(
+            "Saved {} tame DPs ({} ops) to {}",
+            db.dps.len(),
+            db.total_ops,
+            output
+        );
+    }
+    outcome.map_err(|stop| {
+        anyhow!(
+            "Precomputation {}; the database holds fewer tames than asked for",
+            stop
+        )
+    })
+}
+
+/// Hash160 brute force over the requested range, for targets without a public key
+fn run_scan(args: &Args, scan: &ScanArgs) -> anyhow::Result<()> {
+    let form = scan.form;
+    let progress_path = scan.progress.as_deref();
+    if args.pubkey.is_some() || args.pubkey_file.is_some() {
+        return Err(anyhow!(
+            "scan looks for hash160s; search a known public key without `scan`"
+        ));
+    }
+    if kangaroo_options_given(args) || args.algorithm.is_some() {
+        return Err(anyhow!(
+            "scan hashes every key; drop --cpu, --cpu-threads, --kangaroos, --dp-bits, --negation, --seed, --resume, --checkpoint, --work-file, --server, --tame-db, --algorithm and --jump-*"
+        ));
+    }
+
+    let provider_result = match args.target {
+        Some(ref target) => provider::resolve(target)?,
+        None => None,
+    };
+    let mut hashes = Vec::new();
+    for hash160 in &scan.hash160 {
+        hashes.push(ScanTargets::parse_hash160(hash160)?);
+    }
+    for address in &scan.address {
+        hashes.push(ScanTargets::parse_address(address)?);
+    }
+    if let Some(ref path) = scan.targets_file {
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| anyhow!("Failed to read {}: {}", path.display(), e))?;
+        hashes.extend(ScanTargets::parse_list(&text)?);
+    }
+    if hashes.is_empty() {
+        if let Some(ref address) = provider_result.as_ref().and_then(|pr| pr.address.clone()) {
+            hashes.push(ScanTargets::parse_address(address)?);
+        }
+    }
+    if hashes.is_empty() {
+        return Err(anyhow!(
+            "scan needs --hash160, --address, --targets-file or a --target with an address"
+        ));
+    }
+    let targets = ScanTargets::new(hashes)?;
+
+    let range = match provider_result {
+        Some(ref pr) => provider_search_range(args, pr)?,
+        None => requested_range(args, None)?
+            .ok_or_else(|| anyhow!("scan needs --range, --center/--radius or --target"))?,
+    };
+
+    let resume = match progress_path {
+        Some(path) if path.exists() => Some(ScanProgress::load(path)?),
+        _ => None,
+    };
+
+    let gpu_context = pollster::block_on(gpu_crypto::GpuContext::new(
+        gpu_selection(args).first(),
+        args.backend,
+    ))?;
+    let device = gpu_context.device_name().to_string();
+    let threads = scan.threads.unwrap_or(gpu_context.optimal_kangaroos());
+    let resumed = resume.is_some();
+    let mut scanner =
+        Hash160Scanner::new(gpu_context, range, targets.clone(), form, threads, resume)?;
+    if !args.quiet {
+        info!("Hash160 scan");
+        info!("============");
+        info!("Range: {} ({} keys)", range, scanner.keys_total());
+        info!("Targets: {}", targets.len());
+        info!("Form: {:?}", form);
+        info!("GPU: {}", device);
+        info!("Threads: {}", scanner.threads());
+        if resumed {
+            info!("Resuming after {} keys", scanner.keys_done());
+        }
+    }
+
+    // Every hashed key is one operation
+    let search = cli::SearchLoop::new(
+        args.quiet,
+        u64::try_from(scanner.keys_total()).unwrap_or(u64::MAX),
+        args.max_ops,
+    );
+    let save_interval = Duration::from_secs(args.checkpoint_interval.max(1));
+    let mut last_save = Instant::now();
+    let start_time = Instant::now();
+    let mut rate = RateMeter::new();
+    let mut reported = 0;
+
+    // The result says whether every target was found (else the range ran out)
+    let outcome = search.run(|pb| {
+        scanner.step()?;
+        let done = u64::try_from(scanner.keys_done()).unwrap_or(u64::MAX);
+        pb.set_position(done);
+        let keys_per_sec = rate.update(done, Instant::now());
+        pb.set_message(format!("{:.2}M keys/s", keys_per_sec / 1e6));
+        for found in &scanner.found()[reported..] {
+            pb.println(format!(
+                "Found key 0x{} for {}",
+                hex::encode(&found.key),
+                crypto::pubkey_hash_to_address(&found.hash160)
+            ));
+        }
+        reported = scanner.found().len();
+
+        if let Some(path) = progress_path {
+            if last_save.elapsed() >= save_interval {
+                scanner.progress().save(path)?;
+                last_save = Instant::now();
             }
+        }
+        let finished = if scanner.all_found() {
+            Some(true)
+        } else if scanner.is_finished() {
+            Some(false)
+        } else {
+            None
+        };
+        Ok((done, finished))
+    })?;
+    let stopped = match outcome {
+        Ok(true) => {
+            search.pb.finish_with_message("FOUND!");
+            None
+        }
+        Ok(false) => {
+            search.pb.finish_with_message("RANGE DONE");
+            Some(format!("No key in {} hashes to a target", range))
+        }
+        Err(stop) => Some(format!("Scan {}", stop)),
+    };
+    if let Some(path) = progress_path {
+        scanner.progress().save(path)?;
+        if !args.quiet && stopped.is_some() {
+            info!("Progress saved to {}", path.display());
+        }
+    }
 
-            if let Some(ref output) = args.output {
-                std::fs::write(output, &key_hex)?;
-                if !args.quiet && !args.json {
-                    info!("Result written to: {}", output);
-                }
+    let duration = start_time.elapsed();
+    let keys = u64::try_from(scanner.keys_done()).unwrap_or(u64::MAX);
+    for found in scanner.found() {
+        report_found_key(
+            args,
+            &found.key,
+            Metadata {
+                device: device.clone(),
+                range_bits: range.bits(),
+                algorithm: "hash160-scan".to_string(),
+                total_ops: keys,
+                time_seconds: duration.as_secs_f64(),
+                seed: None,
+            },
+        )?;
+    }
+    match stopped {
+        Some(reason) if scanner.found().is_empty() => Err(anyhow!(reason)),
+        _ => Ok(()),
+    }
+}
+
+/// Baby-step giant-step on every core: finds the key or proves it is not in `range`
+fn run_bsgs(
+    args: &Args,
+    pubkey: &crypto::Point,
+    range: KeyRange,
+    template: Option<&TemplateSearch>,
+) -> anyhow::Result<()> {
+    let quiet = args.quiet || args.json;
+    let memory_mib = args.bsgs_memory.unwrap_or(cpu::bsgs::DEFAULT_MEMORY_MIB);
+
+    let start_time = Instant::now();
+    let bsgs = cpu::BsgsSolver::new(range, memory_mib.saturating_mul(1 << 20))?;
+    if !quiet {
+        info!("Algorithm: baby-step giant-step (CPU)");
+        info!(
+            "Baby steps: {} ({:.1} MiB table, built in {:.2}s)",
+            bsgs.baby_steps(),
+            bsgs.table_bytes() as f64 / f64::from(1 << 20),
+            start_time.elapsed().as_secs_f64()
+        );
+        info!("Giant steps: up to {}", bsgs.giant_steps());
+    }
+
+    let pb = cli::progress_bar(quiet, bsgs.giant_steps());
+    let result = bsgs.solve(pubkey, |steps| pb.inc(steps));
+    let duration = start_time.elapsed();
+    let total_ops = bsgs.baby_steps() + pb.position();
+
+    match result {
+        Some(private_key) => {
+            pb.finish_with_message("FOUND!");
+            if !crypto::verify_key(&private_key, pubkey) {
+                return Err(anyhow!("Verification FAILED - this is a bug!"));
             }
+            report_found_key(
+                args,
+                &template_key(template, private_key),
+                Metadata {
+                    device: "cpu".to_string(),
+                    range_bits: range.bits(),
+                    algorithm: "bsgs".to_string(),
+                    total_ops,
+                    time_seconds: duration.as_secs_f64(),
+                    seed: None,
+                },
+            )
+        }
+        None => {
+            pb.finish_with_message("NOT IN RANGE");
+            if !quiet {
+                info!(
+                    "Checked every key in {:.2}s ({} operations)",
+                    duration.as_secs_f64(),
+                    total_ops
+                );
+            }
+            Err(anyhow!("Key is not in {}", range))
+        }
+    }
+}
 
-            return Ok(());
+/// Print (and optionally save) a verified key in the format the flags ask for;
+/// `run` describes the search that found it
+fn report_found_key(args: &Args, private_key: &[u8], run: Metadata) -> anyhow::Result<()> {
+    let key_hex = hex::encode(private_key);
+    let key_hex_trimmed = key_hex.trim_start_matches('0');
+    let key_hex_display = if key_hex_trimmed.is_empty() {
+        "0"
+    } else {
+        key_hex_trimmed
+    };
+
+    if args.json {
+        let result = BenchmarkResult {
+            metric: "hash_rate".to_string(),
+            value: run.total_ops as f64 / run.time_seconds,
+            unit: "ops/s".to_string(),
+            metadata: run,
+        };
+        println!("{}", serde_json::to_string(&result)?);
+    } else if args.quiet {
+        println!("{}", key_hex_display);
+    } else {
+        info!("Private key found: 0x{}", key_hex_display);
+        info!("Verification: SUCCESS");
+        info!("Total operations: {}", run.total_ops);
+        info!("Time elapsed: {:.2}s", run.time_seconds);
+        if let Some(seed) = run.seed {
+            info!("Seed: {}", seed);
         }
+    }
 
-        if total_ops >= max_ops {
-            pb.finish_with_message("LIMIT REACHED");
-            if !args.quiet && !args.json {
+    if let Some(ref output) = args.output {
+        std::fs::write(output, &key_hex)?;
+        if !args.quiet && !args.json {
+            info!("Result written to: {}", output);
+        }
+    }
+
+    Ok(())
+}
+
+/// A key search as `run` resolved it from the flags
+struct KeySearch<'a> {
+    pubkey: Point,
+    base: Point,
+    range: KeyRange,
+    seed: u64,
+    template: Option<&'a TemplateSearch>,
+}
+
+/// One solver per GPU plus `cpu_threads` CPU workers, all feeding one DP table
+/// (the first GPU's, or a fresh one when there is no GPU)
+fn run_shared(
+    args: &Args,
+    contexts: Vec<gpu_crypto::GpuContext>,
+    cpu_threads: usize,
+    key_search: KeySearch,
+) -> anyhow::Result<()> {
+    let KeySearch {
+        pubkey,
+        base,
+        range,
+        seed,
+        template,
+    } = key_search;
+    let range_bits = range.bits();
+    let quiet = args.quiet || args.json;
+    let herds = requested_herds(args)?;
+    let sets = requested_sets(args)?;
+    if sets.is_some() && (contexts.is_empty() || cpu_threads > 0) {
+        return Err(anyhow!(
+            "--algorithm gaudry-schost runs on GPUs only; drop --cpu-threads"
+        ));
+    }
+    let mut names: Vec<String> = contexts
+        .iter()
+        .map(|ctx| ctx.device_name().to_string())
+        .collect();
+    let herd_sizes: Vec<u32> = contexts
+        .iter()
+        .map(|ctx| args.kangaroos.unwrap_or(ctx.optimal_kangaroos()))
+        .collect();
+    let total_kangaroos: u64 = herd_sizes.iter().map(|&k| u64::from(k)).sum::<u64>()
+        + cpu_threads as u64 * u64::from(cpu::CPU_HERD_SIZE);
+    let dp_bits = args
+        .dp_bits
+        .unwrap_or_else(|| solver::auto_dp_bits(range_bits, total_kangaroos));
+
+    if !quiet {
+        for (index, (name, num_k)) in names.iter().zip(&herd_sizes).enumerate() {
+            info!("GPU {}: {} ({} kangaroos)", index, name, num_k);
+        }
+        if cpu_threads > 0 {
+            info!(
+                "CPU: {} threads ({} kangaroos each)",
+                cpu_threads,
+                cpu::CPU_HERD_SIZE
+            );
+        }
+        info!("DP bits: {}", dp_bits);
+        info!("Seed: {}", seed);
+        if args.negation {
+            info!("Walk: negation map");
+        }
+        if herds != Herds::Vow {
+            info!("Herds: {}", herds);
+        }
+        if let Some(sets) = sets {
+            info!("Algorithm: {}", sets);
+        }
+    }
+
+    let jumps = jump_spec(args, total_kangaroos);
+    // Each device takes the next slice of one start grid over all kangaroos
+    let mut next_slice = 0u64;
+    let mut grid_slice = |num_k: u32| {
+        let first = next_slice;
+        next_slice += u64::from(num_k);
+        Some(cpu::init::GridSlice {
+            first,
+            total: total_kangaroos,
+        })
+    };
+    let mut solvers: Vec<solver::KangarooSolver> = Vec::with_capacity(contexts.len());
+    for (index, (ctx, num_k)) in contexts.into_iter().zip(&herd_sizes).enumerate() {
+        let options = SolverOptions {
+            seed: multi_gpu::device_seed(seed, index),
+            grid: grid_slice(*num_k),
+            negation: args.negation,
+            herds,
+            gaudry_schost: sets,
+            jumps,
+            base,
+            ..SolverOptions::default()
+        };
+        let mut solver =
+            solver::KangarooSolver::new_in_range(ctx, pubkey, range, dp_bits, *num_k, options)?;
+        if let Some(first) = solvers.first() {
+            solver.set_dp_table(first.shared_dp_table());
+        }
+        solvers.push(solver);
+    }
+
+    let dp_table = match solvers.first() {
+        Some(first) => first.shared_dp_table(),
+        None => Arc::new(cpu::DPTable::for_search(
+            vec![pubkey],
+            range,
+            args.negation,
+            herds,
+            base,
+        )),
+    };
+    let walker = Arc::new(cpu::CpuWalker::with_base(
+        &range,
+        &jumps,
+        &base,
+        dp_bits,
+        args.negation,
+        solver::stuck_steps(dp_bits),
+    )?);
+    let layout =
+        cpu::init::HerdLayout::for_search(vec![pubkey], range, args.negation, herds, base)?;
+
+    let gpus = solvers.len();
+    let mut devices: Vec<Box<dyn multi_gpu::Device>> = solvers
+        .into_iter()
+        .map(|solver| Box::new(solver) as Box<dyn multi_gpu::Device>)
+        .collect();
+    for index in 0..cpu_threads {
+        devices.push(Box::new(cpu::CpuWorker::new(
+            walker.clone(),
+            layout.clone(),
+            dp_table.clone(),
+            index as u32,
+            multi_gpu::cpu_worker_seed(seed, index),
+            grid_slice(cpu::CPU_HERD_SIZE),
+        )));
+    }
+    if cpu_threads > 0 {
+        names.push("cpu".to_string());
+    }
+
+    let model = match sets {
+        Some(sets) => SearchModel::gaudry_schost(&range, total_kangaroos, dp_bits, sets),
+        None => SearchModel::new(&range, total_kangaroos, dp_bits, args.negation, herds),
+    };
+    let mut rate = RateMeter::new();
+    let search = cli::SearchLoop::new(quiet, model.expected_ops() as u64, args.max_ops);
+    let pb = &search.pb;
+
+    if !quiet {
+        info!("Starting search on {}...", names.join(" + "));
+    }
+    let start_time = Instant::now();
+    let mut last_log = Instant::now();
+    let mut last_ops = vec![0u64; devices.len()];
+    let mut total_ops = 0u64;
+    let mut stopped = None;
+
+    // The devices run their own loops; the report callback is where they get stopped
+    let result = multi_gpu::run_devices(devices, |ops| {
+        total_ops = ops.iter().sum();
+        pb.set_position(total_ops);
+        let ops_per_sec = rate.update(total_ops, Instant::now());
+        pb.set_message(
+            SearchStats::new(&model, total_ops, ops_per_sec, dp_table.count_by_type()).to_string(),
+        );
+
+        if last_log.elapsed() >= Duration::from_secs(10) {
+            let window = last_log.elapsed().as_secs_f64();
+            let deltas: Vec<u64> = ops.iter().zip(&last_ops).map(|(n, b)| n - b).collect();
+            let mut rates: Vec<String> = deltas[..gpus]
+                .iter()
+                .enumerate()
+                .map(|(i, delta)| format!("GPU {}: {:.2}M/s", i, *delta as f64 / window / 1e6))
+                .collect();
+            if cpu_threads > 0 {
+                let cpu_delta: u64 = deltas[gpus..].iter().sum();
+                rates.push(format!("CPU: {:.2}M/s", cpu_delta as f64 / window / 1e6));
+            }
+            let aggregate: u64 = deltas.iter().sum();
+            if !quiet {
                 info!(
-                    "Maximum operations reached ({}) without finding key",
-                    max_ops
+                    "{} | Total: {:.2}M/s",
+                    rates.join(" | "),
+                    aggregate as f64 / window / 1e6
                 );
             }
-            return Err(anyhow!("Key not found within {} operations", max_ops));
+            last_ops = ops.to_vec();
+            last_log = Instant::now();
+        }
+
+        if stopped.is_none() {
+            stopped = search.check(total_ops);
+        }
+        stopped.is_none()
+    })?;
+    let duration = start_time.elapsed();
+
+    match (result, stopped) {
+        (Some(private_key), _) => {
+            pb.finish_with_message("FOUND!");
+            if !crypto::verify_key_with_base(&private_key, &pubkey, &base) {
+                return Err(anyhow!("Verification FAILED - this is a bug!"));
+            }
+            report_found_key(
+                args,
+                &template_key(template, private_key),
+                Metadata {
+                    device: names.join(" + "),
+                    range_bits,
+                    algorithm: json_algorithm(args),
+                    total_ops,
+                    time_seconds: duration.as_secs_f64(),
+                    seed: Some(seed),
+                },
+            )
+        }
+        (None, Some(stop)) => Err(anyhow!("Key not found: {}", stop)),
+        (None, None) => Err(anyhow!("Devices stopped without a key")),
+    }
+}
+
+/// Solve all pubkeys of a file with one shared tame herd
+fn run_multi_target(args: &Args, path: &std::path::Path) -> anyhow::Result<()> {
+    if args.pubkey.is_some()
+        || args.target.is_some()
+        || args.cpu
+        || args.json
+        || args.resume.is_some()
+        || args.checkpoint.is_some()
+        || args.work_file.is_some()
+        || args.server.is_some()
+        || matches!(
+            args.algorithm,
+            Some(Algorithm::Three | Algorithm::Four | Algorithm::Bsgs)
+        )
+        || !gpu_selection(args).is_single()
+    {
+        return Err(anyhow!(
+            "--pubkey-file cannot be combined with --pubkey, --target, --cpu, --json, --resume, --checkpoint, --work-file, --server, --algorithm three/four/bsgs or several GPUs"
+        ));
+    }
+
+    let text = std::fs::read_to_string(path)
+        .map_err(|e| anyhow!("Cannot read {}: {}", path.display(), e))?;
+    let pubkeys =
+        crypto::parse_pubkey_list(&text).map_err(|e| anyhow!("{}: {:#}", path.display(), e))?;
+    let range = requested_or_default_range(args)?;
+    let range_bits = range.bits();
+    let seed = args.seed.unwrap_or_else(cpu::init::random_seed);
+
+    if !args.quiet {
+        info!("Kangaroo ECDLP Solver");
+        info!("=====================");
+        info!("Targets: {} pubkeys from {}", pubkeys.len(), path.display());
+        info!("Search range: {} ({} bits)", range, range_bits);
+        info!("Seed: {}", seed);
+    }
+
+    let gpu_context = pollster::block_on(gpu_crypto::GpuContext::new(
+        gpu_selection(args).first(),
+        args.backend,
+    ))?;
+    let num_k = args.kangaroos.unwrap_or(gpu_context.optimal_kangaroos());
+    let dp_bits = args
+        .dp_bits
+        .unwrap_or_else(|| solver::auto_dp_bits(range_bits, u64::from(num_k)));
+    if !args.quiet {
+        info!("GPU: {}", gpu_context.device_name());
+        info!("DP bits: {}", dp_bits);
+        info!("Kangaroos: {}", num_k);
+    }
+
+    let options = SolverOptions {
+        seed,
+        negation: args.negation,
+        gaudry_schost: requested_sets(args)?,
+        jumps: jump_spec(args, u64::from(num_k)),
+        base: requested_base(args)?,
+        ..SolverOptions::default()
+    };
+    let mut solver =
+        solver::KangarooSolver::new_multi(gpu_context, pubkeys, range, dp_bits, num_k, options)?;
+
+    let total = solver.targets().len();
+    let search = cli::SearchLoop::new(
+        args.quiet,
+        solver.search_model().expected_ops() as u64,
+        args.max_ops,
+    );
+
+    let mut results = String::new();
+    let outcome = search.run(|pb| {
+        let solved = solver.step_targets()?;
+        let total_ops = solver.total_operations();
+        pb.set_position(total_ops);
+        pb.set_message(format!(
+            "{} | solved: {}/{}",
+            solver.stats(),
+            solver.targets().solved(),
+            total
+        ));
+
+        for (target, key) in solved {
+            let pubkey_hex = hex::encode(
+                solver.targets().pubkeys()[target]
+                    .to_affine()
+                    .to_encoded_point(true)
+                    .as_bytes(),
+            );
+            let key_hex = hex::encode(&key);
+            results.push_str(&format!("{} {}\n", pubkey_hex, key_hex));
+            if args.quiet {
+                println!("{} {}", pubkey_hex, key_hex);
+            } else {
+                info!("Target {} ({}) solved: 0x{}", target, pubkey_hex, key_hex);
+            }
+        }
+        Ok((
+            total_ops,
+            solver.targets().all_solved().then_some(total_ops),
+        ))
+    })?;
+    let outcome = match outcome {
+        Ok(total_ops) => {
+            search.pb.finish_with_message("ALL FOUND");
+            if !args.quiet {
+                info!("All {} targets solved in {} operations", total, total_ops);
+            }
+            Ok(())
+        }
+        Err(stop) => Err(anyhow!(
+            "{} of {} targets solved; {}",
+            solver.targets().solved(),
+            total,
+            stop
+        )),
+    };
+
+    if let Some(ref output) = args.output {
+        std::fs::write(output, &results)?;
+        if !args.quiet {
+            info!("Results written to: {}", output);
+        }
+    }
+    outcome
+}
+
+/// The key a search found: `x` mapped back through the --key-template target
+/// that was searched, or as is without a template
+fn template_key(template: Option<&TemplateSearch>, x: Vec<u8>) -> Vec<u8> {
+    match template {
+        Some(template) => template.key(0, &x),
+        None => x,
+    }
+}
+
+/// A key template whose extra ? digits expand into several targets: one
+/// multi-target search over the template's interval, done at the first solve
+fn run_template_targets(
+    args: &Args,
+    template: &TemplateSearch,
+    pubkey: &Point,
+    base: &Point,
+) -> anyhow::Result<()> {
+    if args.cpu
+        || args.cpu_threads.is_some()
+        || args.tame_db.is_some()
+        || matches!(
+            args.algorithm,
+            Some(Algorithm::Three | Algorithm::Four | Algorithm::Bsgs)
+        )
+        || !gpu_selection(args).is_single()
+    {
+        return Err(anyhow!(
+            "A key template with several runs of ? cannot be combined with --cpu, --cpu-threads, --tame-db, --algorithm three/four/bsgs or several GPUs"
+        ));
+    }
+
+    let range = template.range;
+    let range_bits = range.bits();
+    let seed = args.seed.unwrap_or_else(cpu::init::random_seed);
+    let quiet = args.quiet || args.json;
+
+    let gpu_context = pollster::block_on(gpu_crypto::GpuContext::new(
+        gpu_selection(args).first(),
+        args.backend,
+    ))?;
+    let device_name = gpu_context.device_name().to_string();
+    let num_k = args.kangaroos.unwrap_or(gpu_context.optimal_kangaroos());
+    let dp_bits = args
+        .dp_bits
+        .unwrap_or_else(|| solver::auto_dp_bits(range_bits, u64::from(num_k)));
+    if !quiet {
+        info!("Seed: {}", seed);
+        info!("GPU: {}", device_name);
+        info!("DP bits: {}", dp_bits);
+        info!("Kangaroos: {}", num_k);
+    }
+
+    let options = SolverOptions {
+        seed,
+        negation: args.negation,
+        gaudry_schost: requested_sets(args)?,
+        jumps: jump_spec(args, u64::from(num_k)),
+        base: *base,
+        ..SolverOptions::default()
+    };
+    let mut solver = solver::KangarooSolver::new_multi(
+        gpu_context,
+        template.targets(pubkey, base),
+        range,
+        dp_bits,
+        num_k,
+        options,
+    )?;
+
+    let search = cli::SearchLoop::new(
+        quiet,
+        solver.search_model().expected_ops() as u64,
+        args.max_ops,
+    );
+    let start_time = Instant::now();
+    // Only the target of the key's actual digits has a solution
+    let outcome = search.run(|pb| {
+        let solved = solver.step_targets()?;
+        let total_ops = solver.total_operations();
+        pb.set_position(total_ops);
+        pb.set_message(solver.stats().to_string());
+        Ok((total_ops, solved.into_iter().next()))
+    })?;
+    let duration = start_time.elapsed();
+
+    let (target, x) = outcome.map_err(|stop| anyhow!("Key not found: {}", stop))?;
+    search.pb.finish_with_message("FOUND!");
+    let private_key = template.key(target, &x);
+    if !crypto::verify_key_with_base(&private_key, pubkey, base) {
+        return Err(anyhow!("Verification FAILED - this is a bug!"));
+    }
+    report_found_key(
+        args,
+        &private_key,
+        Metadata {
+            device: device_name,
+            range_bits,
+            algorithm: json_algorithm(args),
+            total_ops: solver.total_operations(),
+            time_seconds: duration.as_secs_f64(),
+            seed: Some(seed),
+        },
+    )
+}
+
+/// Name a worker reports to the server, for its logs
+fn worker_name() -> String {
+    std::env::var("HOSTNAME")
+        .or_else(|_| std::env::var("COMPUTERNAME"))
+        .map(|host| format!("{}/{}", host, std::process::id()))
+        .unwrap_or_else(|_| format!("pid {}", std::process::id()))
+}
+
+fn run_server(args: &Args, listen: &str, validation: net::ValidationPolicy) -> anyhow::Result<()> {
+    let params = resolve_params(args)?;
+    let pubkey = crypto::parse_pubkey(&params.pubkey_str)?;
+    let range = params.range;
+    let range_bits = range.bits();
+    // Kangaroo count is unknown up front, so assume a few GPUs worth (~2^20 total)
+    let dp_bits = args
+        .dp_bits
+        .unwrap_or_else(|| solver::auto_dp_bits(range_bits, 1 << 20));
+
+    let header = WorkHeader {
+        pubkey,
+        range,
+        dp_bits,
+        negation: args.negation,
+        herds: requested_herds(args)?,
+        gaudry_schost: requested_sets(args)?,
+    };
+
+    let mut previous = Vec::new();
+    let work_file = match args.work_file {
+        Some(ref path) => {
+            if path.exists() {
+                let (existing, dps) = workfile::read_work_file(path)?;
+                if existing.same_search(&header) {
+                    previous = dps;
+                }
+            }
+            // open() rejects a file for another search
+            Some(workfile::WorkFileWriter::open(path, &header)?)
         }
+        None => None,
+    };
+
+    let server = net::DpServer::bind(listen, header, work_file, validation.clone())?;
+    if !args.quiet {
+        info!("Kangaroo DP Server");
+        info!("==================");
+        info!("Pubkey: {}", params.pubkey_str);
+        info!("Search range: {} ({} bits)", range, range_bits);
+        info!("DP bits: {}", dp_bits);
+        info!(
+            "Spot-checking {:.1}% of DPs, ban after {} failures",
+            validation.fraction * 100.0,
+            validation.ban_after
+        );
+        info!("Listening on {}", server.local_addr()?);
     }
+
+    let start_time = Instant::now();
+    let key = match server.preload(&previous) {
+        Some(key) => key,
+        None => {
+            if !args.quiet && !previous.is_empty() {
+                info!("Loaded {} DPs from work file", previous.len());
+            }
+            server.run()?
+        }
+    };
+
+    let key_hex = hex::encode(&key);
+    let key_hex_display = match key_hex.trim_start_matches('0') {
+        "" => "0",
+        s => s,
+    };
+    if args.quiet {
+        println!("{}", key_hex_display);
+    } else {
+        info!("Private key found: 0x{}", key_hex_display);
+        info!("Total DPs: {}", server.total_dps());
+        for source in server.source_stats() {
+            info!(
+                "  {} '{}': {} accepted, {} checked, {} rejected",
+                source.addr, source.name, source.accepted, source.checked, source.rejected
+            );
+        }
+        info!("Time elapsed: {:.2}s", start_time.elapsed().as_secs_f64());
+    }
+    if let Some(ref output) = args.output {
+        std::fs::write(output, &key_hex)?;
+    }
+    Ok(())
+}
+
+fn write_checkpoint(
+    solver: &mut solver::KangarooSolver,
+    path: &std::path::Path,
+    quiet: bool,
+) -> anyhow::Result<()> {
+    solver.checkpoint()?.save(path)?;
+    if !quiet {
+        info!(
+            "Checkpoint written: {} ({} ops)",
+            path.display(),
+            solver.total_operations()
+        );
+    }
+    Ok(())
 }
 
-#[cfg(all(test, feature = "boha"))]
+#[cfg(test)]
 mod tests {
     use super::*;
 
@@ -638,22 +2363,29 @@ mod tests {
         provider::ProviderResult {
             id: "test/1".to_string(),
             pubkey: None,
+            address: None,
             start: Some(start.to_string()),
             end: Some(end.to_string()),
             range_bits: Some(66),
         }
     }
 
+    fn validate(start: &str, range_bits: u32, pr: &provider::ProviderResult) -> anyhow::Result<()> {
+        let range = KeyRange::from_bits(crypto::parse_hex_u256(start)?, range_bits);
+        let bounds = provider_range(pr)?.unwrap();
+        validate_search_bounds(&range, &bounds, &pr.id)
+    }
+
     #[test]
     fn test_validate_search_bounds_valid() {
         let pr = make_provider_result("20000000000000000", "40000000000000000");
-        assert!(validate_search_bounds("20000000000000000", 64, &pr).is_ok());
+        assert!(validate("20000000000000000", 64, &pr).is_ok());
     }
 
     #[test]
     fn test_validate_search_bounds_start_below_minimum() {
         let pr = make_provider_result("20000000000000000", "40000000000000000");
-        let result = validate_search_bounds("10000000000000000", 64, &pr);
+        let result = validate("10000000000000000", 64, &pr);
         assert!(result.is_err());
         assert!(result.unwrap_err().to_string().contains("below"));
     }
@@ -662,16 +2394,46 @@ mod tests {
     fn test_validate_search_bounds_range_exceeds_end() {
         let pr = make_provider_result("20000000000000000", "40000000000000000");
         // start at 0x30... with range 66 bits would exceed 0x40...
-        let result = validate_search_bounds("30000000000000000", 66, &pr);
+        let result = validate("30000000000000000", 66, &pr);
         assert!(result.is_err());
         assert!(result.unwrap_err().to_string().contains("exceeds"));
     }
 
+    #[test]
+    fn test_gpu_and_max_ops_keep_small_ranges_on_kangaroo() {
+        let range = KeyRange::from_bits([0u8; 32], 40);
+        let args = |extra: &[&str]| Args::parse_from(["kangaroo", "-r", "40"].iter().chain(extra));
+        assert!(use_bsgs(&args(&[]), &range));
+        assert!(!use_bsgs(&args(&["--gpu", "0"]), &range));
+        assert!(!use_bsgs(&args(&["--max-ops", "1000"]), &range));
+        assert!(bsgs_excluded_options_given(&args(&["--gpu", "all"])));
+        assert_eq!(
+            gpu_selection(&args(&[])),
+            gpu_crypto::GpuSelection::Devices(vec![0])
+        );
+    }
+
     #[test]
     fn test_validate_search_bounds_exact_fit() {
         // Range [0x20..., 0x40...) is exactly 2^65 wide
         // Starting at 0x20... with range 65 bits should fit exactly
         let pr = make_provider_result("20000000000000000", "40000000000000000");
-        assert!(validate_search_bounds("20000000000000000", 65, &pr).is_ok());
+        assert!(validate("20000000000000000", 65, &pr).is_ok());
+    }
+
+    #[test]
+    fn test_validate_search_bounds_interval() {
+        let pr = make_provider_result("20000000000000000", "3ffffffffffffffff");
+        let bounds = provider_range(&pr).unwrap().unwrap();
+        let inside = KeyRange::new(
+            crypto::parse_hex_u256("28000000000000000").unwrap(),
+            bounds.end(),
+        )
+        .unwrap();
+        assert!(validate_search_bounds(&inside, &bounds, &pr.id).is_ok());
+        assert!(
+            validate_search_bounds(&KeyRange::from_bits(inside.start(), 66), &bounds, &pr.id)
+                .is_err()
+        );
     }
 }
@@ -1,7 +1,6 @@
 //! 256-bit arithmetic utilities for kangaroo algorithm.
 
 /// Add two 256-bit values (little-endian bytes).
-#[allow(dead_code)]
 pub fn add_256(a: &[u8], b: &[u8], result: &mut [u8]) {
     debug_assert!(a.len() >= 32 && b.len() >= 32 && result.len() >= 32);
 
@@ -14,7 +13,6 @@ pub fn add_256(a: &[u8], b: &[u8], result: &mut [u8]) {
 }
 
 /// Subtract two 256-bit values: result = a - b (little-endian bytes).
-#[allow(dead_code)]
 pub fn subtract_256(a: &[u8], b: &[u8], result: &mut [u8]) {
     debug_assert!(a.len() >= 32 && b.len() >= 32 && result.len() >= 32);
 
@@ -71,6 +69,11 @@ pub fn create_dp_mask(dp_bits: u32) -> [u32; 8] {
     mask
 }
 
+/// Whether little-endian limbs `x` have all bits of `mask` clear
+pub fn is_distinguished(x: &[u32; 8], mask: &[u32; 8]) -> bool {
+    x.iter().zip(mask).all(|(limb, m)| limb & m == 0)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -149,4 +152,16 @@ mod tests {
         assert_eq!(mask[1], 0xFF);
         assert_eq!(mask[2], 0);
     }
+
+    #[test]
+    fn test_is_distinguished_checks_every_limb() {
+        let mask = create_dp_mask(40);
+        let mut x = [0u32; 8];
+        x[2] = 0xFFFF_FFFF;
+        assert!(is_distinguished(&x, &mask));
+        x[1] = 0x80;
+        assert!(!is_distinguished(&x, &mask));
+        x[1] = 0x100;
+        assert!(is_distinguished(&x, &mask));
+    }
 }
@@ -0,0 +1,114 @@
+//! Several devices (GPUs and CPU workers) working on one search through a
+//! shared DP table
+
+use crate::cpu::CpuWorker;
+use crate::solver::KangarooSolver;
+use anyhow::{anyhow, Result};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// How often `run_devices` reports progress
+const REPORT_INTERVAL: Duration = Duration::from_millis(200);
+
+/// Herd seed for device `index` of a run seeded with `seed`; it only salts the
+/// start jitter, the devices' `GridSlice`s keep their start cells apart
+pub fn device_seed(seed: u64, index: usize) -> u64 {
+    seed ^ ((index as u64) << 32)
+}
+
+/// Herd seed for CPU worker `index`; never equal to a `device_seed`
+pub fn cpu_worker_seed(seed: u64, index: usize) -> u64 {
+    seed ^ !((index as u64) << 32)
+}
+
+/// Anything `run_devices` can drive: a GPU solver or a CPU worker
+pub trait Device: Send {
+    /// Run one batch; returns the key once found
+    fn step(&mut self) -> Result<Option<Vec<u8>>>;
+
+    fn total_operations(&self) -> u64;
+}
+
+impl Device for KangarooSolver {
+    fn step(&mut self) -> Result<Option<Vec<u8>>> {
+        KangarooSolver::step(self)
+    }
+
+    fn total_operations(&self) -> u64 {
+        KangarooSolver::total_operations(self)
+    }
+}
+
+impl Device for CpuWorker {
+    fn step(&mut self) -> Result<Option<Vec<u8>>> {
+        Ok(CpuWorker::step(self))
+    }
+
+    fn total_operations(&self) -> u64 {
+        CpuWorker::total_operations(self)
+    }
+}
+
+/// Run every device on its own thread until one of them finds the key.
+///
+/// The devices must already share a DP table (see `KangarooSolver::set_dp_table`).
+/// `report` is called periodically with each device's operation count; it
+/// returns false to stop all devices, in which case `Ok(None)` is returned.
+pub fn run_devices<F>(devices: Vec<Box<dyn Device>>, mut report: F) -> Result<Option<Vec<u8>>>
+where
+    F: FnMut(&[u64]) -> bool,
+{
+    let stop = AtomicBool::new(false);
+    let ops: Vec<AtomicU64> = devices.iter().map(|_| AtomicU64::new(0)).collect();
+    let outcome: Mutex<Option<Result<Vec<u8>>>> = Mutex::new(None);
+
+    std::thread::scope(|s| {
+        for (index, mut device) in devices.into_iter().enumerate() {
+            let (stop, ops, outcome) = (&stop, &ops, &outcome);
+            s.spawn(move || {
+                while !stop.load(Ordering::SeqCst) {
+                    let result = device.step();
+                    ops[index].store(device.total_operations(), Ordering::Relaxed);
+                    let finished = match result {
+                        Ok(Some(key)) => Ok(key),
+                        Ok(None) => continue,
+                        Err(e) => Err(anyhow!("Device {}: {:#}", index, e)),
+                    };
+                    outcome.lock().unwrap().get_or_insert(finished);
+                    stop.store(true, Ordering::SeqCst);
+                }
+            });
+        }
+
+        while !stop.load(Ordering::SeqCst) {
+            std::thread::sleep(REPORT_INTERVAL);
+            let snapshot: Vec<u64> = ops.iter().map(|o| o.load(Ordering::Relaxed)).collect();
+            if !report(&snapshot) {
+                stop.store(true, Ordering::SeqCst);
+            }
+        }
+    });
+
+    let snapshot: Vec<u64> = ops.iter().map(|o| o.load(Ordering::Relaxed)).collect();
+    report(&snapshot);
+    outcome.into_inner().unwrap().transpose()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cpu::init::DEFAULT_SEED;
+
+    #[test]
+    fn test_device_seeds_are_disjoint() {
+        assert_eq!(device_seed(DEFAULT_SEED, 0), DEFAULT_SEED);
+        let seeds: std::collections::HashSet<u64> =
+            (0..16).map(|i| device_seed(DEFAULT_SEED, i)).collect();
+        assert_eq!(seeds.len(), 16);
+        let cpu: std::collections::HashSet<u64> =
+            (0..16).map(|i| cpu_worker_seed(DEFAULT_SEED, i)).collect();
+        assert_eq!(cpu.len(), 16);
+        assert!(seeds.is_disjoint(&cpu));
+    }
+}
@@ -0,0 +1,102 @@
+//! Worker-side connection to a DP server
+
+use super::protocol::{read_message, write_message, Message};
+use super::PROTOCOL_VERSION;
+use crate::gpu::GpuDistinguishedPoint;
+use crate::workfile::WorkHeader;
+use anyhow::{anyhow, bail, Context, Result};
+use std::io::BufWriter;
+use std::net::TcpStream;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// Forwards DPs to a `DpServer` and listens for the "key found" broadcast
+pub struct DpClient {
+    writer: BufWriter<TcpStream>,
+    worker_id: u32,
+    header: WorkHeader,
+    found: Arc<Mutex<Option<Vec<u8>>>>,
+    disconnected: Arc<AtomicBool>,
+}
+
+impl DpClient {
+    /// Connect, introduce ourselves as `name` and receive the search parameters
+    pub fn connect(addr: &str, name: &str) -> Result<Self> {
+        let stream = TcpStream::connect(addr)
+            .with_context(|| format!("Failed to connect to server {}", addr))?;
+        stream.set_nodelay(true)?;
+        let mut reader = stream.try_clone()?;
+        let mut writer = BufWriter::new(stream);
+
+        write_message(
+            &mut writer,
+            &Message::Hello {
+                version: PROTOCOL_VERSION,
+                name: name.to_string(),
+            },
+        )?;
+
+        let (worker_id, header) = match read_message(&mut reader)? {
+            Message::Welcome { worker_id, header } => (worker_id, *header),
+            Message::Reject { reason } => bail!("Server rejected connection: {}", reason),
+            other => bail!("Unexpected reply from server: {:?}", other),
+        };
+
+        let found = Arc::new(Mutex::new(None));
+        let disconnected = Arc::new(AtomicBool::new(false));
+        {
+            let found = found.clone();
+            let disconnected = disconnected.clone();
+            std::thread::spawn(move || loop {
+                match read_message(&mut reader) {
+                    Ok(Message::KeyFound(key)) => {
+                        *found.lock().unwrap() = Some(key);
+                    }
+                    Ok(Message::Reject { reason }) => {
+                        tracing::error!("Server dropped this worker: {}", reason);
+                        disconnected.store(true, Ordering::SeqCst);
+                        break;
+                    }
+                    Ok(other) => tracing::warn!("Ignoring unexpected {:?} from server", other),
+                    Err(_) => {
+                        disconnected.store(true, Ordering::SeqCst);
+                        break;
+                    }
+                }
+            });
+        }
+
+        Ok(Self {
+            writer,
+            worker_id,
+            header,
+            found,
+            disconnected,
+        })
+    }
+
+    /// Identifier the server assigned to this worker
+    pub fn worker_id(&self) -> u32 {
+        self.worker_id
+    }
+
+    /// Search parameters dictated by the server
+    pub fn header(&self) -> &WorkHeader {
+        &self.header
+    }
+
+    pub fn send(&mut self, dps: &[GpuDistinguishedPoint]) -> Result<()> {
+        if self.disconnected.load(Ordering::SeqCst) && self.found_key().is_none() {
+            return Err(anyhow!("Lost connection to server"));
+        }
+        if dps.is_empty() {
+            return Ok(());
+        }
+        write_message(&mut self.writer, &Message::Dps(dps.to_vec()))
+    }
+
+    /// Key broadcast by the server, once any worker's DP completed a collision
+    pub fn found_key(&self) -> Option<Vec<u8>> {
+        self.found.lock().unwrap().clone()
+    }
+}
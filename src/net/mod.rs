@@ -0,0 +1,19 @@
+//! Distributed DP collection over TCP
+//!
+//! A single server owns the authoritative `DPTable` and hands the search
+//! parameters to every worker that connects. Workers stream their DPs to the
+//! server instead of checking them locally; when the server finds a tame/wild
+//! collision it broadcasts the key and every worker stops. Submissions are
+//! spot-checked so one bad worker cannot poison the table.
+
+mod client;
+mod protocol;
+mod server;
+mod validate;
+
+pub use client::DpClient;
+pub use server::DpServer;
+pub use validate::{SourceStats, ValidationPolicy};
+
+/// Bump on any incompatible wire format change
+pub const PROTOCOL_VERSION: u32 = 1;
@@ -0,0 +1,196 @@
+//! Wire format shared by server and client
+//!
+//! Every frame is `len: u32 LE | kind: u8 | payload`, where `len` covers the
+//! kind byte and the payload.
+
+use crate::gpu::GpuDistinguishedPoint;
+use crate::workfile::WorkHeader;
+use anyhow::{anyhow, bail, Result};
+use std::io::{Read, Write};
+
+/// Upper bound on a single frame, protects the server from absurd allocations
+const MAX_FRAME: usize = 64 * 1024 * 1024;
+
+const KIND_HELLO: u8 = 1;
+const KIND_WELCOME: u8 = 2;
+const KIND_REJECT: u8 = 3;
+const KIND_DPS: u8 = 4;
+const KIND_KEY_FOUND: u8 = 5;
+
+const DP_SIZE: usize = std::mem::size_of::<GpuDistinguishedPoint>();
+
+#[derive(Debug)]
+pub enum Message {
+    /// Worker -> server: first frame on a connection
+    Hello { version: u32, name: String },
+    /// Server -> worker: accepted, with the search to run
+    Welcome {
+        worker_id: u32,
+        header: Box<WorkHeader>,
+    },
+    /// Server -> worker: connection refused
+    Reject { reason: String },
+    /// Worker -> server: a batch of distinguished points
+    Dps(Vec<GpuDistinguishedPoint>),
+    /// Server -> worker: the search is over
+    KeyFound(Vec<u8>),
+}
+
+pub fn write_message<W: Write>(w: &mut W, msg: &Message) -> Result<()> {
+    let (kind, payload): (u8, Vec<u8>) = match msg {
+        Message::Hello { version, name } => {
+            let mut p = version.to_le_bytes().to_vec();
+            p.extend_from_slice(name.as_bytes());
+            (KIND_HELLO, p)
+        }
+        Message::Welcome { worker_id, header } => {
+            let mut p = worker_id.to_le_bytes().to_vec();
+            p.extend_from_slice(&header.to_bytes());
+            (KIND_WELCOME, p)
+        }
+        Message::Reject { reason } => (KIND_REJECT, reason.as_bytes().to_vec()),
+        Message::Dps(dps) => (KIND_DPS, bytemuck::cast_slice(dps).to_vec()),
+        Message::KeyFound(key) => (KIND_KEY_FOUND, key.clone()),
+    };
+
+    let len = (payload.len() + 1) as u32;
+    w.write_all(&len.to_le_bytes())?;
+    w.write_all(&[kind])?;
+    w.write_all(&payload)?;
+    w.flush()?;
+    Ok(())
+}
+
+pub fn read_message<R: Read>(r: &mut R) -> Result<Message> {
+    let mut len_bytes = [0u8; 4];
+    r.read_exact(&mut len_bytes)?;
+    let len = u32::from_le_bytes(len_bytes) as usize;
+    if len == 0 || len > MAX_FRAME {
+        bail!("Invalid frame length {}", len);
+    }
+
+    let mut frame = vec![0u8; len];
+    r.read_exact(&mut frame)?;
+    let (kind, payload) = (frame[0], &frame[1..]);
+
+    match kind {
+        KIND_HELLO => {
+            if payload.len() < 4 {
+                bail!("Short hello frame");
+            }
+            Ok(Message::Hello {
+                version: u32::from_le_bytes(payload[..4].try_into().unwrap()),
+                name: String::from_utf8_lossy(&payload[4..]).into_owned(),
+            })
+        }
+        KIND_WELCOME => {
+            if payload.len() < 4 {
+                bail!("Short welcome frame");
+            }
+            Ok(Message::Welcome {
+                worker_id: u32::from_le_bytes(payload[..4].try_into().unwrap()),
+                header: Box::new(WorkHeader::from_bytes(&payload[4..])?),
+            })
+        }
+        KIND_REJECT => Ok(Message::Reject {
+            reason: String::from_utf8_lossy(payload).into_owned(),
+        }),
+        KIND_DPS => {
+            if !payload.len().is_multiple_of(DP_SIZE) {
+                bail!("DP frame is not a whole number of records");
+            }
+            Ok(Message::Dps(
+                payload
+                    .chunks_exact(DP_SIZE)
+                    .map(bytemuck::pod_read_unaligned)
+                    .collect(),
+            ))
+        }
+        KIND_KEY_FOUND => Ok(Message::KeyFound(payload.to_vec())),
+        other => Err(anyhow!("Unknown frame kind {}", other)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cpu::init::Herds;
+    use crate::range::KeyRange;
+    use k256::elliptic_curve::ops::MulByGenerator;
+    use k256::{ProjectivePoint, Scalar};
+
+    #[test]
+    fn test_frames_roundtrip() {
+        let header = WorkHeader {
+            pubkey: ProjectivePoint::mul_by_generator(&Scalar::from(99u64)),
+            range: KeyRange::from_bits([3u8; 32], 40),
+            dp_bits: 12,
+            negation: false,
+            herds: Herds::Four,
+            gaudry_schost: None,
+        };
+        let dp = GpuDistinguishedPoint {
+            x: [1; 8],
+            dist: [2; 8],
+            ktype: 1,
+            kangaroo_id: 77,
+            kind: 0,
+            target: 0,
+            _padding: [0; 4],
+        };
+
+        let mut buf = Vec::new();
+        write_message(
+            &mut buf,
+            &Message::Hello {
+                version: 1,
+                name: "box1".into(),
+            },
+        )
+        .unwrap();
+        write_message(
+            &mut buf,
+            &Message::Welcome {
+                worker_id: 4,
+                header: Box::new(header.clone()),
+            },
+        )
+        .unwrap();
+        write_message(&mut buf, &Message::Dps(vec![dp, dp])).unwrap();
+        write_message(&mut buf, &Message::KeyFound(vec![0x12, 0x34])).unwrap();
+
+        let mut r = buf.as_slice();
+        assert!(matches!(
+            read_message(&mut r).unwrap(),
+            Message::Hello { version: 1, ref name } if name == "box1"
+        ));
+        match read_message(&mut r).unwrap() {
+            Message::Welcome {
+                worker_id,
+                header: h,
+            } => {
+                assert_eq!(worker_id, 4);
+                assert_eq!(*h, header);
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+        match read_message(&mut r).unwrap() {
+            Message::Dps(dps) => {
+                assert_eq!(dps.len(), 2);
+                assert_eq!(dps[1].kangaroo_id, 77);
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+        assert!(matches!(
+            read_message(&mut r).unwrap(),
+            Message::KeyFound(ref k) if k == &[0x12, 0x34]
+        ));
+    }
+
+    #[test]
+    fn test_rejects_oversized_frame() {
+        let mut buf = ((MAX_FRAME + 1) as u32).to_le_bytes().to_vec();
+        buf.push(KIND_DPS);
+        assert!(read_message(&mut buf.as_slice()).is_err());
+    }
+}
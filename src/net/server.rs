@@ -0,0 +1,418 @@
+//! DP collection server
+
+use super::protocol::{read_message, write_message, Message};
+use super::validate::{dp_matches, SourceStats, ValidationPolicy};
+use super::PROTOCOL_VERSION;
+use crate::cpu::DPTable;
+use crate::crypto::verify_key;
+use crate::gpu::GpuDistinguishedPoint;
+use crate::workfile::{WorkFileWriter, WorkHeader};
+use anyhow::{Context, Result};
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tracing::{error, info, warn};
+
+/// Interval between progress lines while serving
+const REPORT_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Owns the authoritative DP table for a distributed search
+pub struct DpServer {
+    listener: TcpListener,
+    shared: Arc<Shared>,
+}
+
+struct Shared {
+    header: WorkHeader,
+    table: DPTable,
+    work_file: Option<Mutex<WorkFileWriter>>,
+    /// Write halves of connected workers, used for the "key found" broadcast
+    workers: Mutex<Vec<TcpStream>>,
+    found: Mutex<Option<Vec<u8>>>,
+    next_worker_id: AtomicU32,
+    validation: ValidationPolicy,
+    sources: Mutex<HashMap<IpAddr, SourceStats>>,
+}
+
+/// Outcome of screening one submitted DP
+enum Verdict {
+    Accept,
+    Reject,
+    Ban,
+}
+
+impl DpServer {
+    /// Bind to `addr`; every accepted DP is also appended to `work_file` if given
+    pub fn bind(
+        addr: &str,
+        header: WorkHeader,
+        work_file: Option<WorkFileWriter>,
+        validation: ValidationPolicy,
+    ) -> Result<Self> {
+        let listener =
+            TcpListener::bind(addr).with_context(|| format!("Failed to listen on {}", addr))?;
+        Ok(Self {
+            listener,
+            shared: Arc::new(Shared {
+                table: header.dp_table(),
+                header,
+                work_file: work_file.map(Mutex::new),
+                workers: Mutex::new(Vec::new()),
+                found: Mutex::new(None),
+                next_worker_id: AtomicU32::new(0),
+                validation,
+                sources: Mutex::new(HashMap::new()),
+            }),
+        })
+    }
+
+    pub fn local_addr(&self) -> Result<SocketAddr> {
+        Ok(self.listener.local_addr()?)
+    }
+
+    pub fn total_dps(&self) -> usize {
+        self.shared.table.total_dps()
+    }
+
+    /// Per-address acceptance statistics, most DPs first
+    pub fn source_stats(&self) -> Vec<SourceStats> {
+        let mut stats: Vec<_> = self
+            .shared
+            .sources
+            .lock()
+            .unwrap()
+            .values()
+            .cloned()
+            .collect();
+        stats.sort_by_key(|s| std::cmp::Reverse(s.accepted));
+        stats
+    }
+
+    /// Seed the table with DPs from an earlier session (e.g. the server's work file)
+    pub fn preload(&self, dps: &[GpuDistinguishedPoint]) -> Option<Vec<u8>> {
+        dps.iter().find_map(|dp| self.shared.ingest(*dp))
+    }
+
+    /// Serve workers until a verified key is found, then broadcast it and return it
+    pub fn run(&self) -> Result<Vec<u8>> {
+        self.listener.set_nonblocking(true)?;
+        let mut last_report = Instant::now();
+
+        loop {
+            if let Some(key) = self.shared.found.lock().unwrap().clone() {
+                return Ok(key);
+            }
+
+            match self.listener.accept() {
+                Ok((stream, peer)) => {
+                    stream.set_nonblocking(false)?;
+                    let shared = self.shared.clone();
+                    std::thread::spawn(move || {
+                        if let Err(e) = shared.handle_worker(stream, peer) {
+                            warn!("Worker {} disconnected: {}", peer, e);
+                        }
+                    });
+                }
+                Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => {
+                    std::thread::sleep(Duration::from_millis(50));
+                }
+                Err(e) => return Err(e.into()),
+            }
+
+            if last_report.elapsed() >= REPORT_INTERVAL {
+                let (tame, wild) = self.shared.table.count_by_type();
+                info!(
+                    "Workers: {} | DPs: {} ({} tame, {} wild)",
+                    self.shared.workers.lock().unwrap().len(),
+                    tame + wild,
+                    tame,
+                    wild
+                );
+                for source in self.source_stats() {
+                    info!(
+                        "  {} '{}': {} accepted, {} checked, {} rejected{}",
+                        source.addr,
+                        source.name,
+                        source.accepted,
+                        source.checked,
+                        source.rejected,
+                        status_suffix(&source)
+                    );
+                }
+                last_report = Instant::now();
+            }
+        }
+    }
+}
+
+impl Shared {
+    fn handle_worker(&self, stream: TcpStream, peer: SocketAddr) -> Result<()> {
+        let mut reader = stream.try_clone()?;
+        let mut writer = stream;
+
+        let name = match read_message(&mut reader)? {
+            Message::Hello { version, name } if version == PROTOCOL_VERSION => name,
+            Message::Hello { version, .. } => {
+                let reason = format!(
+                    "protocol version {} not supported (server speaks {})",
+                    version, PROTOCOL_VERSION
+                );
+                write_message(&mut writer, &Message::Reject { reason })?;
+                return Ok(());
+            }
+            other => anyhow::bail!("expected hello, got {:?}", other),
+        };
+
+        let banned = {
+            let mut sources = self.sources.lock().unwrap();
+            let source = sources
+                .entry(peer.ip())
+                .or_insert_with(|| SourceStats::new(peer.ip(), name.clone()));
+            source.name = name.clone();
+            source.banned
+        };
+        if banned {
+            let reason = "this address is banned for submitting invalid DPs".to_string();
+            write_message(&mut writer, &Message::Reject { reason })?;
+            return Ok(());
+        }
+
+        let worker_id = self.next_worker_id.fetch_add(1, Ordering::SeqCst);
+        write_message(
+            &mut writer,
+            &Message::Welcome {
+                worker_id,
+                header: Box::new(self.header.clone()),
+            },
+        )?;
+        if let Some(key) = self.found.lock().unwrap().clone() {
+            write_message(&mut writer, &Message::KeyFound(key))?;
+            return Ok(());
+        }
+        self.workers.lock().unwrap().push(writer.try_clone()?);
+        info!("Worker {} '{}' connected from {}", worker_id, name, peer);
+
+        let mut received = 0u64;
+        loop {
+            match read_message(&mut reader) {
+                Ok(Message::Dps(dps)) => {
+                    received += dps.len() as u64;
+                    let mut accepted = Vec::with_capacity(dps.len());
+                    for dp in dps {
+                        match self.screen(peer.ip(), &dp) {
+                            Verdict::Accept => accepted.push(dp),
+                            Verdict::Reject => {}
+                            Verdict::Ban => {
+                                warn!(
+                                    "Banning {} ('{}') after repeated invalid DPs",
+                                    peer.ip(),
+                                    name
+                                );
+                                let reason = "too many invalid DPs".to_string();
+                                write_message(&mut writer, &Message::Reject { reason })?;
+                                return Ok(());
+                            }
+                        }
+                    }
+
+                    if let Some(ref work_file) = self.work_file {
+                        work_file.lock().unwrap().append(&accepted)?;
+                    }
+                    for dp in accepted {
+                        if let Some(key) = self.ingest(dp) {
+                            info!("Worker {} '{}' delivered the colliding DP", worker_id, name);
+                            self.broadcast(key);
+                            break;
+                        }
+                    }
+                }
+                Ok(other) => warn!("Worker {}: ignoring unexpected {:?}", worker_id, other),
+                Err(e) => {
+                    info!(
+                        "Worker {} '{}' left after {} DPs ({})",
+                        worker_id, name, received, e
+                    );
+                    return Ok(());
+                }
+            }
+        }
+    }
+
+    /// Spot-check a DP against the policy and record the result for its source
+    fn screen(&self, ip: IpAddr, dp: &GpuDistinguishedPoint) -> Verdict {
+        let quarantined = self
+            .sources
+            .lock()
+            .unwrap()
+            .get(&ip)
+            .is_some_and(|s| s.quarantined);
+        let check = quarantined || rand::random::<f64>() < self.validation.fraction;
+        // Recompute outside the lock, it is the expensive part
+        let valid = !check || dp_matches(&self.header, dp);
+
+        let mut sources = self.sources.lock().unwrap();
+        let source = sources
+            .entry(ip)
+            .or_insert_with(|| SourceStats::new(ip, String::new()));
+        if check {
+            source.checked += 1;
+        }
+        if valid {
+            source.accepted += 1;
+            return Verdict::Accept;
+        }
+
+        source.rejected += 1;
+        if !source.quarantined {
+            warn!(
+                "Invalid DP from {} ('{}'), checking all its DPs from now on",
+                ip, source.name
+            );
+            source.quarantined = true;
+        }
+        if source.rejected >= u64::from(self.validation.ban_after) {
+            source.banned = true;
+            Verdict::Ban
+        } else {
+            Verdict::Reject
+        }
+    }
+
+    /// Insert one DP; returns a key only if it verifies against the target
+    fn ingest(&self, dp: GpuDistinguishedPoint) -> Option<Vec<u8>> {
+        let key = self.table.insert_and_check(dp)?;
+        if verify_key(&key, &self.header.pubkey) {
+            Some(key)
+        } else {
+            error!(
+                "Collision produced 0x{} which does not match the target",
+                hex::encode(&key)
+            );
+            None
+        }
+    }
+
+    fn broadcast(&self, key: Vec<u8>) {
+        {
+            let mut found = self.found.lock().unwrap();
+            if found.is_some() {
+                return;
+            }
+            *found = Some(key.clone());
+        }
+        let msg = Message::KeyFound(key);
+        for worker in self.workers.lock().unwrap().iter_mut() {
+            // Workers that already went away are not an error here
+            let _ = write_message(worker, &msg);
+        }
+    }
+}
+
+fn status_suffix(source: &SourceStats) -> &'static str {
+    if source.banned {
+        " [banned]"
+    } else if source.quarantined {
+        " [quarantined]"
+    } else {
+        ""
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::convert::affine_to_gpu;
+    use crate::cpu::init::Herds;
+    use crate::net::DpClient;
+    use crate::range::KeyRange;
+    use k256::elliptic_curve::ops::MulByGenerator;
+    use k256::{ProjectivePoint, Scalar};
+
+    /// DP at `k * G` reached after walking `dist0`
+    fn dp(k: u64, dist0: u32, ktype: u32) -> GpuDistinguishedPoint {
+        let point = ProjectivePoint::mul_by_generator(&Scalar::from(k));
+        GpuDistinguishedPoint {
+            x: affine_to_gpu(&point.to_affine()).x,
+            dist: [dist0, 0, 0, 0, 0, 0, 0, 0],
+            ktype,
+            kangaroo_id: 0,
+            kind: 0,
+            target: 0,
+            _padding: [0; 4],
+        }
+    }
+
+    fn header() -> WorkHeader {
+        WorkHeader {
+            pubkey: ProjectivePoint::mul_by_generator(&Scalar::from(7u64)),
+            range: KeyRange::from_bits([0u8; 32], 20),
+            dp_bits: 8,
+            negation: false,
+            herds: Herds::Vow,
+            gaudry_schost: None,
+        }
+    }
+
+    fn check_everything() -> ValidationPolicy {
+        ValidationPolicy {
+            fraction: 1.0,
+            ban_after: 2,
+        }
+    }
+
+    #[test]
+    fn test_workers_collide_through_server() {
+        let server = DpServer::bind("127.0.0.1:0", header(), None, check_everything()).unwrap();
+        let addr = server.local_addr().unwrap().to_string();
+        let handle = std::thread::spawn(move || server.run().unwrap());
+
+        // start + tame_dist - wild_dist = 0 + 10 - 3 = 7, split across two workers
+        let mut tame = DpClient::connect(&addr, "tame").unwrap();
+        let mut wild = DpClient::connect(&addr, "wild").unwrap();
+        assert_ne!(tame.worker_id(), wild.worker_id());
+        assert_eq!(wild.header().dp_bits, 8);
+        // Both land on 10 * G: tame from start 0, wild from pubkey 7 * G
+        tame.send(&[dp(10, 10, 0)]).unwrap();
+        wild.send(&[dp(10, 3, 1)]).unwrap();
+
+        assert_eq!(handle.join().unwrap(), vec![7]);
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while tame.found_key().is_none() && Instant::now() < deadline {
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(tame.found_key(), Some(vec![7]));
+    }
+
+    #[test]
+    fn test_forged_dps_get_source_banned() {
+        let server = DpServer::bind("127.0.0.1:0", header(), None, check_everything()).unwrap();
+        let addr = server.local_addr().unwrap().to_string();
+        let server = Arc::new(server);
+        {
+            let server = server.clone();
+            std::thread::spawn(move || server.run());
+        }
+
+        let mut client = DpClient::connect(&addr, "flaky").unwrap();
+        client.send(&[dp(10, 10, 0)]).unwrap();
+        // Claims 10 * G is 11 steps from start
+        client.send(&[dp(10, 11, 0), dp(12, 11, 0)]).unwrap();
+
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while Instant::now() < deadline {
+            if server.source_stats().first().is_some_and(|s| s.banned) {
+                break;
+            }
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        let stats = &server.source_stats()[0];
+        assert_eq!(stats.accepted, 1);
+        assert_eq!(stats.rejected, 2);
+        assert!(stats.quarantined && stats.banned);
+        assert_eq!(server.total_dps(), 1);
+
+        assert!(DpClient::connect(&addr, "flaky again").is_err());
+    }
+}
@@ -0,0 +1,228 @@
+//! Spot-checks of DPs submitted by workers
+//!
+//! A DP claims that `start + dist` (tame) or `pubkey + dist` (wild) times G
+//! has the reported affine x (measured from the midpoint for the negation map
+//! and mirrored herds). Recomputing that costs one scalar multiplication,
+//! so the server checks a random fraction of submissions, every DP from a
+//! source that has already failed once, and bans sources that keep failing.
+
+use crate::convert::{affine_to_gpu, le_bytes_to_scalar, signed_limbs_to_scalar};
+use crate::cpu::init::Herds;
+use crate::gpu::{GpuDistinguishedPoint, KTYPE_MIRRORED_WILD, KTYPE_TAME, KTYPE_WILD};
+use crate::workfile::WorkHeader;
+use k256::elliptic_curve::ops::MulByGenerator;
+use k256::elliptic_curve::Group;
+use k256::ProjectivePoint;
+use std::net::IpAddr;
+
+/// How aggressively the server checks incoming DPs
+#[derive(Clone, Debug)]
+pub struct ValidationPolicy {
+    /// Fraction of DPs recomputed from sources in good standing (0.0 - 1.0)
+    pub fraction: f64,
+    /// Failed checks after which a source is disconnected and refused
+    pub ban_after: u32,
+}
+
+impl Default for ValidationPolicy {
+    fn default() -> Self {
+        Self {
+            fraction: 0.01,
+            ban_after: 3,
+        }
+    }
+}
+
+/// Acceptance record for one source address
+#[derive(Clone, Debug)]
+pub struct SourceStats {
+    pub addr: IpAddr,
+    /// Name the most recent worker from this address introduced itself with
+    pub name: String,
+    pub accepted: u64,
+    pub checked: u64,
+    pub rejected: u64,
+    /// Failed at least once; every further DP is checked
+    pub quarantined: bool,
+    pub banned: bool,
+}
+
+impl SourceStats {
+    pub fn new(addr: IpAddr, name: String) -> Self {
+        Self {
+            addr,
+            name,
+            accepted: 0,
+            checked: 0,
+            rejected: 0,
+            quarantined: false,
+            banned: false,
+        }
+    }
+}
+
+/// Recompute a DP from its distance and compare against the reported x
+pub fn dp_matches(header: &WorkHeader, dp: &GpuDistinguishedPoint) -> bool {
+    let dist = signed_limbs_to_scalar(&dp.dist);
+    let start = le_bytes_to_scalar(&header.range.start());
+
+    if header.negation {
+        // Distances are relative to the midpoint, and a wild's sign is lost on
+        // every reflection, so either sign may be the right one
+        let mid = le_bytes_to_scalar(&header.range.midpoint());
+        return match dp.ktype {
+            0 => x_matches(ProjectivePoint::mul_by_generator(&dist), dp),
+            1 => {
+                let shifted = header.pubkey - ProjectivePoint::mul_by_generator(&mid);
+                let step = ProjectivePoint::mul_by_generator(&dist);
+                x_matches(shifted + step, dp) || x_matches(shifted - step, dp)
+            }
+            _ => false,
+        };
+    }
+
+    if header.herds != Herds::Vow {
+        let mid = le_bytes_to_scalar(&header.range.midpoint());
+        let shifted = header.pubkey - ProjectivePoint::mul_by_generator(&mid);
+        let step = ProjectivePoint::mul_by_generator(&dist);
+        let point = match dp.ktype {
+            KTYPE_TAME => step,
+            KTYPE_WILD => shifted + step,
+            KTYPE_MIRRORED_WILD => step - shifted,
+            _ => return false,
+        };
+        return x_matches(point, dp);
+    }
+
+    let point = match dp.ktype {
+        0 => ProjectivePoint::mul_by_generator(&(start + dist)),
+        1 => header.pubkey + ProjectivePoint::mul_by_generator(&dist),
+        _ => return false,
+    };
+    x_matches(point, dp)
+}
+
+fn x_matches(point: ProjectivePoint, dp: &GpuDistinguishedPoint) -> bool {
+    !bool::from(point.is_identity()) && affine_to_gpu(&point.to_affine()).x == dp.x
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::range::KeyRange;
+    use k256::Scalar;
+
+    fn dp_for(point: ProjectivePoint, dist: [u32; 8], ktype: u32) -> GpuDistinguishedPoint {
+        GpuDistinguishedPoint {
+            x: affine_to_gpu(&point.to_affine()).x,
+            dist,
+            ktype,
+            kangaroo_id: 0,
+            kind: 0,
+            target: 0,
+            _padding: [0; 4],
+        }
+    }
+
+    #[test]
+    fn test_dp_matches_tame_and_wild() {
+        let g = ProjectivePoint::GENERATOR;
+        let mut start = [0u8; 32];
+        start[0] = 100;
+        let header = WorkHeader {
+            pubkey: g * Scalar::from(150u64),
+            range: KeyRange::from_bits(start, 20),
+            dp_bits: 8,
+            negation: false,
+            herds: Herds::Vow,
+            gaudry_schost: None,
+        };
+
+        // Tame at start + 5
+        let tame = dp_for(g * Scalar::from(105u64), [5, 0, 0, 0, 0, 0, 0, 0], 0);
+        assert!(dp_matches(&header, &tame));
+
+        // Wild at pubkey - 3, distance stored as two's complement
+        let minus_three = [
+            u32::MAX - 2,
+            u32::MAX,
+            u32::MAX,
+            u32::MAX,
+            u32::MAX,
+            u32::MAX,
+            u32::MAX,
+            u32::MAX,
+        ];
+        let wild = dp_for(g * Scalar::from(147u64), minus_three, 1);
+        assert!(dp_matches(&header, &wild));
+
+        // Forged distance
+        let mut forged = tame;
+        forged.dist[0] = 6;
+        assert!(!dp_matches(&header, &forged));
+        let mut wrong_type = tame;
+        wrong_type.ktype = 1;
+        assert!(!dp_matches(&header, &wrong_type));
+    }
+
+    #[test]
+    fn test_dp_matches_negation_walk() {
+        // start 0, 8-bit range: mid = 128, pubkey 150 * G so k' = 22
+        let g = ProjectivePoint::GENERATOR;
+        let header = WorkHeader {
+            pubkey: g * Scalar::from(150u64),
+            range: KeyRange::from_bits([0u8; 32], 8),
+            dp_bits: 4,
+            negation: true,
+            herds: Herds::Vow,
+            gaudry_schost: None,
+        };
+
+        let tame = dp_for(g * Scalar::from(9u64), [9, 0, 0, 0, 0, 0, 0, 0], 0);
+        assert!(dp_matches(&header, &tame));
+
+        // Reflected wild: -(k' + 5) * G stored with distance -5
+        let minus_five = [
+            u32::MAX - 4,
+            u32::MAX,
+            u32::MAX,
+            u32::MAX,
+            u32::MAX,
+            u32::MAX,
+            u32::MAX,
+            u32::MAX,
+        ];
+        let wild = dp_for(-(g * Scalar::from(27u64)), minus_five, 1);
+        assert!(dp_matches(&header, &wild));
+
+        let mut forged = wild;
+        forged.dist[0] = 6;
+        assert!(!dp_matches(&header, &forged));
+    }
+
+    #[test]
+    fn test_dp_matches_mirrored_herds() {
+        // start 0, 8-bit range: mid = 128, pubkey 150 * G so k' = 22
+        let g = ProjectivePoint::GENERATOR;
+        let header = WorkHeader {
+            pubkey: g * Scalar::from(150u64),
+            range: KeyRange::from_bits([0u8; 32], 8),
+            dp_bits: 4,
+            negation: false,
+            herds: Herds::Three,
+            gaudry_schost: None,
+        };
+
+        let tame = dp_for(g * Scalar::from(9u64), [9, 0, 0, 0, 0, 0, 0, 0], 0);
+        assert!(dp_matches(&header, &tame));
+        let wild = dp_for(g * Scalar::from(25u64), [3, 0, 0, 0, 0, 0, 0, 0], 1);
+        assert!(dp_matches(&header, &wild));
+
+        // Mirrored wild at d = 30 sits at 30 - k' = 8
+        let mirrored = dp_for(g * Scalar::from(8u64), [30, 0, 0, 0, 0, 0, 0, 0], 2);
+        assert!(dp_matches(&header, &mirrored));
+        let mut wrong_type = mirrored;
+        wrong_type.ktype = 1;
+        assert!(!dp_matches(&header, &wrong_type));
+    }
+}
@@ -24,6 +24,7 @@ pub fn resolve(path: &str) -> Result<ProviderResult> {
     Ok(ProviderResult {
         id: puzzle_id,
         pubkey,
+        address: Some(puzzle.address.value.to_string()),
         start,
         end,
         range_bits,
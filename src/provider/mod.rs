@@ -19,11 +19,13 @@ pub struct ProviderResult {
     /// Compressed public key (33 bytes hex)
     pub pubkey: Option<String>,
 
+    /// P2PKH address holding the prize
+    pub address: Option<String>,
+
     /// Start of key range (hex, without 0x prefix)
     pub start: Option<String>,
 
     /// End of key range (hex, without 0x prefix) - used for validation
-    #[allow(dead_code)]
     pub end: Option<String>,
 
     /// Key range in bits
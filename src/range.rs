@@ -0,0 +1,228 @@
+//! Inclusive key intervals
+//!
+//! A search covers the keys `start..=end`. Power-of-two ranges (`--start` plus
+//! `--range <bits>`) are one case; puzzle bounds or `--range a:b` give any width.
+
+use crate::crypto::{parse_hex_u256, U256};
+use anyhow::{anyhow, bail, Result};
+use k256::elliptic_curve::bigint::{CheckedAdd, CheckedMul, Encoding};
+use k256::U256 as K256U256;
+use std::fmt;
+
+/// Keys `start..=end`, both little-endian like `U256`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct KeyRange {
+    start: U256,
+    end: U256,
+}
+
+impl KeyRange {
+    pub fn new(start: U256, end: U256) -> Result<Self> {
+        if uint(&start) > uint(&end) {
+            bail!(
+                "Range start {} is above its end {}",
+                format_key(&start),
+                format_key(&end)
+            );
+        }
+        Ok(Self { start, end })
+    }
+
+    /// `[start, start + 2^range_bits)`, cut off at the top of the key space
+    pub fn from_bits(start: U256, range_bits: u32) -> Self {
+        let span = if range_bits >= 256 {
+            K256U256::MAX
+        } else {
+            K256U256::ONE
+                .shl_vartime(range_bits as usize)
+                .wrapping_sub(&K256U256::ONE)
+        };
+        Self {
+            start,
+            end: uint(&start).saturating_add(&span).to_le_bytes(),
+        }
+    }
+
+    /// `[center - radius, center + radius]`
+    pub fn from_center(center: U256, radius: U256) -> Result<Self> {
+        let (center, radius) = (uint(&center), uint(&radius));
+        if radius > center {
+            bail!("Radius reaches below key 0");
+        }
+        let end = Option::<K256U256>::from(center.checked_add(&radius))
+            .ok_or_else(|| anyhow!("Radius reaches past 2^256"))?;
+        Ok(Self {
+            start: center.wrapping_sub(&radius).to_le_bytes(),
+            end: end.to_le_bytes(),
+        })
+    }
+
+    pub fn start(&self) -> U256 {
+        self.start
+    }
+
+    pub fn end(&self) -> U256 {
+        self.end
+    }
+
+    /// Number of keys; the whole key space saturates at `2^256 - 1`
+    pub fn width(&self) -> K256U256 {
+        uint(&self.end)
+            .wrapping_sub(&uint(&self.start))
+            .saturating_add(&K256U256::ONE)
+    }
+
+    /// Smallest `range_bits` whose power-of-two range holds this one
+    pub fn bits(&self) -> u32 {
+        uint(&self.end).wrapping_sub(&uint(&self.start)).bits() as u32
+    }
+
+    /// `start + width / 2`, the origin of negation-map distances
+    pub fn midpoint(&self) -> U256 {
+        uint(&self.start)
+            .wrapping_add(&self.width().shr_vartime(1))
+            .to_le_bytes()
+    }
+
+    /// Whether `other` lies within this range
+    pub fn contains(&self, other: &KeyRange) -> bool {
+        uint(&other.start) >= uint(&self.start) && uint(&other.end) <= uint(&self.end)
+    }
+}
+
+impl fmt::Display for KeyRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "[{}, {}]",
+            format_key(&self.start),
+            format_key(&self.end)
+        )
+    }
+}
+
+/// `--range` value: a bit count (from `--start`) or an inclusive `a:b` interval
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RangeSpec {
+    Bits(u32),
+    Interval(KeyRange),
+}
+
+impl std::str::FromStr for RangeSpec {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.split_once(':') {
+            Some((a, b)) => {
+                let start = parse_key(a).map_err(|e| e.to_string())?;
+                let end = parse_key(b).map_err(|e| e.to_string())?;
+                KeyRange::new(start, end)
+                    .map(RangeSpec::Interval)
+                    .map_err(|e| e.to_string())
+            }
+            None => s
+                .trim()
+                .parse()
+                .map(RangeSpec::Bits)
+                .map_err(|_| format!("'{}' is neither a bit count nor an a:b interval", s)),
+        }
+    }
+}
+
+/// Parse a key given in decimal, or in hex with a `0x` prefix
+pub fn parse_key(s: &str) -> Result<U256> {
+    let s = s.trim();
+    if let Some(hex) = s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+        if hex.is_empty() || hex.len() > 64 {
+            bail!("Invalid hex key '{}'", s);
+        }
+        return parse_hex_u256(hex);
+    }
+
+    if s.is_empty() || !s.bytes().all(|b| b.is_ascii_digit()) {
+        bail!("Invalid key '{}': expected decimal or 0x-prefixed hex", s);
+    }
+    let ten = K256U256::from_u8(10);
+    let mut value = K256U256::ZERO;
+    for digit in s.bytes() {
+        let next = value
+            .checked_mul(&ten)
+            .and_then(|v| v.checked_add(&K256U256::from_u8(digit - b'0')));
+        value = Option::from(next).ok_or_else(|| anyhow!("Key {} does not fit in 256 bits", s))?;
+    }
+    Ok(value.to_le_bytes())
+}
+
+/// `0x`-prefixed hex without leading zeros
+pub fn format_key(key: &U256) -> String {
+    let mut be = *key;
+    be.reverse();
+    match hex::encode(be).trim_start_matches('0') {
+        "" => "0x0".to_string(),
+        s => format!("0x{}", s),
+    }
+}
+
+/// Numeric order of two keys
+pub fn compare_keys(a: &U256, b: &U256) -> std::cmp::Ordering {
+    uint(a).cmp(&uint(b))
+}
+
+fn uint(value: &U256) -> K256U256 {
+    K256U256::from_le_slice(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(value: u64) -> U256 {
+        K256U256::from_u64(value).to_le_bytes()
+    }
+
+    #[test]
+    fn test_parse_key_formats() {
+        assert_eq!(parse_key("1000").unwrap(), key(1000));
+        assert_eq!(parse_key("0x3e8").unwrap(), key(1000));
+        assert_eq!(parse_key("0X3E8").unwrap(), key(1000));
+        assert!(parse_key("3e8").is_err());
+        assert!(parse_key("0x").is_err());
+        assert!(parse_key(&"9".repeat(80)).is_err());
+    }
+
+    #[test]
+    fn test_range_spec() {
+        assert_eq!("40".parse::<RangeSpec>(), Ok(RangeSpec::Bits(40)));
+        let RangeSpec::Interval(range) = "0x4000:0x7fff".parse().unwrap() else {
+            panic!("not an interval");
+        };
+        assert_eq!((range.start(), range.end()), (key(0x4000), key(0x7fff)));
+        assert!("0x7fff:0x4000".parse::<RangeSpec>().is_err());
+        assert!("forty".parse::<RangeSpec>().is_err());
+    }
+
+    #[test]
+    fn test_width_bits_and_midpoint() {
+        let range = KeyRange::from_bits(key(0x4000), 14);
+        assert_eq!(range.end(), key(0x7fff));
+        assert_eq!(range.width(), K256U256::from_u64(0x4000));
+        assert_eq!(range.bits(), 14);
+        assert_eq!(range.midpoint(), key(0x6000));
+
+        // 3 * 2^12 keys need 14 bits as a power-of-two range
+        let range = KeyRange::new(key(0x1000), key(0x3fff)).unwrap();
+        assert_eq!(range.width(), K256U256::from_u64(0x3000));
+        assert_eq!(range.bits(), 14);
+        assert_eq!(range.midpoint(), key(0x2800));
+
+        let range = KeyRange::from_center(key(1000), key(10)).unwrap();
+        assert_eq!((range.start(), range.end()), (key(990), key(1010)));
+        assert!(KeyRange::from_center(key(5), key(10)).is_err());
+        assert!(range.contains(&KeyRange::new(key(995), key(1010)).unwrap()));
+        assert!(!range.contains(&KeyRange::new(key(995), key(1011)).unwrap()));
+
+        let full = KeyRange::from_bits([0xFF; 32], 8);
+        assert_eq!(full.end(), [0xFF; 32]);
+        assert_eq!(format_key(&key(0x3e8)), "0x3e8");
+    }
+}
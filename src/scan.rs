@@ -0,0 +1,777 @@
+//! Hash160 brute force for puzzles without a public key
+//!
+//! Kangaroo walks start from the public key, which a puzzle only reveals once
+//! coins have been spent from its address. Until then all there is to test a
+//! candidate against is the hash160 in the address, and the only search is to
+//! hash every key of the range: `O(N)` work instead of `O(√N)`, so this is for
+//! narrow ranges.
+//!
+//! [`Hash160Scanner`] splits the range into one run of consecutive keys per GPU
+//! thread and steps all runs in lockstep, so its progress is a single counter
+//! and resuming recomputes the start points from it.
+//!
+//! Progress file layout (all integers little-endian), like a checkpoint:
+//!
+//! ```text
+//! magic        8 bytes  "KANGSCAN"
+//! version      u32
+//! payload_len  u64
+//! payload      payload_len bytes
+//! checksum     32 bytes  SHA-256(payload)
+//! ```
+
+use crate::checkpoint::Reader;
+use crate::convert::{affine_to_gpu, le_bytes_to_scalar, scalar_to_key_bytes, u256_to_u128};
+use crate::crypto::compute_hash160;
+use crate::gpu::{
+    GpuAffinePoint, GpuContext, GpuScanConfig, GpuScanHit, ScanBuffers, ScanPipeline,
+    HIT_HEADER_SIZE, SCAN_BATCH, SCAN_FORM_COMPRESSED, SCAN_FORM_UNCOMPRESSED, SCAN_HIT_CAPACITY,
+};
+use crate::range::KeyRange;
+use anyhow::{anyhow, bail, Context, Result};
+use bitcoin::hashes::{sha256, Hash};
+use bitcoin::Address;
+use clap::ValueEnum;
+use k256::elliptic_curve::bigint::Encoding;
+use k256::elliptic_curve::ops::MulByGenerator;
+use k256::elliptic_curve::sec1::ToEncodedPoint;
+use k256::elliptic_curve::BatchNormalize;
+use k256::{ProjectivePoint, Scalar, U256 as K256U256};
+use std::path::Path;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+const MAGIC: &[u8; 8] = b"KANGSCAN";
+/// Bump whenever the payload layout changes
+pub const SCAN_PROGRESS_VERSION: u32 = 1;
+
+/// Widest range a scan takes (bits); far past anything a scan can finish
+const MAX_SCAN_BITS: usize = 96;
+
+/// Dispatch durations the batch count is tuned between
+const FAST_DISPATCH: Duration = Duration::from_millis(25);
+const SLOW_DISPATCH: Duration = Duration::from_millis(100);
+/// Keeps hit offsets within a u32
+const MAX_BATCHES_PER_CALL: u32 = 1 << 20;
+
+/// Which serializations of each public key are hashed
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum AddressForm {
+    /// 33-byte compressed keys (every puzzle address)
+    Compressed,
+    /// 65-byte uncompressed keys
+    Uncompressed,
+    /// Both, at twice the hashing work
+    Both,
+}
+
+impl AddressForm {
+    /// `SCAN_FORM_*` bits
+    fn bits(self) -> u32 {
+        match self {
+            AddressForm::Compressed => SCAN_FORM_COMPRESSED,
+            AddressForm::Uncompressed => SCAN_FORM_UNCOMPRESSED,
+            AddressForm::Both => SCAN_FORM_COMPRESSED | SCAN_FORM_UNCOMPRESSED,
+        }
+    }
+
+    fn from_bits(bits: u32) -> Result<Self> {
+        match bits {
+            SCAN_FORM_COMPRESSED => Ok(AddressForm::Compressed),
+            SCAN_FORM_UNCOMPRESSED => Ok(AddressForm::Uncompressed),
+            b if b == SCAN_FORM_COMPRESSED | SCAN_FORM_UNCOMPRESSED => Ok(AddressForm::Both),
+            other => bail!("Unknown address forms {}", other),
+        }
+    }
+}
+
+/// The hash160s a scan looks for, sorted and without duplicates
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ScanTargets {
+    hashes: Vec<[u8; 20]>,
+}
+
+impl ScanTargets {
+    pub fn new(mut hashes: Vec<[u8; 20]>) -> Result<Self> {
+        if hashes.is_empty() {
+            bail!("No hash160s to scan for");
+        }
+        hashes.sort_unstable();
+        hashes.dedup();
+        Ok(Self { hashes })
+    }
+
+    /// A hash160 from 40 hex digits
+    pub fn parse_hash160(s: &str) -> Result<[u8; 20]> {
+        let bytes = hex::decode(s.trim()).map_err(|e| anyhow!("Invalid hash160 '{}': {}", s, e))?;
+        bytes
+            .try_into()
+            .map_err(|_| anyhow!("Invalid hash160 '{}': expected 20 bytes", s))
+    }
+
+    /// The hash160 inside a P2PKH address
+    pub fn parse_address(s: &str) -> Result<[u8; 20]> {
+        let address =
+            Address::from_str(s.trim()).map_err(|e| anyhow!("Invalid address '{}': {}", s, e))?;
+        let hash = address
+            .assume_checked_ref()
+            .pubkey_hash()
+            .ok_or_else(|| anyhow!("{} is not a P2PKH address", s))?;
+        Ok(hash.to_byte_array())
+    }
+
+    /// A hash160 from 40 hex digits or a P2PKH address
+    pub fn parse(s: &str) -> Result<[u8; 20]> {
+        match s.trim().len() {
+            40 => Self::parse_hash160(s),
+            _ => Self::parse_address(s),
+        }
+    }
+
+    /// Parse a list of hash160s or addresses, one per line (blank lines and `#`
+    /// comments are skipped)
+    pub fn parse_list(text: &str) -> Result<Vec<[u8; 20]>> {
+        let mut hashes = Vec::new();
+        for (i, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            hashes.push(Self::parse(line).with_context(|| format!("line {}", i + 1))?);
+        }
+        Ok(hashes)
+    }
+
+    pub fn len(&self) -> usize {
+        self.hashes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.hashes.is_empty()
+    }
+
+    pub fn contains(&self, hash: &[u8; 20]) -> bool {
+        self.hashes.binary_search(hash).is_ok()
+    }
+
+    /// SHA-256 over the sorted hashes, ties a progress file to its targets
+    pub fn digest(&self) -> [u8; 32] {
+        sha256::Hash::hash(self.hashes.as_flattened()).to_byte_array()
+    }
+
+    /// Little-endian words sorted the way the kernel's binary search compares them
+    fn gpu_words(&self) -> Vec<[u32; 5]> {
+        let mut words: Vec<[u32; 5]> = self
+            .hashes
+            .iter()
+            .map(|hash| {
+                std::array::from_fn(|i| {
+                    u32::from_le_bytes(hash[4 * i..4 * i + 4].try_into().unwrap())
+                })
+            })
+            .collect();
+        words.sort_unstable();
+        words
+    }
+}
+
+/// A key whose public key hashes to one of the targets
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ScanFound {
+    /// Big-endian, leading zeros trimmed
+    pub key: Vec<u8>,
+    pub hash160: [u8; 20],
+    /// Matched in compressed rather than uncompressed form
+    pub compressed: bool,
+}
+
+/// hash160 of `point` in one form
+fn point_hash160(point: &ProjectivePoint, compressed: bool) -> [u8; 20] {
+    compute_hash160(point.to_affine().to_encoded_point(compressed).as_bytes())
+}
+
+/// Check `key` against the targets in the forms asked for
+fn check_key(key: &Scalar, targets: &ScanTargets, forms: AddressForm) -> Vec<ScanFound> {
+    let point = ProjectivePoint::mul_by_generator(key);
+    [
+        (SCAN_FORM_COMPRESSED, true),
+        (SCAN_FORM_UNCOMPRESSED, false),
+    ]
+    .into_iter()
+    .filter(|&(bit, _)| forms.bits() & bit != 0)
+    .filter_map(|(_, compressed)| {
+        let hash160 = point_hash160(&point, compressed);
+        targets.contains(&hash160).then(|| ScanFound {
+            key: scalar_to_key_bytes(key),
+            hash160,
+            compressed,
+        })
+    })
+    .collect()
+}
+
+/// Hash every key of `range` on the CPU; the reference the GPU scan is checked
+/// against, and the scan of keys too close to zero for the GPU kernel
+pub fn cpu_scan(range: &KeyRange, targets: &ScanTargets, forms: AddressForm) -> Vec<ScanFound> {
+    let start = le_bytes_to_scalar(&range.start());
+    let width = u256_to_u128(&range.width().to_le_bytes());
+    (0..width)
+        .map(|offset| start + Scalar::from(offset))
+        .filter(|key| !bool::from(key.is_zero()))
+        .flat_map(|key| check_key(&key, targets, forms))
+        .collect()
+}
+
+/// State of an interrupted scan
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ScanProgress {
+    pub range: KeyRange,
+    pub forms: AddressForm,
+    /// `ScanTargets::digest` of the targets scanned for
+    pub targets_digest: [u8; 32],
+    pub threads: u32,
+    /// Keys per thread
+    pub chunk: u128,
+    /// Keys each thread has checked
+    pub done: u128,
+    pub found: Vec<ScanFound>,
+}
+
+impl ScanProgress {
+    /// Serialize to the versioned, checksummed binary format
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut payload = Vec::new();
+        payload.extend_from_slice(&self.range.start());
+        payload.extend_from_slice(&self.range.end());
+        payload.extend_from_slice(&self.forms.bits().to_le_bytes());
+        payload.extend_from_slice(&self.targets_digest);
+        payload.extend_from_slice(&self.threads.to_le_bytes());
+        payload.extend_from_slice(&self.chunk.to_le_bytes());
+        payload.extend_from_slice(&self.done.to_le_bytes());
+        payload.extend_from_slice(&(self.found.len() as u64).to_le_bytes());
+        for found in &self.found {
+            let mut key = [0u8; 32];
+            key[32 - found.key.len()..].copy_from_slice(&found.key);
+            payload.extend_from_slice(&key);
+            payload.extend_from_slice(&found.hash160);
+            payload.push(u8::from(found.compressed));
+        }
+
+        let checksum = sha256::Hash::hash(&payload);
+
+        let mut out = Vec::with_capacity(payload.len() + 52);
+        out.extend_from_slice(MAGIC);
+        out.extend_from_slice(&SCAN_PROGRESS_VERSION.to_le_bytes());
+        out.extend_from_slice(&(payload.len() as u64).to_le_bytes());
+        out.extend_from_slice(&payload);
+        out.extend_from_slice(checksum.as_byte_array());
+        out
+    }
+
+    /// Parse and validate a progress file
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let mut header = Reader::new(bytes);
+        if header.take(8)? != MAGIC {
+            bail!("Not a scan progress file (bad magic)");
+        }
+        let version = header.u32()?;
+        if version != SCAN_PROGRESS_VERSION {
+            bail!(
+                "Unsupported scan progress version {} (expected {})",
+                version,
+                SCAN_PROGRESS_VERSION
+            );
+        }
+        let payload_len = header.u64()? as usize;
+        let payload = header.take(payload_len)?;
+        let checksum = header.take(32)?;
+        if sha256::Hash::hash(payload).as_byte_array() != checksum {
+            bail!("Scan progress checksum mismatch (file is corrupted)");
+        }
+
+        let mut r = Reader::new(payload);
+        let mut start = [0u8; 32];
+        start.copy_from_slice(r.take(32)?);
+        let mut end = [0u8; 32];
+        end.copy_from_slice(r.take(32)?);
+        let range = KeyRange::new(start, end).context("Invalid range in scan progress")?;
+        let forms = AddressForm::from_bits(r.u32()?)?;
+        let mut targets_digest = [0u8; 32];
+        targets_digest.copy_from_slice(r.take(32)?);
+        let threads = r.u32()?;
+        let chunk = u128::from_le_bytes(r.take(16)?.try_into().unwrap());
+        let done = u128::from_le_bytes(r.take(16)?.try_into().unwrap());
+        let num_found = r.u64()?;
+        let mut found = Vec::new();
+        for _ in 0..num_found {
+            let key = r.take(32)?;
+            let first = key.iter().position(|&b| b != 0).unwrap_or(31);
+            let mut hash160 = [0u8; 20];
+            hash160.copy_from_slice(r.take(20)?);
+            found.push(ScanFound {
+                key: key[first..].to_vec(),
+                hash160,
+                compressed: r.take(1)?[0] != 0,
+            });
+        }
+
+        Ok(Self {
+            range,
+            forms,
+            targets_digest,
+            threads,
+            chunk,
+            done,
+            found,
+        })
+    }
+
+    /// Write atomically (temp file + rename), like a checkpoint
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let mut tmp = path.as_os_str().to_owned();
+        tmp.push(".tmp");
+        let tmp = std::path::PathBuf::from(tmp);
+
+        std::fs::write(&tmp, self.to_bytes())
+            .with_context(|| format!("Failed to write scan progress {}", tmp.display()))?;
+        std::fs::rename(&tmp, path)
+            .with_context(|| format!("Failed to move scan progress to {}", path.display()))?;
+        Ok(())
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let bytes = std::fs::read(path)
+            .with_context(|| format!("Failed to read scan progress {}", path.display()))?;
+        Self::from_bytes(&bytes)
+            .with_context(|| format!("Invalid scan progress {}", path.display()))
+    }
+}
+
+/// Where the GPU threads walk: keys `base + t·chunk .. base + (t + 1)·chunk`
+/// of thread `t`, clipped to `width` keys
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Layout {
+    base: Scalar,
+    width: u128,
+    threads: u32,
+    chunk: u128,
+}
+
+impl Layout {
+    fn new(base: Scalar, width: u128, threads: u32) -> Self {
+        // No idle threads on narrow ranges
+        let threads = u128::from(threads.max(1))
+            .min(width.div_ceil(u128::from(SCAN_BATCH)))
+            .max(1) as u32;
+        Self {
+            base,
+            width,
+            threads,
+            chunk: width.div_ceil(u128::from(threads)),
+        }
+    }
+
+    /// Offset from `base` of the key `offset` keys past thread `thread`'s
+    /// position `done`, if it is one the thread is responsible for
+    fn key_offset(&self, thread: u32, done: u128, offset: u128) -> Option<u128> {
+        let step = done + offset;
+        let key = u128::from(thread) * self.chunk + step;
+        (thread < self.threads && step < self.chunk && key < self.width).then_some(key)
+    }
+
+    /// Affine points of every thread after `done` keys
+    fn points(&self, done: u128) -> Vec<GpuAffinePoint> {
+        let stride = ProjectivePoint::mul_by_generator(&Scalar::from(self.chunk));
+        let mut point = ProjectivePoint::mul_by_generator(&(self.base + Scalar::from(done)));
+        let points: Vec<ProjectivePoint> = (0..self.threads)
+            .map(|_| {
+                let p = point;
+                point += stride;
+                p
+            })
+            .collect();
+        ProjectivePoint::batch_normalize(points.as_slice())
+            .iter()
+            .map(affine_to_gpu)
+            .collect()
+    }
+}
+
+/// GPU hash160 scan of one range
+pub struct Hash160Scanner {
+    ctx: GpuContext,
+    pipeline: ScanPipeline,
+    buffers: ScanBuffers,
+    range: KeyRange,
+    targets: ScanTargets,
+    forms: AddressForm,
+    /// Keys below this many are hashed on the CPU
+    cpu_keys: u128,
+    layout: Layout,
+    done: u128,
+    batches_per_call: u32,
+    found: Vec<ScanFound>,
+}
+
+impl Hash160Scanner {
+    /// Set up a scan of `range` with up to `threads` GPU threads, or continue
+    /// the one `resume` was saved from
+    pub fn new(
+        ctx: GpuContext,
+        range: KeyRange,
+        targets: ScanTargets,
+        forms: AddressForm,
+        threads: u32,
+        resume: Option<ScanProgress>,
+    ) -> Result<Self> {
+        if range.width().bits() > MAX_SCAN_BITS {
+            bail!(
+                "{} is too wide to scan (at most 2^{} keys)",
+                range,
+                MAX_SCAN_BITS
+            );
+        }
+        // Far from the group order, so no thread point is ever ±i·G of a batch
+        if K256U256::from_le_bytes(range.end()).bit_vartime(255) {
+            bail!("Scan ranges must end below 2^255");
+        }
+        // The kernel adds up to SCAN_BATCH·G to each point, so its keys start above that
+        let (start, end) = (
+            K256U256::from_le_bytes(range.start()),
+            K256U256::from_le_bytes(range.end()),
+        );
+        let top = K256U256::from(SCAN_BATCH);
+        let low = (start <= top)
+            .then(|| KeyRange::new(range.start(), end.min(top).to_le_bytes()))
+            .transpose()?;
+        let cpu_keys = low.map_or(0, |low| u256_to_u128(&low.width().to_le_bytes()));
+        let width = u256_to_u128(&range.width().to_le_bytes()) - cpu_keys;
+        let base = le_bytes_to_scalar(&range.start()) + Scalar::from(cpu_keys);
+
+        let (layout, done, found) = match resume {
+            Some(progress) => {
+                if progress.range != range
+                    || progress.forms != forms
+                    || progress.targets_digest != targets.digest()
+                {
+                    bail!("Scan progress is for a different range, target list or --form");
+                }
+                let layout = Layout {
+                    base,
+                    width,
+                    threads: progress.threads,
+                    chunk: progress.chunk,
+                };
+                if layout.threads == 0 || u128::from(layout.threads) * layout.chunk < width {
+                    bail!("Scan progress does not cover its range");
+                }
+                (layout, progress.done, progress.found)
+            }
+            None => {
+                let found = match low {
+                    Some(low) => cpu_scan(&low, &targets, forms),
+                    None => Vec::new(),
+                };
+                (Layout::new(base, width, threads), 0, found)
+            }
+        };
+
+        let pipeline = ScanPipeline::new(&ctx)?;
+        let g_multiples: Vec<GpuAffinePoint> = {
+            let points: Vec<ProjectivePoint> = (1..=u64::from(SCAN_BATCH))
+                .map(|i| ProjectivePoint::mul_by_generator(&Scalar::from(i)))
+                .collect();
+            ProjectivePoint::batch_normalize(points.as_slice())
+                .iter()
+                .map(affine_to_gpu)
+                .collect()
+        };
+        let config = GpuScanConfig {
+            num_threads: layout.threads,
+            batches_per_call: 1,
+            forms: forms.bits(),
+            num_targets: targets.len() as u32,
+        };
+        let buffers = ScanBuffers::new(
+            &ctx,
+            &pipeline,
+            &config,
+            &g_multiples,
+            &layout.points(done.min(layout.chunk)),
+            &targets.gpu_words(),
+        )?;
+
+        Ok(Self {
+            ctx,
+            pipeline,
+            buffers,
+            range,
+            targets,
+            forms,
+            cpu_keys,
+            layout,
+            done,
+            batches_per_call: 1,
+            found,
+        })
+    }
+}
+
+impl Hash160Scanner {
+    /// Run one dispatch; returns the keys it found
+    pub fn step(&mut self) -> Result<Vec<ScanFound>> {
+        if self.is_finished() {
+            return Ok(Vec::new());
+        }
+        // No batches past the end of the threads' runs
+        let remaining = (self.layout.chunk - self.done).div_ceil(u128::from(SCAN_BATCH));
+        let batches = u128::from(self.batches_per_call).min(remaining) as u32;
+        let config = GpuScanConfig {
+            num_threads: self.layout.threads,
+            batches_per_call: batches,
+            forms: self.forms.bits(),
+            num_targets: self.targets.len() as u32,
+        };
+        self.ctx
+            .queue
+            .write_buffer(&self.buffers.config_buffer, 0, bytemuck::bytes_of(&config));
+
+        let started = Instant::now();
+        let hits = self.dispatch()?;
+        let elapsed = started.elapsed();
+        if elapsed < FAST_DISPATCH {
+            self.batches_per_call = (self.batches_per_call * 2).min(MAX_BATCHES_PER_CALL);
+        } else if elapsed > SLOW_DISPATCH {
+            self.batches_per_call = (self.batches_per_call / 2).max(1);
+        }
+
+        // A hit is only a hash match: recompute it, and drop keys past a
+        // thread's run (those belong to the next thread or lie past the range)
+        let mut found = Vec::new();
+        for hit in hits {
+            let Some(offset) =
+                self.layout
+                    .key_offset(hit.thread, self.done, u128::from(hit.offset))
+            else {
+                continue;
+            };
+            let key = self.layout.base + Scalar::from(offset);
+            for hit in check_key(&key, &self.targets, self.forms) {
+                if !self.found.contains(&hit) {
+                    self.found.push(hit.clone());
+                    found.push(hit);
+                }
+            }
+        }
+        self.done += u128::from(batches) * u128::from(SCAN_BATCH);
+        Ok(found)
+    }
+
+    /// Dispatch the kernel and read back its hits
+    fn dispatch(&self) -> Result<Vec<GpuScanHit>> {
+        let hit_size = std::mem::size_of::<GpuScanHit>();
+        let mut encoder = self
+            .ctx
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("Scan Encoder"),
+            });
+        encoder.clear_buffer(&self.buffers.hit_count_buffer, 0, None);
+        {
+            let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                label: Some("Scan Pass"),
+                timestamp_writes: None,
+            });
+            pass.set_pipeline(&self.pipeline.pipeline);
+            pass.set_bind_group(0, &self.buffers.bind_group, &[]);
+            pass.dispatch_workgroups(self.layout.threads.div_ceil(64), 1, 1);
+        }
+        encoder.copy_buffer_to_buffer(
+            &self.buffers.hit_count_buffer,
+            0,
+            &self.buffers.staging_buffer,
+            0,
+            4,
+        );
+        encoder.copy_buffer_to_buffer(
+            &self.buffers.hits_buffer,
+            0,
+            &self.buffers.staging_buffer,
+            HIT_HEADER_SIZE as u64,
+            (SCAN_HIT_CAPACITY as usize * hit_size) as u64,
+        );
+        self.ctx.queue.submit(Some(encoder.finish()));
+
+        let staging: Vec<u8> = pollster::block_on(self.ctx.read_buffer(
+            &self.buffers.staging_buffer,
+            0,
+            (HIT_HEADER_SIZE + SCAN_HIT_CAPACITY as usize * hit_size) as u64,
+        ))?;
+        let count = u32::from_le_bytes(staging[..4].try_into().unwrap());
+        if count > SCAN_HIT_CAPACITY {
+            // Only real keys match, so this takes thousands of duplicate targets
+            bail!(
+                "{} hash160 matches in one dispatch, more than the kernel reports",
+                count
+            );
+        }
+        Ok(staging[HIT_HEADER_SIZE..]
+            .chunks_exact(hit_size)
+            .take(count as usize)
+            .map(|chunk| *bytemuck::from_bytes::<GpuScanHit>(chunk))
+            .collect())
+    }
+
+    /// Every key of the range has been hashed
+    pub fn is_finished(&self) -> bool {
+        self.done >= self.layout.chunk
+    }
+
+    /// Keys hashed so far, in the CPU prefix and the GPU threads' runs
+    pub fn keys_done(&self) -> u128 {
+        let gpu = (u128::from(self.layout.threads) * self.done.min(self.layout.chunk))
+            .min(self.layout.width);
+        self.cpu_keys + gpu
+    }
+
+    /// Keys of the whole range
+    pub fn keys_total(&self) -> u128 {
+        self.cpu_keys + self.layout.width
+    }
+
+    pub fn threads(&self) -> u32 {
+        self.layout.threads
+    }
+
+    /// Every target found so far
+    pub fn found(&self) -> &[ScanFound] {
+        &self.found
+    }
+
+    /// Every target has a key
+    pub fn all_found(&self) -> bool {
+        let mut hashes: Vec<[u8; 20]> = self.found.iter().map(|f| f.hash160).collect();
+        hashes.sort_unstable();
+        hashes.dedup();
+        hashes.len() == self.targets.len()
+    }
+
+    /// Snapshot to save for a later `new` with `resume`
+    pub fn progress(&self) -> ScanProgress {
+        ScanProgress {
+            range: self.range,
+            forms: self.forms,
+            targets_digest: self.targets.digest(),
+            threads: self.layout.threads,
+            chunk: self.layout.chunk,
+            done: self.done.min(self.layout.chunk),
+            found: self.found.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(value: u64) -> [u8; 32] {
+        K256U256::from_u64(value).to_le_bytes()
+    }
+
+    // Puzzle 10: key 0x202 in [0x200, 0x3ff]
+    const PUZZLE_10_ADDRESS: &str = "1LeBZP5QCwwgXRtmVUvTVrraqPUokyLHqe";
+
+    #[test]
+    fn test_parse_targets() {
+        let point = ProjectivePoint::mul_by_generator(&Scalar::from(0x202u64));
+        let hash160 = point_hash160(&point, true);
+        assert_eq!(ScanTargets::parse(PUZZLE_10_ADDRESS).unwrap(), hash160);
+        assert_eq!(ScanTargets::parse(&hex::encode(hash160)).unwrap(), hash160);
+        assert!(ScanTargets::parse_hash160("abcd").is_err());
+        assert!(ScanTargets::parse("1NotAnAddress").is_err());
+
+        let list = format!(
+            "# puzzle 10\n{}\n\n{}\n",
+            PUZZLE_10_ADDRESS,
+            hex::encode([0x11; 20])
+        );
+        let targets = ScanTargets::new(ScanTargets::parse_list(&list).unwrap()).unwrap();
+        assert_eq!(targets.len(), 2);
+        assert!(targets.contains(&hash160));
+        assert!(!targets.contains(&[0x22; 20]));
+        assert!(ScanTargets::new(Vec::new()).is_err());
+
+        let dup = ScanTargets::new(vec![[0x11; 20], hash160, [0x11; 20]]).unwrap();
+        assert_eq!(dup.len(), 2);
+        assert_eq!(dup.digest(), targets.digest());
+    }
+
+    #[test]
+    fn test_cpu_scan_finds_puzzle_10() {
+        let targets =
+            ScanTargets::new(vec![ScanTargets::parse(PUZZLE_10_ADDRESS).unwrap()]).unwrap();
+        let range = KeyRange::from_bits(key(0x200), 9);
+        let found = cpu_scan(&range, &targets, AddressForm::Both);
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].key, vec![0x02, 0x02]);
+        assert!(found[0].compressed);
+        assert!(cpu_scan(&range, &targets, AddressForm::Uncompressed).is_empty());
+    }
+
+    #[test]
+    fn test_layout() {
+        let layout = Layout::new(Scalar::ONE, 1000, 8);
+        assert_eq!((layout.threads, layout.chunk), (8, 125));
+        assert_eq!(layout.key_offset(1, 100, 24), Some(249));
+        assert_eq!(layout.key_offset(1, 100, 25), None);
+        assert_eq!(layout.key_offset(7, 124, 0), Some(999));
+        assert_eq!(layout.key_offset(8, 0, 0), None);
+
+        // Fewer threads than batches on narrow ranges, and the last one clipped
+        let layout = Layout::new(Scalar::ONE, 40, 8);
+        assert_eq!((layout.threads, layout.chunk), (2, 20));
+        let layout = Layout::new(Scalar::ONE, 50, 2);
+        assert_eq!(layout.key_offset(1, 24, 0), Some(49));
+        assert_eq!(layout.key_offset(1, 24, 1), None);
+
+        let points = layout.points(3);
+        let expected = ProjectivePoint::mul_by_generator(&Scalar::from(1 + 25 + 3u64));
+        assert_eq!(points.len(), 2);
+        let expected = affine_to_gpu(&expected.to_affine());
+        assert_eq!((points[1].x, points[1].y), (expected.x, expected.y));
+    }
+
+    #[test]
+    fn test_progress_roundtrip_and_corruption() {
+        let progress = ScanProgress {
+            range: KeyRange::from_bits(key(0x200), 9),
+            forms: AddressForm::Both,
+            targets_digest: [0xab; 32],
+            threads: 16,
+            chunk: 32,
+            done: 7,
+            found: vec![ScanFound {
+                key: vec![0x02, 0x02],
+                hash160: [0x11; 20],
+                compressed: true,
+            }],
+        };
+        assert_eq!(
+            ScanProgress::from_bytes(&progress.to_bytes()).unwrap(),
+            progress
+        );
+
+        let mut bytes = progress.to_bytes();
+        let mid = bytes.len() / 2;
+        bytes[mid] ^= 0x01;
+        assert!(ScanProgress::from_bytes(&bytes).is_err());
+        let mut bytes = progress.to_bytes();
+        bytes[..8].copy_from_slice(b"KANGTAME");
+        assert!(ScanProgress::from_bytes(&bytes).is_err());
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("scan.progress");
+        progress.save(&path).unwrap();
+        assert_eq!(ScanProgress::load(&path).unwrap(), progress);
+    }
+}
@@ -0,0 +1,238 @@
+// =============================================================================
+// Hash160 Scan - GPU Kernel
+// =============================================================================
+// Brute force for targets known only by address: every thread walks its own
+// run of consecutive keys k, k+1, k+2, ... and compares RIPEMD160(SHA256(pubkey))
+// of each against a sorted list of target hashes.
+//
+// Consecutive points come from adding 1·G .. SCAN_BATCH·G to the current point,
+// with one shared inversion per batch (Montgomery's trick, as in the kangaroo
+// kernel but within a single thread).
+
+// -----------------------------------------------------------------------------
+// Configuration
+// -----------------------------------------------------------------------------
+
+struct ScanConfig {
+    num_threads: u32,
+    batches_per_call: u32,
+    forms: u32,
+    num_targets: u32
+}
+
+// ScanConfig.forms and ScanHit.form bits (must match SCAN_FORM_* in gpu/scan.rs)
+const FORM_COMPRESSED: u32 = 1u;
+const FORM_UNCOMPRESSED: u32 = 2u;
+
+// Keys per batch (must match SCAN_BATCH in gpu/scan.rs)
+const SCAN_BATCH: u32 = 32u;
+
+// Must match Rust GpuScanHit struct layout!
+struct ScanHit {
+    thread: u32,
+    // Keys past the thread's point at the start of the dispatch
+    offset: u32,
+    form: u32,
+    _padding: u32
+}
+
+// -----------------------------------------------------------------------------
+// Bindings
+// -----------------------------------------------------------------------------
+
+@group(0) @binding(0) var<uniform> config: ScanConfig;
+// g_multiples[i] = (i + 1)·G
+@group(0) @binding(1) var<storage, read> g_multiples: array<AffinePoint>;
+@group(0) @binding(2) var<storage, read_write> points: array<AffinePoint>;
+// Hash160s as five little-endian words, sorted by (w0, w1, .., w4)
+@group(0) @binding(3) var<storage, read> targets: array<array<u32, 5>>;
+@group(0) @binding(4) var<storage, read_write> hits: array<ScanHit>;
+@group(0) @binding(5) var<storage, read_write> hit_count: atomic<u32>;
+
+// -----------------------------------------------------------------------------
+// Canonical form: field operations leave values in [0, 2p), hashes need [0, p)
+// -----------------------------------------------------------------------------
+
+fn fe_canonical(a: array<u32, 8>) -> array<u32, 8> {
+    let top = a[7] & a[6] & a[5] & a[4] & a[3] & a[2];
+    let ge_p = top == 0xFFFFFFFFu && (a[1] > P1 || (a[1] == P1 && a[0] >= P0));
+    if (ge_p) {
+        return fe_sub(a, array<u32, 8>(P0, P1, P2, P3, P4, P5, P6, P7));
+    }
+    return a;
+}
+
+fn bswap32(x: u32) -> u32 {
+    return (x << 24u) | ((x << 8u) & 0x00FF0000u) | ((x >> 8u) & 0x0000FF00u) | (x >> 24u);
+}
+
+// -----------------------------------------------------------------------------
+// Hash160 of a serialized public key
+// -----------------------------------------------------------------------------
+
+// RIPEMD160 of a 32-byte SHA-256 digest
+fn ripemd160_of_sha256(digest: array<u32, 8>) -> array<u32, 5> {
+    var block: array<u32, 16>;
+    block[0] = bswap32(digest[0]); block[1] = bswap32(digest[1]);
+    block[2] = bswap32(digest[2]); block[3] = bswap32(digest[3]);
+    block[4] = bswap32(digest[4]); block[5] = bswap32(digest[5]);
+    block[6] = bswap32(digest[6]); block[7] = bswap32(digest[7]);
+    block[8] = 0x80u;
+    block[14] = 256u;
+    return ripemd160_compress(ripemd160_iv(), block);
+}
+
+// 02/03 || x: 33 bytes, one SHA-256 block
+fn hash160_compressed(x: array<u32, 8>, y_odd: bool) -> array<u32, 5> {
+    let prefix = select(0x02u, 0x03u, y_odd);
+    var block: array<u32, 16>;
+    block[0] = (prefix << 24u) | (x[7] >> 8u);
+    block[1] = (x[7] << 24u) | (x[6] >> 8u);
+    block[2] = (x[6] << 24u) | (x[5] >> 8u);
+    block[3] = (x[5] << 24u) | (x[4] >> 8u);
+    block[4] = (x[4] << 24u) | (x[3] >> 8u);
+    block[5] = (x[3] << 24u) | (x[2] >> 8u);
+    block[6] = (x[2] << 24u) | (x[1] >> 8u);
+    block[7] = (x[1] << 24u) | (x[0] >> 8u);
+    block[8] = (x[0] << 24u) | 0x00800000u;
+    block[15] = 33u * 8u;
+    return ripemd160_of_sha256(sha256_compress(sha256_iv(), block));
+}
+
+// 04 || x || y: 65 bytes, two SHA-256 blocks
+fn hash160_uncompressed(x: array<u32, 8>, y: array<u32, 8>) -> array<u32, 5> {
+    var block: array<u32, 16>;
+    block[0] = (0x04u << 24u) | (x[7] >> 8u);
+    block[1] = (x[7] << 24u) | (x[6] >> 8u);
+    block[2] = (x[6] << 24u) | (x[5] >> 8u);
+    block[3] = (x[5] << 24u) | (x[4] >> 8u);
+    block[4] = (x[4] << 24u) | (x[3] >> 8u);
+    block[5] = (x[3] << 24u) | (x[2] >> 8u);
+    block[6] = (x[2] << 24u) | (x[1] >> 8u);
+    block[7] = (x[1] << 24u) | (x[0] >> 8u);
+    block[8] = (x[0] << 24u) | (y[7] >> 8u);
+    block[9] = (y[7] << 24u) | (y[6] >> 8u);
+    block[10] = (y[6] << 24u) | (y[5] >> 8u);
+    block[11] = (y[5] << 24u) | (y[4] >> 8u);
+    block[12] = (y[4] << 24u) | (y[3] >> 8u);
+    block[13] = (y[3] << 24u) | (y[2] >> 8u);
+    block[14] = (y[2] << 24u) | (y[1] >> 8u);
+    block[15] = (y[1] << 24u) | (y[0] >> 8u);
+    let state = sha256_compress(sha256_iv(), block);
+
+    var tail: array<u32, 16>;
+    tail[0] = (y[0] << 24u) | 0x00800000u;
+    tail[15] = 65u * 8u;
+    return ripemd160_of_sha256(sha256_compress(state, tail));
+}
+
+// -----------------------------------------------------------------------------
+// Target lookup (binary search over the sorted list)
+// -----------------------------------------------------------------------------
+
+fn is_target(h: array<u32, 5>) -> bool {
+    var lo = 0u;
+    var hi = config.num_targets;
+    while (lo < hi) {
+        let mid = (lo + hi) / 2u;
+        let t = targets[mid];
+        var order = 0;
+        if (t[0] != h[0]) {
+            order = select(1, -1, t[0] < h[0]);
+        } else if (t[1] != h[1]) {
+            order = select(1, -1, t[1] < h[1]);
+        } else if (t[2] != h[2]) {
+            order = select(1, -1, t[2] < h[2]);
+        } else if (t[3] != h[3]) {
+            order = select(1, -1, t[3] < h[3]);
+        } else if (t[4] != h[4]) {
+            order = select(1, -1, t[4] < h[4]);
+        }
+        if (order == 0) {
+            return true;
+        }
+        if (order < 0) {
+            lo = mid + 1u;
+        } else {
+            hi = mid;
+        }
+    }
+    return false;
+}
+
+fn record_hit(thread: u32, offset: u32, form: u32) {
+    let idx = atomicAdd(&hit_count, 1u);
+    if (idx < arrayLength(&hits)) {
+        var hit: ScanHit;
+        hit.thread = thread;
+        hit.offset = offset;
+        hit.form = form;
+        hit._padding = 0u;
+        hits[idx] = hit;
+    }
+}
+
+fn check_point(x: array<u32, 8>, y: array<u32, 8>, thread: u32, offset: u32) {
+    let cx = fe_canonical(x);
+    let cy = fe_canonical(y);
+    if ((config.forms & FORM_COMPRESSED) != 0u) {
+        if (is_target(hash160_compressed(cx, (cy[0] & 1u) == 1u))) {
+            record_hit(thread, offset, FORM_COMPRESSED);
+        }
+    }
+    if ((config.forms & FORM_UNCOMPRESSED) != 0u) {
+        if (is_target(hash160_uncompressed(cx, cy))) {
+            record_hit(thread, offset, FORM_UNCOMPRESSED);
+        }
+    }
+}
+
+// -----------------------------------------------------------------------------
+// Main compute shader
+// -----------------------------------------------------------------------------
+
+@compute @workgroup_size(64)
+fn main(@builtin(global_invocation_id) global_id: vec3<u32>) {
+    let tid = global_id.x;
+    if (tid >= config.num_threads) {
+        return;
+    }
+
+    var p = points[tid];
+    // prod[i] = dx_0 · dx_1 · .. · dx_i with dx_i = x((i + 1)·G) - x(P)
+    var prod: array<array<u32, 8>, SCAN_BATCH>;
+
+    for (var batch = 0u; batch < config.batches_per_call; batch = batch + 1u) {
+        let first = batch * SCAN_BATCH;
+        check_point(p.x, p.y, tid, first);
+
+        // The host keeps P away from ±i·G, so no dx is zero
+        var acc = fe_one();
+        for (var i = 0u; i < SCAN_BATCH; i = i + 1u) {
+            acc = fe_mul(acc, fe_sub(g_multiples[i].x, p.x));
+            prod[i] = acc;
+        }
+
+        // Walk back, peeling one 1/dx_i off the shared inverse per point
+        var inv = fe_inv(acc);
+        var next: AffinePoint;
+        for (var j = 0u; j < SCAN_BATCH; j = j + 1u) {
+            let i = SCAN_BATCH - 1u - j;
+            let g = g_multiples[i];
+            var dx_inv = inv;
+            if (i > 0u) {
+                dx_inv = fe_mul(inv, prod[i - 1u]);
+                inv = fe_mul(inv, fe_sub(g.x, p.x));
+            }
+            let q = affine_add_with_inv(p.x, p.y, g.x, g.y, dx_inv);
+            if (i == SCAN_BATCH - 1u) {
+                next = q;
+            } else {
+                check_point(q.x, q.y, tid, first + i + 1u);
+            }
+        }
+        p = next;
+    }
+
+    points[tid] = p;
+}
@@ -14,9 +14,22 @@ struct Config {
     num_kangaroos: u32,
     steps_per_call: u32,
     jump_table_size: u32,
-    _padding: u32
+    flags: u32,
+    stuck_steps: u32
 }
 
+// Config.flags bits (must match CONFIG_* in gpu/mod.rs)
+const FLAG_NEGATION_MAP: u32 = 1u;
+const FLAG_RESTART_ON_DP: u32 = 2u;
+
+// Kangaroo.is_active of a parked kangaroo (must match KANGAROO_PARKED)
+const KANGAROO_PARKED: u32 = 2u;
+
+// DistinguishedPoint.kind (must match DP_KIND_* in gpu/mod.rs)
+const DP_KIND_POINT: u32 = 0u;
+const DP_KIND_STUCK: u32 = 1u;
+const DP_KIND_RESTART: u32 = 2u;
+
 // Must match Rust GpuKangaroo struct layout!
 struct Kangaroo {
     x: array<u32, 8>,
@@ -24,7 +37,18 @@ struct Kangaroo {
     dist: array<u32, 8>,
     ktype: u32,
     is_active: u32,
-    _padding: array<u32, 6>
+    last_jump: u32,
+    steps_since_dp: u32,
+    cycle_steps: u32,
+    cycle_x: array<u32, 2>,
+    target_index: u32
+}
+
+// Must match Rust GpuDpCounters
+struct DpCounters {
+    count: atomic<u32>,
+    overflow: atomic<u32>,
+    restarts: atomic<u32>
 }
 
 struct DistinguishedPoint {
@@ -32,7 +56,9 @@ struct DistinguishedPoint {
     dist: array<u32, 8>,
     ktype: u32,
     kangaroo_id: u32,
-    _padding: array<u32, 6>
+    kind: u32,
+    target_index: u32,
+    _padding: array<u32, 4>
 }
 
 // -----------------------------------------------------------------------------
@@ -40,11 +66,12 @@ struct DistinguishedPoint {
 // -----------------------------------------------------------------------------
 
 @group(0) @binding(0) var<uniform> config: Config;
-@group(0) @binding(1) var<storage, read> jump_points: array<AffinePoint, 256>;
-@group(0) @binding(2) var<storage, read> jump_distances: array<array<u32, 8>, 256>;
+@group(0) @binding(1) var<storage, read> jump_points: array<AffinePoint>;
+@group(0) @binding(2) var<storage, read> jump_distances: array<array<u32, 8>>;
 @group(0) @binding(3) var<storage, read_write> kangaroos: array<Kangaroo>;
 @group(0) @binding(4) var<storage, read_write> dp_buffer: array<DistinguishedPoint>;
-@group(0) @binding(5) var<storage, read_write> dp_count: atomic<u32>;
+@group(0) @binding(5) var<storage, read_write> dp_counters: DpCounters;
+@group(0) @binding(6) var<storage, read> restart_pool: array<Kangaroo>;
 
 // Shared memory for batch inversion (Montgomery's trick)
 // We batch invert (x_jump - x_point) for all threads
@@ -52,60 +79,61 @@ var<workgroup> shared_dx: array<array<u32, 8>, 64>;      // Delta X values
 var<workgroup> shared_prod: array<array<u32, 8>, 64>;    // Prefix products
 var<workgroup> shared_suffix: array<array<u32, 8>, 64>;
 
+// -----------------------------------------------------------------------------
+// Jump selection from the top limb of x, clear of the low bits the DP test uses
+// (must match jump_index in cpu/jumps.rs)
+// -----------------------------------------------------------------------------
+
+fn jump_index(x: array<u32, 8>) -> u32 {
+    let h = x[7] * 0x9e3779b1u;
+    return (h ^ (h >> 16u)) % config.jump_table_size;
+}
+
+// -----------------------------------------------------------------------------
+// DP test against the full 256-bit mask (dp_bits may exceed 32)
+// -----------------------------------------------------------------------------
+
+fn is_distinguished(x: array<u32, 8>) -> bool {
+    let lo = vec4<u32>(x[0], x[1], x[2], x[3]) & config.dp_mask_lo;
+    let hi = vec4<u32>(x[4], x[5], x[6], x[7]) & config.dp_mask_hi;
+    return all((lo | hi) == vec4<u32>(0u));
+}
+
 // -----------------------------------------------------------------------------
 // Store distinguished point
 // -----------------------------------------------------------------------------
 
-fn store_dp(k: Kangaroo, kangaroo_id: u32) {
-    let idx = atomicAdd(&dp_count, 1u);
-
-    if (idx < 65536u) {
-        var dp: DistinguishedPoint;
-        dp.x = k.x;
-        dp.dist = k.dist;
-        dp.ktype = k.ktype;
-        dp.kangaroo_id = kangaroo_id;
-        dp._padding = array<u32, 6>(0u, 0u, 0u, 0u, 0u, 0u);
-        dp_buffer[idx] = dp;
+// Returns false if the buffer is full; the caller then keeps the kangaroo where
+// it is, so nothing is lost and the DP is stored by a later dispatch
+fn store_dp(k: Kangaroo, kangaroo_id: u32, kind: u32) -> bool {
+    let idx = atomicAdd(&dp_counters.count, 1u);
+
+    if (idx >= arrayLength(&dp_buffer)) {
+        atomicAdd(&dp_counters.overflow, 1u);
+        return false;
     }
+
+    var dp: DistinguishedPoint;
+    dp.x = k.x;
+    dp.dist = k.dist;
+    dp.ktype = k.ktype;
+    dp.kangaroo_id = kangaroo_id;
+    dp.kind = kind;
+    dp.target_index = k.target_index;
+    dp._padding = array<u32, 4>(0u, 0u, 0u, 0u);
+    dp_buffer[idx] = dp;
+    return true;
 }
 
 // -----------------------------------------------------------------------------
-// Affine point addition: R = P + Q (both affine)
-// Returns (x3, y3) given (x1, y1), (x2, y2), and precomputed inv = 1/(x2-x1)
-// 
-// Formula:
-//   λ = (y2 - y1) * inv
-//   x3 = λ² - x1 - x2
-//   y3 = λ * (x1 - x3) - y1
-//
-// Cost: 2M + 1S (with precomputed inverse)
-// Compare to Jacobian mixed add: 8M + 4S
+// Two's complement negation of a 256-bit distance: -a mod 2^256
 // -----------------------------------------------------------------------------
 
-fn affine_add_with_inv(
-    x1: array<u32, 8>,
-    y1: array<u32, 8>,
-    x2: array<u32, 8>,
-    y2: array<u32, 8>,
-    dx_inv: array<u32, 8>
-) -> AffinePoint {
-    // λ = (y2 - y1) / (x2 - x1) = (y2 - y1) * dx_inv
-    let dy = fe_sub(y2, y1);
-    let lambda = fe_mul(dy, dx_inv);
-    
-    // x3 = λ² - x1 - x2
-    let lambda_sq = fe_square(lambda);
-    let x3 = fe_sub(fe_sub(lambda_sq, x1), x2);
-    
-    // y3 = λ * (x1 - x3) - y1
-    let x1_minus_x3 = fe_sub(x1, x3);
-    let y3 = fe_sub(fe_mul(lambda, x1_minus_x3), y1);
-    
-    var result: AffinePoint;
-    result.x = x3;
-    result.y = y3;
-    return result;
+fn scalar_neg_256(a: array<u32, 8>) -> array<u32, 8> {
+    var inv: array<u32, 8>;
+    inv[0] = ~a[0]; inv[1] = ~a[1]; inv[2] = ~a[2]; inv[3] = ~a[3];
+    inv[4] = ~a[4]; inv[5] = ~a[5]; inv[6] = ~a[6]; inv[7] = ~a[7];
+    return scalar_add_256(inv, array<u32, 8>(1u, 0u, 0u, 0u, 0u, 0u, 0u, 0u));
 }
 
 // -----------------------------------------------------------------------------
@@ -122,7 +150,7 @@ fn main(@builtin(global_invocation_id) global_id: vec3<u32>, @builtin(local_invo
     var valid = false;
     if (kid < config.num_kangaroos) {
         k = kangaroos[kid];
-        if (k.is_active != 0u) {
+        if (k.is_active == 1u) {
             valid = true;
         }
     }
@@ -140,13 +168,25 @@ fn main(@builtin(global_invocation_id) global_id: vec3<u32>, @builtin(local_invo
         py = fe_one();
     }
 
-    // Track if we already stored a DP this batch
-    var dp_stored = false;
+    // Set when the DP buffer is full; the kangaroo then waits for the next dispatch
+    var halted = false;
+
+    // Set once this kangaroo cycles or goes too long without a DP; it then idles
+    // until the host reseeds it
+    var parked = false;
+
+    let negation_map = (config.flags & FLAG_NEGATION_MAP) != 0u;
+    let restart_on_dp = (config.flags & FLAG_RESTART_ON_DP) != 0u;
 
     // Perform jumps
     for (var step = 0u; step < config.steps_per_call; step++) {
         // Select jump based on x coordinate
-        let jump_idx = px[0] & 0xFFu;
+        var jump_idx = jump_index(px);
+        // Negation map: P -> -(P + J) -> P is a fruitless 2-cycle whenever the
+        // same jump comes up twice in a row, so never repeat the previous jump
+        if (negation_map && jump_idx == k.last_jump) {
+            jump_idx = (jump_idx + 1u) % config.jump_table_size;
+        }
         let jump_point = jump_points[jump_idx];
         let jump_dist = jump_distances[jump_idx];
         
@@ -364,26 +404,76 @@ fn main(@builtin(global_invocation_id) global_id: vec3<u32>, @builtin(local_invo
         // POINT ADDITION AND DP CHECK
         // =====================================================================
 
-        if (valid) {
+        // Set when this kangaroo took over a fresh walk from the restart pool;
+        // dx_inv belongs to the old position, so it skips the rest of this step
+        var restarted = false;
+
+        if (valid && !parked && !halted) {
             // Check for DP before the jump (on current position)
-            if (!dp_stored) {
-                if ((px[0] & config.dp_mask_lo.x) == 0u) {
-                    k.x = px;
-                    k.y = py;
-                    store_dp(k, kid);
-                    dp_stored = true;
+            if (is_distinguished(px)) {
+                k.steps_since_dp = 0u;
+                k.x = px;
+                k.y = py;
+                if (restart_on_dp) {
+                    // Gaudry–Schost: the walk ends here and continues from a fresh
+                    // start point; with the pool used up, the host restarts it
+                    let slot = atomicAdd(&dp_counters.restarts, 1u);
+                    if (slot < arrayLength(&restart_pool)) {
+                        halted = !store_dp(k, kid, DP_KIND_POINT);
+                        if (!halted) {
+                            k = restart_pool[slot];
+                            px = k.x;
+                            py = k.y;
+                            restarted = true;
+                        }
+                    } else {
+                        parked = store_dp(k, kid, DP_KIND_RESTART);
+                        halted = !parked;
+                    }
+                } else {
+                    halted = !store_dp(k, kid, DP_KIND_POINT);
                 }
             }
 
             // Perform affine addition: P = P + jump_point
             // Skip if dx was zero (point collision - astronomically unlikely)
-            if (!dx_was_zero) {
+            if (!dx_was_zero && !halted && !parked && !restarted) {
                 let result = affine_add_with_inv(px, py, jump_point.x, jump_point.y, dx_inv);
                 px = result.x;
                 py = result.y;
 
                 // Update distance
                 k.dist = scalar_add_256(k.dist, jump_dist);
+
+                // Keep the class representative with even y; -P sits at the negated distance
+                if (negation_map) {
+                    k.last_jump = jump_idx;
+                    if ((py[0] & 1u) == 1u) {
+                        py = fe_sub(fe_zero(), py);
+                        k.dist = scalar_neg_256(k.dist);
+                    }
+                }
+            }
+
+            if (config.stuck_steps != 0u && !halted && !parked && !restarted) {
+                k.steps_since_dp += 1u;
+
+                // Brent: a walk that comes back to the x saved at the last power-of-two
+                // step count is in a cycle it will never leave
+                k.cycle_steps += 1u;
+                let cycled = px[0] == k.cycle_x[0] && px[1] == k.cycle_x[1];
+                if ((k.cycle_steps & (k.cycle_steps - 1u)) == 0u) {
+                    k.cycle_x[0] = px[0];
+                    k.cycle_x[1] = px[1];
+                }
+
+                if (cycled || k.steps_since_dp >= config.stuck_steps) {
+                    k.x = px;
+                    k.y = py;
+                    // Without room for the report, wait and report again next dispatch
+                    parked = store_dp(k, kid, DP_KIND_STUCK);
+                    halted = !parked;
+                }
             }
         }
     }
@@ -392,6 +482,9 @@ fn main(@builtin(global_invocation_id) global_id: vec3<u32>, @builtin(local_invo
     if (valid) {
         k.x = px;
         k.y = py;
+        if (parked) {
+            k.is_active = KANGAROO_PARKED;
+        }
         kangaroos[kid] = k;
     }
 }
@@ -2,20 +2,39 @@
 //!
 //! Coordinates GPU compute with CPU collision detection.
 
-use crate::cpu::init::{generate_jump_table, initialize_kangaroos};
-use crate::cpu::DPTable;
+use crate::checkpoint::{jump_table_hash, Checkpoint};
+use crate::cpu::gaudry_schost::SetOverlap;
+use crate::cpu::init::{initialize_kangaroos, GridSlice, HerdLayout, Herds, DEFAULT_SEED};
+use crate::cpu::jumps::{generate_jump_table, generate_jump_table_with_base, JumpTableSpec};
+use crate::cpu::{DPTable, DpIngest, Hits, Insertion, TargetSet};
 use crate::crypto::{Point, U256};
 use crate::gpu::{
-    GpuBuffers, GpuConfig, GpuContext, GpuDistinguishedPoint, GpuKangaroo, KangarooPipeline,
+    GpuBuffers, GpuConfig, GpuContext, GpuDistinguishedPoint, GpuDpCounters, GpuKangaroo,
+    KangarooPipeline, CONFIG_NEGATION_MAP, CONFIG_RESTART_ON_DP, DP_HEADER_SIZE, DP_KIND_POINT,
+    DP_KIND_RESTART, DP_KIND_STUCK, DP_SLOTS, KTYPE_TAME, KTYPE_WILD,
 };
 use crate::math::create_dp_mask;
-use anyhow::Result;
+use crate::net::DpClient;
+use crate::range::KeyRange;
+use crate::stats::{RateMeter, SearchModel, SearchStats};
+use crate::tamedb::TameDb;
+use crate::workfile::{WorkFileWriter, WorkHeader};
+use anyhow::{bail, Result};
+use rayon::prelude::*;
+use std::sync::Arc;
 use std::time::Instant;
-use tracing::info;
+use tracing::{info, warn};
 
 const MAX_DISTINGUISHED_POINTS: u32 = 65_536;
+/// Largest DP buffer (per slot) grown into after overflows
+const MAX_DP_CAPACITY: u32 = 1 << 18;
 /// Target dispatch time in milliseconds (stay under TDR threshold)
 const TARGET_DISPATCH_MS: u128 = 50;
+/// A kangaroo is stuck after this many expected DP spacings without a DP
+const STUCK_DP_SPACINGS: u128 = 20;
+/// Fresh walks per restart pool (Gaudry–Schost); kangaroos that find it used up
+/// wait for the host to restart them
+const RESTART_POOL_SIZE: u32 = 16_384;
 
 /// Shared resources for batch mode (pipeline created once, reused)
 #[allow(dead_code)]
@@ -33,16 +52,95 @@ impl SharedResources {
     }
 }
 
+/// Tunables beyond the search parameters
+#[derive(Clone, Debug)]
+pub struct SolverOptions {
+    /// Salt for the herd's start positions; workers sharing a DP table need different seeds.
+    /// Recorded in checkpoints so a run can be replayed.
+    pub seed: u64,
+    /// This device's slice of a start grid shared with the other devices of the
+    /// search; `None` spreads the herd over the whole range
+    pub grid: Option<GridSlice>,
+    /// Walk on {P, -P} classes for ~sqrt(2) fewer expected operations
+    pub negation: bool,
+    /// Herd composition (three and four kangaroos need one pubkey and no negation map)
+    pub herds: Herds,
+    /// Gaudry–Schost instead of kangaroo walks: every walk restarts at a random
+    /// point of its set after each DP (van Oorschot–Wiener herds, no negation map)
+    pub gaudry_schost: Option<SetOverlap>,
+    /// Overlap each dispatch with reading the previous one's DPs
+    pub pipelined: bool,
+    /// Jump table; every device sharing a DP table must use the same one
+    pub jumps: JumpTableSpec,
+    /// Precomputed tame DPs: the herd is all wild and the DP table starts out
+    /// with these (range, DP bits and jumps must be the database's)
+    pub tame_db: Option<Arc<TameDb>>,
+    /// Solve `P = k * base`; G for an ordinary private key
+    pub base: Point,
+}
+
+impl Default for SolverOptions {
+    fn default() -> Self {
+        Self {
+            seed: DEFAULT_SEED,
+            grid: None,
+            negation: false,
+            herds: Herds::Vow,
+            gaudry_schost: None,
+            pipelined: true,
+            jumps: JumpTableSpec::default(),
+            tame_db: None,
+            base: Point::GENERATOR,
+        }
+    }
+}
+
 /// Main Kangaroo solver
 pub struct KangarooSolver {
     ctx: GpuContext,
     pipeline: KangarooPipeline,
     buffers: GpuBuffers,
-    dp_table: DPTable,
+    dp_table: Arc<DPTable>,
+    targets: TargetSet,
     total_ops: u64,
     num_kangaroos: u32,
-    #[allow(dead_code)]
     steps_per_call: u32,
+    pubkey: Point,
+    range: KeyRange,
+    dp_bits: u32,
+    jumps: JumpTableSpec,
+    jump_table_hash: [u8; 32],
+    negation: bool,
+    herds: Herds,
+    gaudry_schost: Option<SetOverlap>,
+    /// Wilds only, against a precomputed tame DP database
+    tame_db: bool,
+    seed: u64,
+    layout: HerdLayout,
+    stuck_steps: u32,
+    reseeds: u64,
+    merges: u64,
+    dp_overflows: u64,
+    model: SearchModel,
+    rate: RateMeter,
+    work_file: Option<WorkFileWriter>,
+    server: Option<DpClient>,
+    ingest: DpIngest,
+    pipelined: bool,
+    /// Dispatches submitted so far; picks the DP slot
+    frames: u64,
+    in_flight: Option<InFlight>,
+    /// Hits collected by `settle`, applied by the next step
+    hits: Hits,
+}
+
+/// A submitted dispatch whose DPs are being mapped for reading
+struct InFlight {
+    slot: usize,
+    submission: wgpu::SubmissionIndex,
+    /// DPs copied to the staging buffer along with the count
+    copied: u32,
+    mapped: std::sync::mpsc::Receiver<Result<(), wgpu::BufferAsyncError>>,
 }
 
 impl KangarooSolver {
@@ -54,7 +152,100 @@ impl KangarooSolver {
         dp_bits: u32,
         num_kangaroos: u32,
     ) -> Result<Self> {
-        Self::new_internal(ctx, pubkey, start, range_bits, dp_bits, num_kangaroos, true)
+        Self::new_internal(
+            ctx,
+            vec![pubkey],
+            KeyRange::from_bits(start, range_bits),
+            dp_bits,
+            num_kangaroos,
+            SolverOptions::default(),
+            true,
+        )
+    }
+
+    pub fn new_with_options(
+        ctx: GpuContext,
+        pubkey: Point,
+        start: U256,
+        range_bits: u32,
+        dp_bits: u32,
+        num_kangaroos: u32,
+        options: SolverOptions,
+    ) -> Result<Self> {
+        Self::new_internal(
+            ctx,
+            vec![pubkey],
+            KeyRange::from_bits(start, range_bits),
+            dp_bits,
+            num_kangaroos,
+            options,
+            true,
+        )
+    }
+
+    /// Search an exact interval `range` rather than `start + 2^range_bits`
+    pub fn new_in_range(
+        ctx: GpuContext,
+        pubkey: Point,
+        range: KeyRange,
+        dp_bits: u32,
+        num_kangaroos: u32,
+        options: SolverOptions,
+    ) -> Result<Self> {
+        Self::new_internal(
+            ctx,
+            vec![pubkey],
+            range,
+            dp_bits,
+            num_kangaroos,
+            options,
+            true,
+        )
+    }
+
+    /// Solve `pubkey = k * base` for `k` in `range`, a discrete log relative to
+    /// `base` rather than G (e.g. `base = s * G` for keys `a + s * x`)
+    pub fn new_with_base(
+        ctx: GpuContext,
+        pubkey: Point,
+        base: Point,
+        range: KeyRange,
+        dp_bits: u32,
+        num_kangaroos: u32,
+        options: SolverOptions,
+    ) -> Result<Self> {
+        Self::new_internal(
+            ctx,
+            vec![pubkey],
+            range,
+            dp_bits,
+            num_kangaroos,
+            SolverOptions { base, ..options },
+            true,
+        )
+    }
+
+    /// Solve several pubkeys known to lie in the same range: one tame herd is
+    /// shared and the wild herd is split between the targets
+    pub fn new_multi(
+        ctx: GpuContext,
+        pubkeys: Vec<Point>,
+        range: KeyRange,
+        dp_bits: u32,
+        num_kangaroos: u32,
+        options: SolverOptions,
+    ) -> Result<Self> {
+        if pubkeys.is_empty() {
+            bail!("Multi-target solver needs at least one pubkey");
+        }
+        if num_kangaroos / 2 < pubkeys.len() as u32 {
+            bail!(
+                "{} kangaroos cannot give each of {} targets a wild kangaroo",
+                num_kangaroos,
+                pubkeys.len()
+            );
+        }
+        Self::new_internal(ctx, pubkeys, range, dp_bits, num_kangaroos, options, true)
     }
 
     #[allow(dead_code)]
@@ -68,11 +259,11 @@ impl KangarooSolver {
     ) -> Result<Self> {
         Self::new_internal(
             ctx.clone(),
-            pubkey,
-            start,
-            range_bits,
+            vec![pubkey],
+            KeyRange::from_bits(start, range_bits),
             dp_bits,
             num_kangaroos,
+            SolverOptions::default(),
             false,
         )
     }
@@ -130,8 +321,9 @@ impl KangarooSolver {
         dp_bits: u32,
         num_kangaroos: u32,
     ) -> Result<Self> {
-        let jump_table_size = 256u32;
-        let (jump_points, jump_distances) = generate_jump_table(range_bits);
+        let range = KeyRange::from_bits(start, range_bits);
+        let jumps = JumpTableSpec::default();
+        let (jump_points, jump_distances) = generate_jump_table(&range, &jumps)?;
 
         // Create DP mask
         let dp_mask = create_dp_mask(dp_bits);
@@ -150,8 +342,10 @@ impl KangarooSolver {
             dp_mask_hi: [dp_mask[4], dp_mask[5], dp_mask[6], dp_mask[7]],
             num_kangaroos,
             steps_per_call,
-            jump_table_size,
-            _padding: 0,
+            jump_table_size: jumps.size,
+            flags: 0,
+            stuck_steps: stuck_steps(dp_bits),
+            _padding: [0; 3],
         };
 
         // Create buffers (reusing bind_group_layout from shared pipeline)
@@ -164,14 +358,23 @@ impl KangarooSolver {
             &jump_distances,
             num_kangaroos,
             max_dps,
+            0,
         )?;
 
         // Initialize kangaroos
-        let kangaroos = initialize_kangaroos(&pubkey, &start, range_bits, num_kangaroos)?;
+        let kangaroos = initialize_kangaroos(
+            &pubkey,
+            &Point::GENERATOR,
+            &range,
+            num_kangaroos,
+            DEFAULT_SEED,
+        )?;
+        let layout = HerdLayout::new(vec![pubkey], range, false);
         upload_kangaroos(ctx, &buffers, &kangaroos)?;
 
         // Use start for key computation: k = start + tame_dist - wild_dist
         // Pass full 256-bit start to DPTable
+        let dp_table = Arc::new(DPTable::new(start));
 
         // Clone pipeline (wgpu types are Arc-wrapped, so this is cheap)
         let pipeline_clone = KangarooPipeline {
@@ -183,20 +386,46 @@ impl KangarooSolver {
             ctx: ctx.clone(),
             pipeline: pipeline_clone,
             buffers,
-            dp_table: DPTable::new(start),
+            dp_table: dp_table.clone(),
+            targets: TargetSet::new(vec![pubkey]),
             total_ops: 0,
             num_kangaroos,
             steps_per_call,
+            pubkey,
+            range,
+            dp_bits,
+            jumps,
+            jump_table_hash: jump_table_hash(&jump_distances),
+            negation: false,
+            herds: Herds::Vow,
+            gaudry_schost: None,
+            tame_db: false,
+            seed: DEFAULT_SEED,
+            layout,
+            stuck_steps: stuck_steps(dp_bits),
+            reseeds: 0,
+            merges: 0,
+            dp_overflows: 0,
+            model: SearchModel::new(&range, u64::from(num_kangaroos), dp_bits, false, Herds::Vow),
+            rate: RateMeter::new(),
+            work_file: None,
+            server: None,
+            ingest: DpIngest::spawn(dp_table),
+            pipelined: true,
+            frames: 0,
+            in_flight: None,
+            hits: Vec::new(),
         })
     }
 
+    #[allow(clippy::too_many_arguments)]
     fn new_internal(
         ctx: GpuContext,
-        pubkey: Point,
-        start: U256,
-        range_bits: u32,
+        pubkeys: Vec<Point>,
+        range: KeyRange,
         dp_bits: u32,
         num_kangaroos: u32,
+        options: SolverOptions,
         verbose: bool,
     ) -> Result<Self> {
         if verbose {
@@ -210,14 +439,34 @@ impl KangarooSolver {
         if verbose {
             info!("Generating jump table...");
         }
-        let jump_table_size = 256u32;
-        let (jump_points, jump_distances) = generate_jump_table(range_bits);
+        let (jump_points, jump_distances) =
+            generate_jump_table_with_base(&range, &options.jumps, &options.base)?;
         if verbose {
-            info!("Jump table generated: {} entries", jump_table_size);
+            info!("Jump table generated: {} entries", options.jumps.size);
             for (i, dist) in jump_distances.iter().enumerate().take(4) {
                 info!("Jump dist[{}] = 0x{:08x}", i, dist[0]);
             }
         }
+        if let Some(db) = &options.tame_db {
+            if pubkeys.len() != 1
+                || range != db.range
+                || dp_bits != db.dp_bits
+                || options.jumps != db.jumps
+            {
+                bail!(
+                    "A search against precomputed tames needs their range, DP bits and jump table"
+                );
+            }
+            if db.jump_table_hash != jump_table_hash(&jump_distances) {
+                bail!("Tame DP database jump table does not match this build");
+            }
+            if options.negation || options.herds != Herds::Vow || options.gaudry_schost.is_some() {
+                bail!("Precomputed tames take wild kangaroos without the negation map");
+            }
+            if options.base != Point::GENERATOR {
+                bail!("Precomputed tames are multiples of G; they cannot serve another base");
+            }
+        }
 
         // Create DP mask
         let dp_mask = create_dp_mask(dp_bits);
@@ -234,13 +483,22 @@ impl KangarooSolver {
             MAX_DISTINGUISHED_POINTS,
         );
 
+        if let Some(sets) = options.gaudry_schost {
+            sets.validate()?;
+            if options.negation || options.herds != Herds::Vow {
+                bail!("Gaudry–Schost takes tame and wild herds without the negation map");
+            }
+        }
+        let flags = config_flags(options.negation, options.gaudry_schost.is_some());
         let config = GpuConfig {
             dp_mask_lo: [dp_mask[0], dp_mask[1], dp_mask[2], dp_mask[3]],
             dp_mask_hi: [dp_mask[4], dp_mask[5], dp_mask[6], dp_mask[7]],
             num_kangaroos,
             steps_per_call,
-            jump_table_size,
-            _padding: 0,
+            jump_table_size: options.jumps.size,
+            flags,
+            stuck_steps: stuck_steps(dp_bits),
+            _padding: [0; 3],
         };
         if verbose {
             info!("Config created: steps_per_call={}", steps_per_call);
@@ -251,6 +509,11 @@ impl KangarooSolver {
             info!("Creating GPU buffers...");
         }
         let max_dps = MAX_DISTINGUISHED_POINTS;
+        let restart_pool_size = if options.gaudry_schost.is_some() {
+            RESTART_POOL_SIZE
+        } else {
+            0
+        };
         let buffers = GpuBuffers::new(
             &ctx,
             &pipeline,
@@ -259,56 +522,188 @@ impl KangarooSolver {
             &jump_distances,
             num_kangaroos,
             max_dps,
+            restart_pool_size,
         )?;
 
         // Initialize kangaroos
-        let kangaroos = initialize_kangaroos(&pubkey, &start, range_bits, num_kangaroos)?;
+        let layout = HerdLayout::for_search(
+            pubkeys.clone(),
+            range,
+            options.negation,
+            options.herds,
+            options.base,
+        )?;
+        let kangaroos = match (options.gaudry_schost, &options.tame_db) {
+            (Some(sets), _) => sets.herd(&layout, num_kangaroos, options.seed),
+            (None, Some(_)) => layout.single_herd(KTYPE_WILD, num_kangaroos, options.seed),
+            (None, None) => layout.herd_in(num_kangaroos, options.seed, options.grid),
+        };
         upload_kangaroos(&ctx, &buffers, &kangaroos)?;
 
+        let dp_table = Arc::new(DPTable::for_search(
+            pubkeys.clone(),
+            range,
+            options.negation,
+            options.herds,
+            options.base,
+        ));
+        if let Some(db) = &options.tame_db {
+            for dp in &db.dps {
+                dp_table.insert(*dp);
+            }
+        }
+
         // Create solver instance
         let mut solver = Self {
             ctx,
             pipeline,
             buffers,
-            dp_table: DPTable::new(start),
+            dp_table: dp_table.clone(),
+            targets: TargetSet::with_base(pubkeys.clone(), options.base),
             total_ops: 0,
             num_kangaroos,
             steps_per_call,
+            pubkey: pubkeys[0],
+            range,
+            dp_bits,
+            jumps: options.jumps,
+            jump_table_hash: jump_table_hash(&jump_distances),
+            negation: options.negation,
+            herds: options.herds,
+            gaudry_schost: options.gaudry_schost,
+            tame_db: options.tame_db.is_some(),
+            seed: options.seed,
+            layout,
+            stuck_steps: stuck_steps(dp_bits),
+            reseeds: 0,
+            merges: 0,
+            dp_overflows: 0,
+            model: match (options.gaudry_schost, &options.tame_db) {
+                (Some(sets), _) => {
+                    SearchModel::gaudry_schost(&range, u64::from(num_kangaroos), dp_bits, sets)
+                }
+                (None, Some(db)) => SearchModel::tame_db(
+                    &range,
+                    u64::from(num_kangaroos),
+                    dp_bits,
+                    db.dps.len() as u64,
+                ),
+                (None, None) => SearchModel::new(
+                    &range,
+                    u64::from(num_kangaroos),
+                    dp_bits,
+                    options.negation,
+                    options.herds,
+                ),
+            },
+            rate: RateMeter::new(),
+            work_file: None,
+            server: None,
+            ingest: DpIngest::spawn(dp_table),
+            pipelined: options.pipelined,
+            frames: 0,
+            in_flight: None,
+            hits: Vec::new(),
         };
 
+        for slot in 0..DP_SLOTS {
+            solver.refill_restart_pool(slot, restart_pool_size);
+        }
+
         // Auto-calibrate steps_per_call
         solver.calibrate(dp_bits, verbose);
 
         // Update config buffer with calibrated value and correct DP mask
-        let final_config = GpuConfig {
-            dp_mask_lo: [dp_mask[0], dp_mask[1], dp_mask[2], dp_mask[3]],
-            dp_mask_hi: [dp_mask[4], dp_mask[5], dp_mask[6], dp_mask[7]],
-            num_kangaroos,
-            steps_per_call: solver.steps_per_call,
-            jump_table_size: 256,
-            _padding: 0,
-        };
-        solver.ctx.queue.write_buffer(
-            &solver.buffers.config_buffer,
-            0,
-            bytemuck::bytes_of(&final_config),
-        );
-
-        // Reset DP count after calibration warmup
-        solver.reset_dp_count()?;
+        solver.write_config();
 
         Ok(solver)
     }
 
-    /// Run one batch of GPU operations
+    /// Run one batch of GPU operations; returns the (first) target's key once solved
     pub fn step(&mut self) -> Result<Option<Vec<u8>>> {
-        // Create command encoder
+        self.step_targets()?;
+        Ok(self.targets.key(0))
+    }
+
+    /// Run one batch of GPU operations; returns the targets it solved as (index, key)
+    ///
+    /// Dispatches are pipelined: this submits the next batch first and then reads
+    /// the previous one's DPs while the GPU works. DPs go to the DP table on the
+    /// ingestion thread, so a collision may be reported one or two calls late.
+    pub fn step_targets(&mut self) -> Result<Vec<(usize, Vec<u8>)>> {
+        if let Some(key) = self.server.as_ref().and_then(DpClient::found_key) {
+            let solved = self.targets.solve(0, &key);
+            return Ok(self.keys_of(solved));
+        }
+
+        let submitted = self.submit_frame();
+
+        // Update operation count
+        self.total_ops += (self.num_kangaroos as u64) * (self.steps_per_call as u64);
+        self.rate.update(self.total_ops, Instant::now());
+
+        // Log progress every 10M ops (less verbose)
+        if self.total_ops % 10_000_000 < (self.num_kangaroos as u64 * self.steps_per_call as u64) {
+            let (tame, wild) = self.dp_table.count_by_type();
+            tracing::info!(
+                "Ops: {}M | DPs: {} ({} tame, {} wild) | Reseeds: {} | Merges: {} | Overflows: {}",
+                self.total_ops / 1_000_000,
+                self.dp_table.total_dps(),
+                tame,
+                wild,
+                self.reseeds,
+                self.merges,
+                self.dp_overflows
+            );
+        }
+
+        let previous = if self.pipelined {
+            self.in_flight.replace(submitted)
+        } else {
+            Some(submitted)
+        };
+        if let Some(frame) = previous {
+            self.collect_frame(frame)?;
+        }
+
+        let mut hits = std::mem::take(&mut self.hits);
+        if self.pipelined {
+            hits.extend(self.ingest.drain());
+        } else {
+            hits.extend(self.ingest.flush());
+        }
+        let solved = self.apply_hits(hits);
+
+        if !solved.is_empty() && !self.targets.all_solved() {
+            // Wilds of a solved target have nothing left to find
+            self.retarget_wilds()?;
+        }
+
+        Ok(self.keys_of(solved))
+    }
+
+    /// Submit one dispatch into the next DP slot and start mapping its DPs
+    fn submit_frame(&mut self) -> InFlight {
+        let slot = (self.frames % DP_SLOTS as u64) as usize;
+        self.frames += 1;
+        let buffers = &self.buffers.slots[slot];
+
+        // Expected DPs per dispatch with plenty of headroom; a busier dispatch is
+        // finished with a second copy in `collect_frame`
+        let expected = (u64::from(self.num_kangaroos) * u64::from(self.steps_per_call))
+            >> self.dp_bits.min(63);
+        let copied = (expected * 2 + 256).min(u64::from(buffers.capacity)) as u32;
+        let dp_size = std::mem::size_of::<GpuDistinguishedPoint>() as u64;
+        let header = DP_HEADER_SIZE as u64;
+        let mapped_size = header + u64::from(copied) * dp_size;
+
         let mut encoder = self
             .ctx
             .device
             .create_command_encoder(&wgpu::CommandEncoderDescriptor {
                 label: Some("Kangaroo Encoder"),
             });
+        encoder.clear_buffer(&buffers.dp_count_buffer, 0, None);
 
         // Dispatch compute
         {
@@ -318,84 +713,186 @@ impl KangarooSolver {
             });
 
             pass.set_pipeline(&self.pipeline.pipeline);
-            pass.set_bind_group(0, &self.buffers.bind_group, &[]);
+            pass.set_bind_group(0, &buffers.bind_group, &[]);
 
             let workgroups = self.num_kangaroos.div_ceil(64); // Workgroup size is 64
             pass.dispatch_workgroups(workgroups, 1, 1);
         }
 
-        // Copy DP count for readback (first 4 bytes of staging)
+        // Counters, then the DPs, into the slot's staging buffer
         encoder.copy_buffer_to_buffer(
-            &self.buffers.dp_count_buffer,
+            &buffers.dp_count_buffer,
             0,
-            &self.buffers.staging_buffer,
+            &buffers.staging_buffer,
             0,
-            4,
+            header,
+        );
+        encoder.copy_buffer_to_buffer(
+            &buffers.dp_buffer,
+            0,
+            &buffers.staging_buffer,
+            header,
+            mapped_size - header,
         );
 
-        // Submit
-        self.ctx.queue.submit(Some(encoder.finish()));
+        let submission = self.ctx.queue.submit(Some(encoder.finish()));
 
-        // Update operation count
-        self.total_ops += (self.num_kangaroos as u64) * (self.steps_per_call as u64);
+        let (tx, rx) = std::sync::mpsc::channel();
+        buffers.staging_buffer.slice(0..mapped_size).map_async(
+            wgpu::MapMode::Read,
+            move |result| {
+                let _ = tx.send(result);
+            },
+        );
 
-        // Log progress every 10M ops (less verbose)
-        if self.total_ops % 10_000_000 < (self.num_kangaroos as u64 * self.steps_per_call as u64) {
-            let (tame, wild) = self.dp_table.count_by_type();
-            tracing::info!(
-                "Ops: {}M | DPs: {} ({} tame, {} wild)",
-                self.total_ops / 1_000_000,
-                self.dp_table.total_dps(),
-                tame,
-                wild
-            );
+        InFlight {
+            slot,
+            submission,
+            copied,
+            mapped: rx,
         }
+    }
 
-        // Read back DP count and process if any found
-        // TODO: Optimization: Use double buffering for async readback.
-        // Currently we block here waiting for GPU to finish execution and transfer data.
-        // With double buffering, we could dispatch the next batch while waiting for
-        // the previous one, keeping GPU fully occupied.
-        // Needs:
-        // 1. Two sets of buffers (or at least staging buffers)
-        // 2. State machine to manage "Dispatch A -> Read B -> Dispatch B -> Read A"
-        let dp_count = self.read_dp_count()?;
-        if dp_count > 0 {
-            // Copy DP buffer for readback
-            let mut encoder2 =
-                self.ctx
-                    .device
-                    .create_command_encoder(&wgpu::CommandEncoderDescriptor {
-                        label: Some("DP Readback"),
-                    });
-
-            let dp_size = std::mem::size_of::<GpuDistinguishedPoint>();
-            let max_dps = MAX_DISTINGUISHED_POINTS as usize;
-            let actual_count = (dp_count as usize).min(max_dps);
-            let copy_size = (actual_count * dp_size) as u64;
-
-            encoder2.copy_buffer_to_buffer(
-                &self.buffers.dp_buffer,
-                0,
-                &self.buffers.staging_buffer,
-                4,
-                copy_size,
-            );
+    /// Wait for a submitted dispatch and hand its DPs on
+    fn collect_frame(&mut self, frame: InFlight) -> Result<()> {
+        let staging = &self.buffers.slots[frame.slot].staging_buffer;
+        self.ctx
+            .device
+            .poll(wgpu::PollType::Wait {
+                submission_index: Some(frame.submission),
+                timeout: None,
+            })
+            .unwrap();
+        frame.mapped.recv()??;
+
+        let dp_size = std::mem::size_of::<GpuDistinguishedPoint>();
+        let mapped_size = DP_HEADER_SIZE + frame.copied as usize * dp_size;
+        let data = staging.slice(0..mapped_size as u64).get_mapped_range();
+        let counters = *bytemuck::from_bytes::<GpuDpCounters>(&data[..DP_HEADER_SIZE]);
+        let stored = counters.count.min(self.buffers.slots[frame.slot].capacity);
+        let mut dps: Vec<GpuDistinguishedPoint> = data[DP_HEADER_SIZE..]
+            .chunks_exact(dp_size)
+            .take(stored as usize)
+            .map(|chunk| *bytemuck::from_bytes::<GpuDistinguishedPoint>(chunk))
+            .collect();
+        drop(data);
+        staging.unmap();
+
+        if stored > frame.copied {
+            dps.extend(self.read_dps(frame.slot, frame.copied, stored - frame.copied)?);
+        }
+        if counters.overflow > 0 {
+            // Nothing was lost (those kangaroos wait on their DP), but make room
+            self.dp_overflows += u64::from(counters.overflow);
+            self.relieve_overflow(frame.slot, counters.overflow);
+        }
 
-            self.ctx.queue.submit(Some(encoder2.finish()));
+        let (stuck, mut dps): (Vec<_>, Vec<_>) =
+            dps.into_iter().partition(|dp| dp.kind == DP_KIND_STUCK);
+        for report in &stuck {
+            self.reseed(report.kangaroo_id, report.ktype, report.target);
+            self.reseeds += 1;
+        }
+        if self.gaudry_schost.is_some() {
+            // The next dispatch of this slot hands out fresh walks again
+            self.refill_restart_pool(frame.slot, counters.restarts.min(RESTART_POOL_SIZE));
+            for dp in dps.iter_mut().filter(|dp| dp.kind == DP_KIND_RESTART) {
+                self.reseed(dp.kangaroo_id, dp.ktype, dp.target);
+                dp.kind = DP_KIND_POINT;
+            }
+        }
+        if let Some(ref mut work_file) = self.work_file {
+            work_file.append(&dps)?;
+        }
+        if let Some(ref mut server) = self.server {
+            // The server owns the DP table; it broadcasts the key when it finds one
+            server.send(&dps)?;
+        } else {
+            self.ingest.submit(dps);
+        }
+        Ok(())
+    }
 
-            // Read back DPs and check for collision
-            let dps = self.read_dps(actual_count as u32)?;
-            for dp in dps {
-                if let Some(key) = self.dp_table.insert_and_check(dp) {
-                    return Ok(Some(key));
+    /// Act on what the DP table found: solved targets, merges and relations
+    fn apply_hits(&mut self, hits: Hits) -> Vec<usize> {
+        let mut solved = Vec::new();
+        for (dp, insertion) in hits {
+            if self.targets.all_solved() {
+                break;
+            }
+            match insertion {
+                Insertion::Key { target, key } => {
+                    solved.extend(self.targets.solve(target as usize, &key));
+                }
+                Insertion::Merged { respawn } => {
+                    // A Gaudry–Schost walk has restarted at its DP already
+                    if self.gaudry_schost.is_none() {
+                        self.reseed(respawn, dp.ktype, dp.target);
+                    }
+                    self.merges += 1;
                 }
+                Insertion::Relation {
+                    a,
+                    b,
+                    candidates,
+                    respawn,
+                } => {
+                    info!("Targets {} and {} are related", a, b);
+                    solved.extend(self.targets.relate(a as usize, b as usize, candidates));
+                    if self.gaudry_schost.is_none() {
+                        self.reseed(respawn, KTYPE_WILD, dp.target);
+                    }
+                    self.merges += 1;
+                }
+                Insertion::Stored => {}
             }
+        }
+        solved
+    }
 
-            self.reset_dp_count()?;
+    /// The DP buffer of `slot` filled up: grow it, or once it is at its maximum
+    /// size, halve `steps_per_call`
+    fn relieve_overflow(&mut self, slot: usize, overflow: u32) {
+        let capacity = self.buffers.slots[slot].capacity;
+        if capacity < MAX_DP_CAPACITY {
+            let grown = (capacity * 2).min(MAX_DP_CAPACITY);
+            self.buffers
+                .resize_slot(&self.ctx, &self.pipeline, slot, grown);
+            warn!(
+                "DP buffer full ({} DPs deferred); grown to {} entries",
+                overflow, grown
+            );
+        } else if self.steps_per_call > 1 {
+            self.steps_per_call /= 2;
+            self.write_config();
+            warn!(
+                "DP buffer full ({} DPs deferred); steps_per_call lowered to {}",
+                overflow, self.steps_per_call
+            );
         }
+    }
 
-        Ok(None)
+    /// Finish the dispatch in flight and wait for the ingestion thread, so the
+    /// herd and the DP table agree (their hits are applied by the next step)
+    fn settle(&mut self) -> Result<()> {
+        if let Some(frame) = self.in_flight.take() {
+            self.collect_frame(frame)?;
+        }
+        let hits = self.ingest.flush();
+        self.hits.extend(hits);
+        Ok(())
+    }
+
+    fn keys_of(&self, solved: Vec<usize>) -> Vec<(usize, Vec<u8>)> {
+        solved
+            .into_iter()
+            .filter_map(|t| Some((t, self.targets.key(t)?)))
+            .collect()
+    }
+
+    /// The pubkeys being solved and the keys found so far
+    pub fn targets(&self) -> &TargetSet {
+        &self.targets
     }
 
     /// Get total operations performed
@@ -403,8 +900,313 @@ impl KangarooSolver {
         self.total_ops
     }
 
-    fn read_dp_count(&self) -> Result<u32> {
-        let slice = self.buffers.staging_buffer.slice(0..4);
+    /// Seed the herd was placed with
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Kangaroos restarted because they cycled or stopped finding DPs
+    pub fn reseeds(&self) -> u64 {
+        self.reseeds
+    }
+
+    /// Kangaroos restarted because they merged with another of their herd
+    pub fn merges(&self) -> u64 {
+        self.merges
+    }
+
+    /// DP stores that found the DP buffer full and were deferred to a later dispatch
+    pub fn dp_overflows(&self) -> u64 {
+        self.dp_overflows
+    }
+
+    /// Expected work of this solver's herd on its range
+    pub fn search_model(&self) -> SearchModel {
+        self.model
+    }
+
+    /// Progress against the expected work, with the DP counts of the (possibly shared) table
+    pub fn stats(&self) -> SearchStats {
+        SearchStats::new(
+            &self.model,
+            self.total_ops,
+            self.rate.rate(),
+            self.dp_table.count_by_type(),
+        )
+    }
+
+    /// Restart a kangaroo at a fresh random offset in the same herd (or at a
+    /// random point of its set under Gaudry–Schost).
+    ///
+    /// Wilds of an already solved target move over to an unsolved one.
+    fn reseed(&mut self, kangaroo_id: u32, ktype: u32, target: u32) {
+        if kangaroo_id >= self.num_kangaroos {
+            return;
+        }
+        let target = if ktype == KTYPE_TAME {
+            target
+        } else {
+            self.unsolved_target(target)
+        };
+        let fresh = match self.gaudry_schost {
+            Some(sets) => sets.restart(&self.layout, ktype, target),
+            None => self
+                .layout
+                .spawn(ktype, target, self.layout.random_offset()),
+        };
+        let k_size = std::mem::size_of::<GpuKangaroo>() as u64;
+        self.ctx.queue.write_buffer(
+            &self.buffers.kangaroos_buffer,
+            kangaroo_id as u64 * k_size,
+            bytemuck::bytes_of(&fresh),
+        );
+    }
+
+    /// `target` while it is unsolved, otherwise a random unsolved one
+    fn unsolved_target(&self, target: u32) -> u32 {
+        if self.targets.key(target as usize).is_none() {
+            return target;
+        }
+        let unsolved: Vec<u32> = (0..self.targets.len() as u32)
+            .filter(|&t| self.targets.key(t as usize).is_none())
+            .collect();
+        if unsolved.is_empty() {
+            target
+        } else {
+            unsolved[rand::random::<usize>() % unsolved.len()]
+        }
+    }
+
+    /// Replace the first `count` entries of a slot's restart pool with fresh
+    /// walks, alternately tame and wild so the herds stay balanced
+    fn refill_restart_pool(&self, slot: usize, count: u32) {
+        let Some(sets) = self.gaudry_schost else {
+            return;
+        };
+        let targets: Vec<u32> = (0..count)
+            .map(|_| self.unsolved_target(rand::random::<u32>() % self.targets.len() as u32))
+            .collect();
+        let layout = &self.layout;
+        let fresh: Vec<GpuKangaroo> = targets
+            .into_par_iter()
+            .enumerate()
+            .map(|(i, target)| {
+                let ktype = if i % 2 == 0 { KTYPE_TAME } else { KTYPE_WILD };
+                sets.restart(layout, ktype, target)
+            })
+            .collect();
+        self.buffers.write_restart_pool(&self.ctx, slot, &fresh);
+    }
+
+    /// Respawn the wild kangaroos of solved targets for the unsolved ones
+    fn retarget_wilds(&mut self) -> Result<()> {
+        let herd = self.read_kangaroos()?;
+        for (kangaroo_id, k) in herd.iter().enumerate() {
+            if k.ktype != 0 && self.targets.key(k.target as usize).is_some() {
+                self.reseed(kangaroo_id as u32, k.ktype, k.target);
+            }
+        }
+        Ok(())
+    }
+
+    /// Search parameters for a work file fed by this solver
+    pub fn work_header(&self) -> WorkHeader {
+        WorkHeader {
+            pubkey: self.pubkey,
+            range: self.range,
+            dp_bits: self.dp_bits,
+            negation: self.negation,
+            herds: self.herds,
+            gaudry_schost: self.gaudry_schost,
+        }
+    }
+
+    /// Upload the search config (DP mask, current `steps_per_call`, ...)
+    fn write_config(&self) {
+        let dp_mask = create_dp_mask(self.dp_bits);
+        let config = GpuConfig {
+            dp_mask_lo: [dp_mask[0], dp_mask[1], dp_mask[2], dp_mask[3]],
+            dp_mask_hi: [dp_mask[4], dp_mask[5], dp_mask[6], dp_mask[7]],
+            num_kangaroos: self.num_kangaroos,
+            steps_per_call: self.steps_per_call,
+            jump_table_size: self.jumps.size,
+            flags: self.config_flags(),
+            stuck_steps: self.stuck_steps,
+            _padding: [0; 3],
+        };
+        self.ctx
+            .queue
+            .write_buffer(&self.buffers.config_buffer, 0, bytemuck::bytes_of(&config));
+    }
+
+    fn config_flags(&self) -> u32 {
+        config_flags(self.negation, self.gaudry_schost.is_some())
+    }
+
+    /// Append every DP found from now on to a work file
+    pub fn set_work_file(&mut self, writer: WorkFileWriter) {
+        self.work_file = Some(writer);
+    }
+
+    /// The DP table this solver checks collisions in
+    pub fn shared_dp_table(&self) -> Arc<DPTable> {
+        self.dp_table.clone()
+    }
+
+    /// Check collisions in another solver's table (same search, e.g. another GPU).
+    /// Call before the first `step`.
+    pub fn set_dp_table(&mut self, table: Arc<DPTable>) {
+        self.ingest = DpIngest::spawn(table.clone());
+        self.dp_table = table;
+    }
+
+    /// Forward DPs to a distributed server instead of checking them locally
+    pub fn set_server(&mut self, client: DpClient) {
+        self.server = Some(client);
+    }
+
+    /// Resume a run from a checkpoint (herd, DP table and op counter are restored)
+    pub fn from_checkpoint(ctx: GpuContext, checkpoint: &Checkpoint) -> Result<Self> {
+        let mut solver = Self::new_internal(
+            ctx,
+            vec![checkpoint.pubkey],
+            checkpoint.range,
+            checkpoint.dp_bits,
+            checkpoint.num_kangaroos(),
+            SolverOptions {
+                seed: checkpoint.seed,
+                negation: checkpoint.negation,
+                herds: checkpoint.herds,
+                gaudry_schost: checkpoint.gaudry_schost,
+                jumps: checkpoint.jumps,
+                ..SolverOptions::default()
+            },
+            true,
+        )?;
+
+        if solver.jump_table_hash != checkpoint.jump_table_hash {
+            bail!("Checkpoint jump table does not match this build; cannot resume");
+        }
+
+        // Calibration advanced the fresh herd, so the saved one goes in afterwards
+        upload_kangaroos(&solver.ctx, &solver.buffers, &checkpoint.kangaroos)?;
+        for dp in &checkpoint.dps {
+            // Stored DPs never collide with each other (a collision would have ended the run)
+            let _ = solver.dp_table.insert_and_check(*dp);
+        }
+        solver.total_ops = checkpoint.total_ops;
+
+        info!(
+            "Resumed: {} kangaroos, {} DPs, {} ops",
+            checkpoint.num_kangaroos(),
+            solver.dp_table.total_dps(),
+            solver.total_ops
+        );
+
+        Ok(solver)
+    }
+
+    /// Tame kangaroos only, walking `range` to fill a tame DP database (see `tame_db`)
+    pub fn precompute(
+        ctx: GpuContext,
+        range: KeyRange,
+        dp_bits: u32,
+        num_kangaroos: u32,
+        options: SolverOptions,
+    ) -> Result<Self> {
+        if options.negation
+            || options.herds != Herds::Vow
+            || options.gaudry_schost.is_some()
+            || options.tame_db.is_some()
+            || options.base != Point::GENERATOR
+        {
+            bail!("Tame precomputation walks plain tame kangaroos");
+        }
+        // Any pubkey will do: no wild kangaroo walks towards it
+        let solver = Self::new_internal(
+            ctx,
+            vec![Point::GENERATOR],
+            range,
+            dp_bits,
+            num_kangaroos,
+            options,
+            true,
+        )?;
+
+        // Calibration advanced the mixed herd, so the tames go in afterwards
+        let tames = solver
+            .layout
+            .single_herd(KTYPE_TAME, num_kangaroos, solver.seed);
+        upload_kangaroos(&solver.ctx, &solver.buffers, &tames)?;
+        Ok(solver)
+    }
+
+    /// The tame DPs found so far, as a database for wild-only searches
+    pub fn tame_db(&mut self) -> Result<TameDb> {
+        self.settle()?;
+        Ok(TameDb {
+            range: self.range,
+            dp_bits: self.dp_bits,
+            jumps: self.jumps,
+            jump_table_hash: self.jump_table_hash,
+            total_ops: self.total_ops,
+            dps: self
+                .dp_table
+                .export()
+                .into_iter()
+                .filter(|dp| dp.ktype == KTYPE_TAME)
+                .collect(),
+        })
+    }
+
+    /// Snapshot the current run (reads the herd back from the GPU)
+    pub fn checkpoint(&mut self) -> Result<Checkpoint> {
+        if self.targets.len() > 1 {
+            bail!("Checkpoints of multi-target runs are not supported");
+        }
+        if self.tame_db {
+            bail!("Checkpoints of runs against precomputed tames are not supported");
+        }
+        if self.layout.base() != Point::GENERATOR {
+            bail!("Checkpoints of searches on another base point are not supported");
+        }
+        self.settle()?;
+        Ok(Checkpoint {
+            pubkey: self.pubkey,
+            range: self.range,
+            dp_bits: self.dp_bits,
+            jumps: self.jumps,
+            jump_table_hash: self.jump_table_hash,
+            total_ops: self.total_ops,
+            negation: self.negation,
+            herds: self.herds,
+            gaudry_schost: self.gaudry_schost,
+            seed: self.seed,
+            kangaroos: self.read_kangaroos()?,
+            dps: self.dp_table.export(),
+        })
+    }
+
+    fn read_kangaroos(&self) -> Result<Vec<GpuKangaroo>> {
+        let k_size = std::mem::size_of::<GpuKangaroo>();
+        let copy_size = (self.num_kangaroos as usize * k_size) as u64;
+
+        let mut encoder = self
+            .ctx
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("Kangaroo Readback"),
+            });
+        encoder.copy_buffer_to_buffer(
+            &self.buffers.kangaroos_buffer,
+            0,
+            &self.buffers.kangaroo_staging,
+            0,
+            copy_size,
+        );
+        self.ctx.queue.submit(Some(encoder.finish()));
+
+        let slice = self.buffers.kangaroo_staging.slice(0..copy_size);
         let (tx, rx) = std::sync::mpsc::channel();
         slice.map_async(wgpu::MapMode::Read, move |result| {
             tx.send(result).unwrap();
@@ -417,18 +1219,39 @@ impl KangarooSolver {
         rx.recv()??;
 
         let data = slice.get_mapped_range();
-        let count = u32::from_le_bytes([data[0], data[1], data[2], data[3]]);
+        let kangaroos: Vec<GpuKangaroo> = data
+            .chunks_exact(k_size)
+            .map(|chunk| *bytemuck::from_bytes::<GpuKangaroo>(chunk))
+            .collect();
+
         drop(data);
-        self.buffers.staging_buffer.unmap();
+        self.buffers.kangaroo_staging.unmap();
 
-        Ok(count)
+        Ok(kangaroos)
     }
 
-    fn read_dps(&self, count: u32) -> Result<Vec<GpuDistinguishedPoint>> {
+    /// Read `count` DPs from index `first` of a slot's DP buffer (blocking)
+    fn read_dps(&self, slot: usize, first: u32, count: u32) -> Result<Vec<GpuDistinguishedPoint>> {
+        let buffers = &self.buffers.slots[slot];
         let dp_size = std::mem::size_of::<GpuDistinguishedPoint>();
-        let total_size = 4 + (count as usize * dp_size);
+        let total_size = (count as usize * dp_size) as u64;
+
+        let mut encoder = self
+            .ctx
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("DP Readback"),
+            });
+        encoder.copy_buffer_to_buffer(
+            &buffers.dp_buffer,
+            (first as usize * dp_size) as u64,
+            &buffers.staging_buffer,
+            0,
+            total_size,
+        );
+        self.ctx.queue.submit(Some(encoder.finish()));
 
-        let slice = self.buffers.staging_buffer.slice(0..total_size as u64);
+        let slice = buffers.staging_buffer.slice(0..total_size);
         let (tx, rx) = std::sync::mpsc::channel();
         slice.map_async(wgpu::MapMode::Read, move |result| {
             tx.send(result).unwrap();
@@ -441,28 +1264,17 @@ impl KangarooSolver {
         rx.recv()??;
 
         let data = slice.get_mapped_range();
-
-        // Skip first 4 bytes (count), read DPs
-        let dp_bytes = &data[4..];
-        let dps: Vec<GpuDistinguishedPoint> = dp_bytes
+        let dps: Vec<GpuDistinguishedPoint> = data
             .chunks_exact(dp_size)
-            .take(count as usize)
             .map(|chunk| *bytemuck::from_bytes::<GpuDistinguishedPoint>(chunk))
             .collect();
 
         drop(data);
-        self.buffers.staging_buffer.unmap();
+        buffers.staging_buffer.unmap();
 
         Ok(dps)
     }
 
-    fn reset_dp_count(&self) -> Result<()> {
-        self.ctx
-            .queue
-            .write_buffer(&self.buffers.dp_count_buffer, 0, &[0u8; 4]);
-        Ok(())
-    }
-
     /// Calibrate steps_per_call by measuring actual GPU dispatch times
     fn calibrate(&mut self, dp_bits: u32, verbose: bool) {
         let candidates = [16u32, 32, 64, 128, 256, 512];
@@ -487,12 +1299,16 @@ impl KangarooSolver {
 
             // Update config buffer with new steps_per_call
             let config = GpuConfig {
-                dp_mask_lo: [0; 4], // Not used in timing test
-                dp_mask_hi: [0; 4],
+                // Practically no DPs, so none fill the buffer and halt kangaroos
+                dp_mask_lo: [u32::MAX; 4],
+                dp_mask_hi: [u32::MAX; 4],
                 num_kangaroos: self.num_kangaroos,
                 steps_per_call: steps,
-                jump_table_size: 256,
-                _padding: 0,
+                jump_table_size: self.jumps.size,
+                flags: self.config_flags(),
+                // Calibration walks are not real search; leave them alone
+                stuck_steps: 0,
+                _padding: [0; 3],
             };
             self.ctx.queue.write_buffer(
                 &self.buffers.config_buffer,
@@ -543,7 +1359,8 @@ impl KangarooSolver {
                 timestamp_writes: None,
             });
             pass.set_pipeline(&self.pipeline.pipeline);
-            pass.set_bind_group(0, &self.buffers.bind_group, &[]);
+            // Calibration DPs land in slot 0; its next dispatch clears the count
+            pass.set_bind_group(0, &self.buffers.slots[0].bind_group, &[]);
             let workgroups = self.num_kangaroos.div_ceil(64);
             pass.dispatch_workgroups(workgroups, 1, 1);
         }
@@ -556,6 +1373,31 @@ impl KangarooSolver {
     }
 }
 
+/// `CONFIG_*` bits of a search
+fn config_flags(negation: bool, restart_on_dp: bool) -> u32 {
+    let mut flags = 0;
+    if negation {
+        flags |= CONFIG_NEGATION_MAP;
+    }
+    if restart_on_dp {
+        flags |= CONFIG_RESTART_ON_DP;
+    }
+    flags
+}
+
+/// DP bits when none are given: about √(N/m) steps between DPs for a
+/// `range_bits`-bit range walked by `kangaroos` kangaroos in total
+pub fn auto_dp_bits(range_bits: u32, kangaroos: u64) -> u32 {
+    (range_bits / 2)
+        .saturating_sub((kangaroos as f64).log2() as u32 / 2)
+        .clamp(8, 40)
+}
+
+/// Steps without a DP after which the kernel parks a kangaroo
+pub(crate) fn stuck_steps(dp_bits: u32) -> u32 {
+    (STUCK_DP_SPACINGS << dp_bits.min(64)).min(u32::MAX as u128) as u32
+}
+
 fn upload_kangaroos(
     ctx: &GpuContext,
     buffers: &GpuBuffers,
@@ -571,7 +1413,15 @@ fn upload_kangaroos(
 
 #[cfg(test)]
 mod tests {
-    use super::{KangarooSolver, MAX_DISTINGUISHED_POINTS};
+    use super::{
+        auto_dp_bits, stuck_steps, upload_kangaroos, KangarooSolver, MAX_DISTINGUISHED_POINTS,
+    };
+    use crate::convert::affine_to_gpu;
+    use crate::crypto::parse_pubkey;
+    use crate::gpu_crypto::{GpuBackend, GpuContext};
+    use k256::elliptic_curve::bigint::Encoding;
+    use k256::U256 as K256U256;
+    use std::collections::HashSet;
 
     #[test]
     fn caps_steps_when_dp_buffer_would_overflow() {
@@ -588,4 +1438,112 @@ mod tests {
             KangarooSolver::select_steps_per_call(4_096, 4_096, 16, MAX_DISTINGUISHED_POINTS);
         assert_eq!(steps, 4_096);
     }
+
+    #[test]
+    fn auto_dp_bits_spaces_dps_by_sqrt_of_range_per_kangaroo() {
+        assert_eq!(auto_dp_bits(80, 1 << 20), 30);
+        assert_eq!(auto_dp_bits(80, 3 << 20), 30);
+        assert_eq!(auto_dp_bits(20, 1 << 16), 8);
+        assert_eq!(auto_dp_bits(135, 1 << 10), 40);
+    }
+
+    #[test]
+    fn stuck_threshold_is_twenty_dp_spacings() {
+        assert_eq!(stuck_steps(8), 20 * 256);
+        assert_eq!(stuck_steps(20), 20 << 20);
+        // Sparse DPs saturate rather than wrap
+        assert_eq!(stuck_steps(30), u32::MAX);
+        assert_eq!(stuck_steps(200), u32::MAX);
+    }
+
+    // 2^33 steps per DP: a few minutes on a discrete GPU, far too long for lavapipe.
+    // Run with: cargo test --lib dp_density_above_32_bits_on_gpu -- --ignored
+    #[test]
+    #[ignore]
+    fn dp_density_above_32_bits_on_gpu() {
+        let Ok(ctx) = pollster::block_on(GpuContext::new(0, GpuBackend::Auto)) else {
+            eprintln!("No GPU (or lavapipe) available, skipping");
+            return;
+        };
+        let pubkey =
+            parse_pubkey("033c4a45cbd643ff97d77f41ea37e843648d50fd894b864b0d52febc62f6454f7c")
+                .unwrap();
+        let mut start = [0u8; 32];
+        start[16] = 1;
+        // A wide range, so herds do not merge and every DP is a new entry
+        let mut solver = KangarooSolver::new(ctx, pubkey, start, 128, 33, 1 << 16).unwrap();
+        while solver.total_operations() < 64 << 33 {
+            solver.step().unwrap();
+        }
+        solver.settle().unwrap();
+
+        // DPs are Poisson with mean ops·2^-33; a kernel that stopped at the low
+        // limb would find twice as many, 8 standard deviations out
+        let expected = solver.total_operations() as f64 / (1u64 << 33) as f64;
+        let found = solver.dp_table.total_dps() as f64;
+        assert_eq!(solver.dp_overflows(), 0);
+        assert!(
+            (found - expected).abs() < 4.0 * expected.sqrt(),
+            "{} DPs where {:.1} are expected at 2^-33",
+            found,
+            expected
+        );
+    }
+
+    #[test]
+    fn dp_mask_above_32_bits_on_gpu() {
+        let Ok(ctx) = pollster::block_on(GpuContext::new(0, GpuBackend::Auto)) else {
+            eprintln!("No GPU (or lavapipe) available, skipping");
+            return;
+        };
+        let pubkey =
+            parse_pubkey("033c4a45cbd643ff97d77f41ea37e843648d50fd894b864b0d52febc62f6454f7c")
+                .unwrap();
+        let mut start = [0u8; 32];
+        start[8] = 1;
+        let mut solver = KangarooSolver::new(ctx, pubkey, start, 64, 40, 1024).unwrap();
+
+        // A walk never meets a 2^-40 DP in a test, so kangaroos are placed on
+        // chosen points (the kernel tests a point before jumping from it): x with
+        // its low 40 bits zero must be stored, x with only the low 32 zero must
+        // not (a kernel that read the mask's low limb alone would store both)
+        let mut kangaroos = solver.read_kangaroos().unwrap();
+        let mut planted = Vec::new();
+        for t in 1u64.. {
+            if planted.len() == 16 {
+                break;
+            }
+            let zero_bits = if planted.len() % 2 == 0 { 40 } else { 32 };
+            if zero_bits == 32 && t & 0xff == 0 {
+                continue;
+            }
+            let x = K256U256::from_u64(t).shl_vartime(zero_bits);
+            let Ok(point) = parse_pubkey(&format!("02{}", hex::encode(x.to_be_bytes()))) else {
+                continue;
+            };
+            let point = affine_to_gpu(&point.to_affine());
+            let k = &mut kangaroos[planted.len()];
+            k.x = point.x;
+            k.y = point.y;
+            planted.push((point.x, zero_bits == 40));
+        }
+        upload_kangaroos(&solver.ctx, &solver.buffers, &kangaroos).unwrap();
+
+        solver.steps_per_call = 1;
+        solver.write_config();
+        solver.step().unwrap();
+        solver.settle().unwrap();
+
+        let stored: HashSet<[u32; 8]> = solver.dp_table.export().iter().map(|dp| dp.x).collect();
+        for x in &stored {
+            assert!(
+                x[0] == 0 && x[1] & 0xff == 0,
+                "{:08x?} is not a 40-bit DP",
+                x
+            );
+        }
+        for (x, is_dp) in &planted {
+            assert_eq!(stored.contains(x), *is_dp, "planted {:08x?}", x);
+        }
+    }
 }
@@ -0,0 +1,312 @@
+//! Expected work and live statistics of a search
+//!
+//! m kangaroos split into tame and wild herds with the optimal mean jump meet
+//! after about 2·√N steps in total (√2·√N with the negation map, 1.82·√N and
+//! 1.71·√N with three and four kangaroos, and a factor set by the tame and wild
+//! sets for Gaudry–Schost; wilds against `T` precomputed tame DPs need about
+//! `N / (T·2^dp)`). The walk that
+//! brings the collision to light costs each kangaroo another 2^dp steps on
+//! average before it lands on a DP, so the expected operation count is
+//! `2·√N + m·2^dp`. The collision time is modelled as Rayleigh distributed with
+//! that 2·√N mean, which gives the success probability after a number of
+//! operations.
+
+use crate::cpu::gaudry_schost::SetOverlap;
+use crate::cpu::init::Herds;
+use crate::range::KeyRange;
+use k256::elliptic_curve::bigint::Encoding;
+use std::fmt;
+use std::time::{Duration, Instant};
+
+/// Time constant of the smoothed operation rate
+const RATE_WINDOW_SECS: f64 = 30.0;
+/// Success probability the ETA aims for once the expected work is done
+const LATE_ETA_PROBABILITY: f64 = 0.99;
+
+/// Expected cost of one search
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SearchModel {
+    /// Steps until a tame and a wild kangaroo meet, on average
+    collision_ops: f64,
+    /// Steps from the meeting until it shows up as a DP, over all kangaroos
+    overhead_ops: f64,
+    dp_bits: u32,
+}
+
+impl SearchModel {
+    pub fn new(
+        range: &KeyRange,
+        kangaroos: u64,
+        dp_bits: u32,
+        negation: bool,
+        herds: Herds,
+    ) -> Self {
+        let factor = if negation {
+            std::f64::consts::SQRT_2
+        } else {
+            herds.sqrt_factor()
+        };
+        Self::with_factor(range, kangaroos, dp_bits, factor)
+    }
+
+    /// Gaudry–Schost with the given tame and wild sets
+    pub fn gaudry_schost(range: &KeyRange, kangaroos: u64, dp_bits: u32, sets: SetOverlap) -> Self {
+        Self::with_factor(range, kangaroos, dp_bits, sets.sqrt_factor())
+    }
+
+    /// Wild kangaroos only, against `tame_dps` stored tame DPs: each stands for a
+    /// path of about `2^dp` keys, so a wild step lands on one with probability
+    /// `T·2^dp / N`
+    pub fn tame_db(range: &KeyRange, kangaroos: u64, dp_bits: u32, tame_dps: u64) -> Self {
+        let covered = (tame_dps.max(1) as f64) * 2f64.powi(dp_bits as i32);
+        Self {
+            collision_ops: width(range) / covered,
+            ..Self::with_factor(range, kangaroos, dp_bits, 0.0)
+        }
+    }
+
+    /// Collision after `factor·√N` operations on average
+    fn with_factor(range: &KeyRange, kangaroos: u64, dp_bits: u32, factor: f64) -> Self {
+        Self {
+            collision_ops: factor * width(range).sqrt(),
+            overhead_ops: kangaroos as f64 * 2f64.powi(dp_bits as i32),
+            dp_bits,
+        }
+    }
+
+    /// Expected operations until the key is found
+    pub fn expected_ops(&self) -> f64 {
+        self.collision_ops + self.overhead_ops
+    }
+
+    /// Operations spent by the herd after the collision before a DP reveals it
+    pub fn overhead_ops(&self) -> f64 {
+        self.overhead_ops
+    }
+
+    /// Chance that the key has been found after `ops` operations
+    pub fn success_probability(&self, ops: u64) -> f64 {
+        let walked = (ops as f64 - self.overhead_ops).max(0.0) / self.collision_ops;
+        1.0 - (-std::f64::consts::FRAC_PI_4 * walked * walked).exp()
+    }
+
+    /// Operations after which the key has been found with probability `p`
+    pub fn ops_for_probability(&self, p: f64) -> f64 {
+        let p = p.clamp(0.0, 1.0 - f64::EPSILON);
+        let walked = (-(1.0 - p).ln() / std::f64::consts::FRAC_PI_4).sqrt();
+        self.overhead_ops + walked * self.collision_ops
+    }
+
+    /// DPs per operation: every point is distinguished with probability 2^-dp
+    pub fn dp_rate(&self) -> f64 {
+        2f64.powi(-(self.dp_bits as i32))
+    }
+}
+
+/// Number of keys in `range`
+fn width(range: &KeyRange) -> f64 {
+    range
+        .width()
+        .to_be_bytes()
+        .iter()
+        .fold(0.0, |acc, &byte| acc * 256.0 + f64::from(byte))
+}
+
+/// Exponentially smoothed operations per second
+#[derive(Clone, Debug, Default)]
+pub struct RateMeter {
+    rate: f64,
+    last: Option<(Instant, u64)>,
+}
+
+impl RateMeter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record the operation counter at `now` and return the smoothed rate
+    pub fn update(&mut self, ops: u64, now: Instant) -> f64 {
+        match self.last {
+            Some((at, before)) if now > at => {
+                let secs = now.duration_since(at).as_secs_f64();
+                let sample = ops.saturating_sub(before) as f64 / secs;
+                let weight = if self.rate == 0.0 {
+                    1.0
+                } else {
+                    1.0 - (-secs / RATE_WINDOW_SECS).exp()
+                };
+                self.rate += weight * (sample - self.rate);
+                self.last = Some((now, ops));
+            }
+            Some(_) => {}
+            None => self.last = Some((now, ops)),
+        }
+        self.rate
+    }
+
+    /// Current smoothed rate (0 until two samples were seen)
+    pub fn rate(&self) -> f64 {
+        self.rate
+    }
+}
+
+/// Snapshot of a running search, shown in the progress line
+#[derive(Clone, Debug, PartialEq)]
+pub struct SearchStats {
+    pub ops: u64,
+    /// Smoothed operations per second
+    pub ops_per_sec: f64,
+    pub expected_ops: f64,
+    /// Chance that the key has been found by now
+    pub success_probability: f64,
+    pub tame_dps: usize,
+    pub wild_dps: usize,
+    /// DPs stored per operation
+    pub dp_rate: f64,
+    /// DPs per operation the DP mask should give
+    pub expected_dp_rate: f64,
+    /// Time until the expected work is done, or until 99% success past it
+    pub eta: Option<Duration>,
+}
+
+impl SearchStats {
+    pub fn new(model: &SearchModel, ops: u64, ops_per_sec: f64, dps: (usize, usize)) -> Self {
+        let expected_ops = model.expected_ops();
+        let goal = if (ops as f64) < expected_ops {
+            expected_ops
+        } else {
+            model.ops_for_probability(LATE_ETA_PROBABILITY)
+        };
+        let remaining = goal - ops as f64;
+        let eta = (ops_per_sec > 0.0 && remaining > 0.0)
+            .then(|| Duration::try_from_secs_f64(remaining / ops_per_sec).ok())
+            .flatten();
+        Self {
+            ops,
+            ops_per_sec,
+            expected_ops,
+            success_probability: model.success_probability(ops),
+            tame_dps: dps.0,
+            wild_dps: dps.1,
+            dp_rate: if ops == 0 {
+                0.0
+            } else {
+                (dps.0 + dps.1) as f64 / ops as f64
+            },
+            expected_dp_rate: model.dp_rate(),
+            eta,
+        }
+    }
+}
+
+impl fmt::Display for SearchStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:.2}M/s | P {:.1}% | ETA {} | DPs {}t/{}w ({:.2}x)",
+            self.ops_per_sec / 1e6,
+            self.success_probability * 100.0,
+            self.eta.map_or_else(|| "-".to_string(), format_duration),
+            self.tame_dps,
+            self.wild_dps,
+            self.dp_rate / self.expected_dp_rate
+        )
+    }
+}
+
+/// `1d02h`, `3h04m`, `5m06s` or `7s`
+fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    let (days, hours, mins) = (secs / 86_400, secs / 3600 % 24, secs / 60 % 60);
+    if days > 0 {
+        format!("{}d{:02}h", days, hours)
+    } else if hours > 0 {
+        format!("{}h{:02}m", hours, mins)
+    } else if mins > 0 {
+        format!("{}m{:02}s", mins, secs % 60)
+    } else {
+        format!("{}s", secs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn model(bits: u32, kangaroos: u64, dp_bits: u32, negation: bool) -> SearchModel {
+        SearchModel::new(
+            &KeyRange::from_bits([0; 32], bits),
+            kangaroos,
+            dp_bits,
+            negation,
+            Herds::Vow,
+        )
+    }
+
+    #[test]
+    fn test_expected_ops() {
+        // 2 * 2^20 collision steps plus 2^10 kangaroos * 2^8
+        let m = model(40, 1 << 10, 8, false);
+        assert_eq!(m.expected_ops(), (2u64 << 20) as f64 + (1u64 << 18) as f64);
+        let negated = model(40, 1 << 10, 8, true);
+        assert!(
+            (negated.expected_ops() - (std::f64::consts::SQRT_2 * 1048576.0 + 262144.0)).abs()
+                < 1.0
+        );
+        assert_eq!(m.dp_rate(), 1.0 / 256.0);
+
+        // The full key space does not overflow
+        assert!(model(256, 1, 0, false).expected_ops() > 2f64.powi(128));
+
+        // 2^15 stored tames of 2^10 keys each leave 2^15 wild steps in 2^40
+        let range = KeyRange::from_bits([0; 32], 40);
+        let wild_only = SearchModel::tame_db(&range, 1 << 10, 10, 1 << 15);
+        assert_eq!(
+            wild_only.expected_ops(),
+            (1u64 << 15) as f64 + (1u64 << 20) as f64
+        );
+    }
+
+    #[test]
+    fn test_success_probability() {
+        let m = model(40, 1 << 10, 8, false);
+        assert_eq!(m.success_probability(0), 0.0);
+        assert_eq!(m.success_probability(m.overhead_ops() as u64), 0.0);
+
+        // The mean of a Rayleigh distribution sits at 1 - e^(-pi/4)
+        let at_mean = m.success_probability(m.expected_ops() as u64);
+        assert!((at_mean - 0.544).abs() < 0.001, "{}", at_mean);
+        assert!(m.success_probability(u64::MAX) > 0.999_999);
+
+        for p in [0.1, 0.5, 0.99] {
+            let ops = m.ops_for_probability(p);
+            assert!((m.success_probability(ops as u64) - p).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn test_rate_and_eta() {
+        let m = model(40, 1 << 10, 8, false);
+        let t0 = Instant::now();
+        let mut meter = RateMeter::new();
+        assert_eq!(meter.update(0, t0), 0.0);
+        assert_eq!(meter.update(1_000_000, t0 + Duration::from_secs(1)), 1e6);
+        // A slower sample moves the rate only part of the way
+        let rate = meter.update(1_500_000, t0 + Duration::from_secs(2));
+        assert!(rate > 5e5 && rate < 1e6, "{}", rate);
+
+        let stats = SearchStats::new(&m, 1 << 20, 1e6, (2048, 2048));
+        let remaining = m.expected_ops() - (1 << 20) as f64;
+        assert_eq!(stats.eta.unwrap().as_secs(), (remaining / 1e6) as u64);
+        assert_eq!(stats.dp_rate / stats.expected_dp_rate, 1.0);
+
+        // Past the expected work the ETA aims at 99%
+        let late = SearchStats::new(&m, m.expected_ops() as u64 + 1, 1e6, (0, 0));
+        let remaining = m.ops_for_probability(0.99) - m.expected_ops();
+        assert!((late.eta.unwrap().as_secs_f64() - remaining / 1e6).abs() < 1.0);
+        assert!(SearchStats::new(&m, 0, 0.0, (0, 0)).eta.is_none());
+
+        assert_eq!(format_duration(Duration::from_secs(93_784)), "1d02h");
+        assert_eq!(format_duration(Duration::from_secs(306)), "5m06s");
+    }
+}
@@ -0,0 +1,323 @@
+//! Precomputed tame DPs, reused across searches of the same width
+//!
+//! Bernstein and Lange's variant of the kangaroo method: tame walks over a
+//! range of width `W` are run once and their DPs stored. A later search of any
+//! range no wider than `W` shifts its pubkey into the stored range and runs wild
+//! kangaroos only; each wild walk that lands on a stored tame path gives the key.
+//! The more tame DPs the table holds, the fewer wild steps a search takes.
+//!
+//! Layout (all integers little-endian), like a checkpoint:
+//!
+//! ```text
+//! magic        8 bytes  "KANGTAME"
+//! version      u32
+//! payload_len  u64
+//! payload      payload_len bytes
+//! checksum     32 bytes  SHA-256(payload)
+//! ```
+
+use crate::checkpoint::{read_jump_spec, write_jump_spec, Reader};
+use crate::convert::{key_bytes_to_scalar, le_bytes_to_scalar, scalar_to_key_bytes};
+use crate::cpu::jumps::JumpTableSpec;
+use crate::crypto::Point;
+use crate::gpu::GpuDistinguishedPoint;
+use crate::range::KeyRange;
+use anyhow::{bail, Context, Result};
+use bitcoin::hashes::{sha256, Hash};
+use k256::elliptic_curve::ops::MulByGenerator;
+use k256::{ProjectivePoint, Scalar};
+use std::path::Path;
+
+const MAGIC: &[u8; 8] = b"KANGTAME";
+/// Bump whenever the payload layout changes
+pub const TAME_DB_VERSION: u32 = 1;
+
+/// Most tame DPs `precompute` collects unless asked for more
+const MAX_DEFAULT_DPS: u64 = 1 << 20;
+
+/// Tame DPs of one precomputation
+#[derive(Clone, Debug)]
+pub struct TameDb {
+    /// Range the tames walked, starting at key 0
+    pub range: KeyRange,
+    pub dp_bits: u32,
+    /// Searches using the table must walk with the same jumps
+    pub jumps: JumpTableSpec,
+    /// SHA-256 over the jump distances, guards against a different table
+    pub jump_table_hash: [u8; 32],
+    /// Operations spent on the precomputation
+    pub total_ops: u64,
+    pub dps: Vec<GpuDistinguishedPoint>,
+}
+
+impl TameDb {
+    /// Serialize to the versioned, checksummed binary format
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut payload = Vec::new();
+        payload.extend_from_slice(&self.range.start());
+        payload.extend_from_slice(&self.range.end());
+        payload.extend_from_slice(&self.dp_bits.to_le_bytes());
+        write_jump_spec(&mut payload, &self.jumps);
+        payload.extend_from_slice(&self.jump_table_hash);
+        payload.extend_from_slice(&self.total_ops.to_le_bytes());
+        payload.extend_from_slice(&(self.dps.len() as u64).to_le_bytes());
+        payload.extend_from_slice(bytemuck::cast_slice(&self.dps));
+
+        let checksum = sha256::Hash::hash(&payload);
+
+        let mut out = Vec::with_capacity(payload.len() + 52);
+        out.extend_from_slice(MAGIC);
+        out.extend_from_slice(&TAME_DB_VERSION.to_le_bytes());
+        out.extend_from_slice(&(payload.len() as u64).to_le_bytes());
+        out.extend_from_slice(&payload);
+        out.extend_from_slice(checksum.as_byte_array());
+        out
+    }
+
+    /// Parse and validate a tame DP database
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let mut header = Reader::new(bytes);
+        if header.take(8)? != MAGIC {
+            bail!("Not a tame DP database (bad magic)");
+        }
+        let version = header.u32()?;
+        if version != TAME_DB_VERSION {
+            bail!(
+                "Unsupported tame DP database version {} (expected {})",
+                version,
+                TAME_DB_VERSION
+            );
+        }
+        let payload_len = header.u64()? as usize;
+        let payload = header.take(payload_len)?;
+        let checksum = header.take(32)?;
+        if sha256::Hash::hash(payload).as_byte_array() != checksum {
+            bail!("Tame DP database checksum mismatch (file is corrupted)");
+        }
+
+        let mut r = Reader::new(payload);
+        let mut start = [0u8; 32];
+        start.copy_from_slice(r.take(32)?);
+        let mut end = [0u8; 32];
+        end.copy_from_slice(r.take(32)?);
+        let range = KeyRange::new(start, end).context("Invalid range in tame DP database")?;
+        let dp_bits = r.u32()?;
+        let jumps = read_jump_spec(&mut r)?;
+        let mut jump_table_hash = [0u8; 32];
+        jump_table_hash.copy_from_slice(r.take(32)?);
+        let total_ops = r.u64()?;
+        let num_dps = r.u64()? as usize;
+        let dps = r.pod_vec::<GpuDistinguishedPoint>(num_dps)?;
+
+        Ok(Self {
+            range,
+            dp_bits,
+            jumps,
+            jump_table_hash,
+            total_ops,
+            dps,
+        })
+    }
+
+    /// Write atomically (temp file + rename), like a checkpoint
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let mut tmp = path.as_os_str().to_owned();
+        tmp.push(".tmp");
+        let tmp = std::path::PathBuf::from(tmp);
+
+        std::fs::write(&tmp, self.to_bytes())
+            .with_context(|| format!("Failed to write tame DP database {}", tmp.display()))?;
+        std::fs::rename(&tmp, path)
+            .with_context(|| format!("Failed to move tame DP database to {}", path.display()))?;
+        Ok(())
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let bytes = std::fs::read(path)
+            .with_context(|| format!("Failed to read tame DP database {}", path.display()))?;
+        Self::from_bytes(&bytes)
+            .with_context(|| format!("Invalid tame DP database {}", path.display()))
+    }
+
+    /// Whether a search of `range` can use these tames: it must be no wider
+    pub fn covers(&self, range: &KeyRange) -> Result<()> {
+        if range.width() > self.range.width() {
+            bail!(
+                "{} is wider than the {}-bit range of the tame DP database",
+                range,
+                self.range.bits()
+            );
+        }
+        Ok(())
+    }
+
+    /// `pubkey` moved from `range` into the stored range: `P - (start - db start)·G`
+    pub fn shift(&self, pubkey: &Point, range: &KeyRange) -> Point {
+        *pubkey - ProjectivePoint::mul_by_generator(&self.offset(range))
+    }
+
+    /// Key of the pubkey `shift` was given, from the key of the shifted one
+    pub fn unshift(&self, key: &[u8], range: &KeyRange) -> Vec<u8> {
+        scalar_to_key_bytes(&(key_bytes_to_scalar(key) + self.offset(range)))
+    }
+
+    fn offset(&self, range: &KeyRange) -> Scalar {
+        le_bytes_to_scalar(&range.start()) - le_bytes_to_scalar(&self.range.start())
+    }
+}
+
+/// Range of a `width_bits` precomputation
+pub fn precompute_range(width_bits: u32) -> KeyRange {
+    KeyRange::from_bits([0; 32], width_bits)
+}
+
+/// Tame DPs worth collecting for `width_bits`: `2^((W - d) / 2)`, so the stored
+/// paths cover about `2^((W + d) / 2)` keys and a search takes `2^((W - d) / 2)`
+/// wild steps, capped at a million DPs
+pub fn default_dps(width_bits: u32, dp_bits: u32) -> u64 {
+    let exponent = width_bits.saturating_sub(dp_bits) / 2;
+    if exponent >= 63 {
+        return MAX_DEFAULT_DPS;
+    }
+    (1u64 << exponent).min(MAX_DEFAULT_DPS)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cpu::init::HerdLayout;
+    use crate::cpu::{CpuWalker, DPTable, Insertion};
+    use crate::gpu::{DP_KIND_STUCK, KTYPE_TAME, KTYPE_WILD};
+    use k256::elliptic_curve::bigint::Encoding;
+    use k256::U256 as K256U256;
+
+    fn key(value: u64) -> [u8; 32] {
+        K256U256::from_u64(value).to_le_bytes()
+    }
+
+    fn sample() -> TameDb {
+        let dp = GpuDistinguishedPoint {
+            x: [0xdead_beef; 8],
+            dist: [42, 0, 0, 0, 0, 0, 0, 0],
+            ktype: KTYPE_TAME,
+            kangaroo_id: 7,
+            kind: 0,
+            target: 0,
+            _padding: [0; 4],
+        };
+        TameDb {
+            range: precompute_range(40),
+            dp_bits: 10,
+            jumps: JumpTableSpec::default(),
+            jump_table_hash: [0xab; 32],
+            total_ops: 1 << 30,
+            dps: vec![dp; 3],
+        }
+    }
+
+    #[test]
+    fn test_roundtrip_and_corruption() {
+        let db = sample();
+        let restored = TameDb::from_bytes(&db.to_bytes()).unwrap();
+        assert_eq!(restored.range, db.range);
+        assert_eq!(restored.dp_bits, 10);
+        assert_eq!(restored.jumps, db.jumps);
+        assert_eq!(restored.jump_table_hash, db.jump_table_hash);
+        assert_eq!(restored.total_ops, 1 << 30);
+        assert_eq!(restored.dps.len(), 3);
+        assert_eq!(restored.dps[2].kangaroo_id, 7);
+
+        let mut bytes = db.to_bytes();
+        let mid = bytes.len() / 2;
+        bytes[mid] ^= 0x01;
+        assert!(TameDb::from_bytes(&bytes).is_err());
+        let bytes = db.to_bytes();
+        assert!(TameDb::from_bytes(&bytes[..bytes.len() - 10]).is_err());
+        let mut bytes = db.to_bytes();
+        bytes[..8].copy_from_slice(b"KANGCKPT");
+        assert!(TameDb::from_bytes(&bytes).is_err());
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("tames.db");
+        db.save(&path).unwrap();
+        assert_eq!(TameDb::load(&path).unwrap().dps.len(), 3);
+    }
+
+    #[test]
+    fn test_shift_and_cover() {
+        let db = sample();
+        let range = KeyRange::from_bits(key(0x1234_0000_0000), 32);
+        db.covers(&range).unwrap();
+        assert!(db.covers(&KeyRange::from_bits(key(0), 41)).is_err());
+
+        let k = 0x1234_0000_0000u64 + 0xabcd_ef01;
+        let pubkey = ProjectivePoint::mul_by_generator(&Scalar::from(k));
+        let shifted = db.shift(&pubkey, &range);
+        assert_eq!(
+            shifted,
+            ProjectivePoint::mul_by_generator(&Scalar::from(0xabcd_ef01u64))
+        );
+        let found = db.unshift(&[0xab, 0xcd, 0xef, 0x01], &range);
+        assert_eq!(key_bytes_to_scalar(&found), Scalar::from(k));
+
+        assert_eq!(default_dps(40, 10), 1 << 15);
+        assert_eq!(default_dps(100, 10), MAX_DEFAULT_DPS);
+        assert_eq!(default_dps(256, 0), MAX_DEFAULT_DPS);
+    }
+
+    #[test]
+    fn test_wild_only_search_with_stored_tames() {
+        // Tames over 24 bits, then a wild-only search of puzzle 20 (key 0xd2c55
+        // in [0x80000, 0xfffff]) shifted into them
+        let db_range = precompute_range(24);
+        let dp_bits = 4;
+        let jumps = JumpTableSpec::default();
+        let walker = CpuWalker::new(&db_range, &jumps, dp_bits, false, 0).unwrap();
+        let generator = ProjectivePoint::GENERATOR;
+        let tames = HerdLayout::new(vec![generator], db_range, false);
+        let table = DPTable::new(db_range.start());
+        let mut herd = tames.single_herd(KTYPE_TAME, 256, 1);
+        while table.count_by_type().0 < default_dps(24, dp_bits) as usize {
+            for dp in walker.walk(&mut herd, 32, 0) {
+                if dp.kind != DP_KIND_STUCK {
+                    table.insert(dp);
+                }
+            }
+        }
+        let db = TameDb {
+            range: db_range,
+            dp_bits,
+            jumps,
+            jump_table_hash: [0; 32],
+            total_ops: 0,
+            dps: table.export(),
+        };
+
+        let pubkey = crate::crypto::parse_pubkey(
+            "033c4a45cbd643ff97d77f41ea37e843648d50fd894b864b0d52febc62f6454f7c",
+        )
+        .unwrap();
+        let range = KeyRange::from_bits(key(0x80000), 19);
+        db.covers(&range).unwrap();
+        let shifted = db.shift(&pubkey, &range);
+        let wilds = HerdLayout::new(vec![shifted], db.range, false);
+        let table = DPTable::with_targets(db.range.start(), vec![shifted], false);
+        for dp in &db.dps {
+            table.insert(*dp);
+        }
+        let mut herd = wilds.single_herd(KTYPE_WILD, 64, 2);
+        assert!(herd.iter().all(|k| k.ktype == KTYPE_WILD));
+        let key = (0..2000).find_map(|_| {
+            walker.walk(&mut herd, 32, 0).into_iter().find_map(|dp| {
+                if dp.kind == DP_KIND_STUCK {
+                    return None;
+                }
+                match table.insert(dp) {
+                    Insertion::Key { key, .. } => Some(key),
+                    _ => None,
+                }
+            })
+        });
+        assert_eq!(db.unshift(&key.unwrap(), &range), vec![0x0d, 0x2c, 0x55]);
+    }
+}
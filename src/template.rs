@@ -0,0 +1,284 @@
+//! Keys with known hex digits
+//!
+//! A template such as `0x1f3a????c0` fixes some digits of the key and leaves
+//! the `?` ones open. With `m` known digits below the longest run of `?`, every
+//! matching key is `offset + 16^m·x` for `x` in an interval, so the search runs
+//! on `P' = (P - offset·Q)·16^-m = x·Q` and maps `x` back. Any other `?` digits
+//! are enumerated, each value adding one offset (one more target `P'`).
+
+use crate::convert::{key_bytes_to_scalar, scalar_to_key_bytes};
+use crate::crypto::{mul_base, Point};
+use crate::range::KeyRange;
+use anyhow::{anyhow, bail, Result};
+use k256::elliptic_curve::bigint::Encoding;
+use k256::elliptic_curve::ops::Reduce;
+use k256::{Scalar, U256 as K256U256};
+use std::fmt;
+
+/// Most targets the `?` digits outside the searched run may expand into
+pub const MAX_TEMPLATE_TARGETS: usize = 1 << 12;
+
+/// `--key-template` value: hex digits, `None` for each `?`, most significant first
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct KeyTemplate {
+    digits: Vec<Option<u8>>,
+}
+
+impl KeyTemplate {
+    pub fn parse(s: &str) -> Result<Self> {
+        let s = s.trim();
+        let hex = s
+            .strip_prefix("0x")
+            .or_else(|| s.strip_prefix("0X"))
+            .unwrap_or(s);
+        if hex.is_empty() || hex.len() > 64 {
+            bail!("Key template '{}' needs 1 to 64 hex digits", s);
+        }
+        let digits =
+            hex.chars()
+                .map(|c| match c {
+                    '?' => Ok(None),
+                    c => c.to_digit(16).map(|d| Some(d as u8)).ok_or_else(|| {
+                        anyhow!("Invalid character '{}' in key template '{}'", c, s)
+                    }),
+                })
+                .collect::<Result<Vec<_>>>()?;
+        if digits.iter().all(Option::is_some) {
+            bail!("Key template '{}' has no ? digit", s);
+        }
+        Ok(Self { digits })
+    }
+
+    /// Number of `?` digits
+    pub fn unknown_digits(&self) -> usize {
+        self.digits.iter().filter(|d| d.is_none()).count()
+    }
+
+    /// Runs of `?` as `(digits below the run, run length)`, lowest run first
+    pub fn blocks(&self) -> Vec<(usize, usize)> {
+        let mut blocks = Vec::new();
+        let mut run: Option<(usize, usize)> = None;
+        for (position, digit) in self.digits.iter().rev().enumerate() {
+            match (digit, run.as_mut()) {
+                (None, Some((_, len))) => *len += 1,
+                (None, None) => run = Some((position, 1)),
+                (Some(_), _) => blocks.extend(run.take()),
+            }
+        }
+        blocks.extend(run);
+        blocks
+    }
+
+    /// Split into the interval search over the longest run of `?` (the lowest
+    /// of equal runs) and the offsets of the other `?` digits' values
+    pub fn plan(&self) -> Result<TemplateSearch> {
+        let blocks = self.blocks();
+        let (shift, len) = blocks
+            .iter()
+            .copied()
+            .max_by_key(|&(shift, len)| (len, std::cmp::Reverse(shift)))
+            .expect("a template has at least one ? digit");
+
+        let outer: Vec<usize> = blocks
+            .iter()
+            .filter(|&&block| block != (shift, len))
+            .flat_map(|&(below, run)| below..below + run)
+            .collect();
+        if 4 * outer.len() > MAX_TEMPLATE_TARGETS.trailing_zeros() as usize {
+            bail!(
+                "Key template has {} runs of ?: the {} digits outside the longest run would take 16^{} targets (at most {}); fix more digits or split the template",
+                blocks.len(),
+                outer.len(),
+                outer.len(),
+                MAX_TEMPLATE_TARGETS
+            );
+        }
+
+        // The template with every ? set to 0, and the known digits below the run
+        let mut known = K256U256::ZERO;
+        for digit in &self.digits {
+            known = known
+                .shl_vartime(4)
+                .wrapping_add(&K256U256::from_u8(digit.unwrap_or(0)));
+        }
+        let low = if shift == 0 {
+            K256U256::ZERO
+        } else {
+            known
+                .shl_vartime(256 - 4 * shift)
+                .shr_vartime(256 - 4 * shift)
+        };
+
+        let first = known.shr_vartime(4 * shift);
+        let span = if len >= 64 {
+            K256U256::MAX
+        } else {
+            K256U256::ONE
+                .shl_vartime(4 * len)
+                .wrapping_sub(&K256U256::ONE)
+        };
+        let range = KeyRange::new(first.to_le_bytes(), first.wrapping_add(&span).to_le_bytes())?;
+
+        let offsets = (0..1usize << (4 * outer.len()))
+            .map(|value| {
+                let offset = outer
+                    .iter()
+                    .enumerate()
+                    .fold(low, |offset, (i, &position)| {
+                        let nibble = K256U256::from_u8(((value >> (4 * i)) & 0xf) as u8);
+                        offset.wrapping_add(&nibble.shl_vartime(4 * position))
+                    });
+                <Scalar as Reduce<K256U256>>::reduce(offset)
+            })
+            .collect();
+
+        Ok(TemplateSearch {
+            range,
+            stride: <Scalar as Reduce<K256U256>>::reduce(K256U256::ONE.shl_vartime(4 * shift)),
+            offsets,
+        })
+    }
+}
+
+impl std::str::FromStr for KeyTemplate {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        Self::parse(s).map_err(|e| e.to_string())
+    }
+}
+
+impl fmt::Display for KeyTemplate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("0x")?;
+        for digit in &self.digits {
+            match digit {
+                Some(d) => write!(f, "{:x}", d)?,
+                None => f.write_str("?")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Keys `offsets[i] + stride·x` for `x` in `range`
+#[derive(Clone, Debug)]
+pub struct TemplateSearch {
+    pub range: KeyRange,
+    pub stride: Scalar,
+    pub offsets: Vec<Scalar>,
+}
+
+impl TemplateSearch {
+    /// `(pubkey - offset·base)·stride^-1` for each offset: `x·base` when the key
+    /// matches that offset
+    pub fn targets(&self, pubkey: &Point, base: &Point) -> Vec<Point> {
+        // The stride is a power of 16, never a multiple of the group order
+        let inverse = self.stride.invert().unwrap();
+        self.offsets
+            .iter()
+            .map(|offset| (pubkey - &mul_base(base, offset)) * inverse)
+            .collect()
+    }
+
+    /// The key of target `target` found at `x` (big-endian, as the solvers return keys)
+    pub fn key(&self, target: usize, x: &[u8]) -> Vec<u8> {
+        scalar_to_key_bytes(&(self.offsets[target] + self.stride * key_bytes_to_scalar(x)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::{parse_hex_u256, verify_key};
+
+    fn key(hex: &str) -> Vec<u8> {
+        scalar_to_key_bytes(&key_bytes_to_scalar(&hex::decode(hex).unwrap()))
+    }
+
+    fn point(hex: &str) -> Point {
+        Point::GENERATOR * key_bytes_to_scalar(&hex::decode(hex).unwrap())
+    }
+
+    #[test]
+    fn test_parse_template() {
+        let template = KeyTemplate::parse("0x1F3a??C0").unwrap();
+        assert_eq!(template.to_string(), "0x1f3a??c0");
+        assert_eq!(template.unknown_digits(), 2);
+        assert_eq!(template.blocks(), vec![(2, 2)]);
+        assert_eq!(
+            KeyTemplate::parse("?1??2???").unwrap().blocks(),
+            vec![(0, 3), (4, 2), (7, 1)]
+        );
+
+        assert!(KeyTemplate::parse("").is_err());
+        assert!(KeyTemplate::parse("0x").is_err());
+        assert!(KeyTemplate::parse("12g?").is_err());
+        assert!(KeyTemplate::parse("1234").is_err());
+        assert!(KeyTemplate::parse(&"?".repeat(65)).is_err());
+    }
+
+    #[test]
+    fn test_trailing_run_is_an_interval() {
+        let plan = KeyTemplate::parse("0x3f????").unwrap().plan().unwrap();
+        assert_eq!(
+            plan.range,
+            KeyRange::new(
+                parse_hex_u256("3f0000").unwrap(),
+                parse_hex_u256("3fffff").unwrap()
+            )
+            .unwrap()
+        );
+        assert_eq!(plan.offsets, vec![Scalar::ZERO]);
+        assert_eq!(plan.stride, Scalar::ONE);
+
+        // Nothing to transform: the target is the pubkey itself
+        let pubkey = point("3f1234");
+        assert_eq!(plan.targets(&pubkey, &Point::GENERATOR), vec![pubkey]);
+        assert_eq!(plan.key(0, &key("3f1234")), key("3f1234"));
+    }
+
+    #[test]
+    fn test_known_low_digits() {
+        let plan = KeyTemplate::parse("0x1f3a???c0").unwrap().plan().unwrap();
+        assert_eq!(plan.offsets.len(), 1);
+        assert_eq!(plan.range.width(), K256U256::from_u64(0x1000));
+
+        // The key is 16^2·x + 0xc0 for x = 0x1f3a5b6 in the range
+        let secret = "01f3a5b6c0";
+        let x = key("01f3a5b6");
+        let single = parse_hex_u256("1f3a5b6").unwrap();
+        assert!(plan.range.contains(&KeyRange::new(single, single).unwrap()));
+        let target = plan.targets(&point(secret), &Point::GENERATOR)[0];
+        assert!(verify_key(&x, &target));
+        assert_eq!(plan.key(0, &x), key(secret));
+    }
+
+    #[test]
+    fn test_other_runs_are_enumerated() {
+        // The run of four is searched, the single ? becomes 16 targets
+        let plan = KeyTemplate::parse("0x7?2????5").unwrap().plan().unwrap();
+        assert_eq!(plan.offsets.len(), 16);
+        assert_eq!(plan.range.width(), K256U256::from_u64(0x10000));
+
+        // 0x79212345 = 0x09000005 + 16·0x7021234
+        let secret = "79212345";
+        let x = key("07021234");
+        let targets = plan.targets(&point(secret), &Point::GENERATOR);
+        let hits: Vec<usize> = (0..targets.len())
+            .filter(|&i| verify_key(&x, &targets[i]))
+            .collect();
+        assert_eq!(hits, vec![9]);
+        assert_eq!(plan.key(9, &x), key(secret));
+    }
+
+    #[test]
+    fn test_too_many_runs_are_rejected() {
+        assert!(KeyTemplate::parse("1??2??3?").unwrap().plan().is_ok());
+        let err = KeyTemplate::parse("1?2?3?4?5????")
+            .unwrap()
+            .plan()
+            .unwrap_err();
+        assert!(err.to_string().contains("16^4 targets"));
+    }
+}
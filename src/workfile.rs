@@ -0,0 +1,409 @@
+//! Append-only distinguished point work files
+//!
+//! A work file records every DP a run produces so that independent runs against
+//! the same pubkey/range can be merged later and checked for a tame/wild
+//! collision offline.
+//!
+//! Layout (all integers little-endian):
+//!
+//! ```text
+//! magic        8 bytes  "KANGWORK"
+//! version      u32
+//! pubkey       33 bytes (compressed SEC1)
+//! start        32 bytes (little-endian U256)
+//! end          32 bytes (little-endian U256, inclusive)
+//! dp_bits      u32
+//! flags        u32      bit 0: negation-map walk, bits 1-2: herd composition,
+//!                       bits 8-23: Gaudry–Schost tame and wild set percent
+//! records      N x GpuDistinguishedPoint (x, dist, ktype, kangaroo_id)
+//! ```
+//!
+//! Records are appended as they arrive. A record cut short by a crash is
+//! ignored on read.
+
+use crate::cpu::gaudry_schost::{self, SetOverlap};
+use crate::cpu::init::Herds;
+use crate::cpu::DPTable;
+use crate::crypto::Point;
+use crate::gpu::GpuDistinguishedPoint;
+use crate::range::KeyRange;
+use anyhow::{anyhow, bail, Context, Result};
+use k256::elliptic_curve::sec1::{FromEncodedPoint, ToEncodedPoint};
+use k256::{AffinePoint, EncodedPoint, ProjectivePoint};
+use std::collections::HashSet;
+use std::fs::{File, OpenOptions};
+use std::io::{BufWriter, Read, Write};
+use std::path::Path;
+
+const MAGIC: &[u8; 8] = b"KANGWORK";
+pub const WORK_FILE_VERSION: u32 = 3;
+const HEADER_SIZE: usize = 8 + 4 + 33 + 32 + 32 + 4 + 4;
+const FLAG_NEGATION: u32 = 1;
+const HERDS_SHIFT: u32 = 1;
+const HERDS_MASK: u32 = 0b11;
+const RECORD_SIZE: usize = std::mem::size_of::<GpuDistinguishedPoint>();
+
+/// Search parameters shared by every DP in a work file
+#[derive(Clone, Debug, PartialEq)]
+pub struct WorkHeader {
+    pub pubkey: Point,
+    pub range: KeyRange,
+    pub dp_bits: u32,
+    /// DPs come from a negation-map walk (distances relative to the range midpoint)
+    pub negation: bool,
+    /// Herd composition; three and four kangaroos also measure from the midpoint
+    pub herds: Herds,
+    /// Walks restart after each DP (Gaudry–Schost); their DPs are like those of
+    /// a van Oorschot–Wiener herd, so this does not set the search apart
+    pub gaudry_schost: Option<SetOverlap>,
+}
+
+impl WorkHeader {
+    pub(crate) fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(HEADER_SIZE);
+        out.extend_from_slice(MAGIC);
+        out.extend_from_slice(&WORK_FILE_VERSION.to_le_bytes());
+        out.extend_from_slice(self.pubkey.to_affine().to_encoded_point(true).as_bytes());
+        out.extend_from_slice(&self.range.start());
+        out.extend_from_slice(&self.range.end());
+        out.extend_from_slice(&self.dp_bits.to_le_bytes());
+        let flags = if self.negation { FLAG_NEGATION } else { 0 };
+        let flags =
+            flags | self.herds.code() << HERDS_SHIFT | gaudry_schost::flag_bits(self.gaudry_schost);
+        out.extend_from_slice(&flags.to_le_bytes());
+        out
+    }
+
+    pub(crate) fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        if bytes.len() < HEADER_SIZE {
+            bail!("Work file truncated (incomplete header)");
+        }
+        if &bytes[0..8] != MAGIC {
+            bail!("Not a kangaroo work file (bad magic)");
+        }
+        let version = u32::from_le_bytes(bytes[8..12].try_into().unwrap());
+        if version != WORK_FILE_VERSION {
+            bail!(
+                "Unsupported work file version {} (expected {})",
+                version,
+                WORK_FILE_VERSION
+            );
+        }
+
+        let encoded = EncodedPoint::from_bytes(&bytes[12..45])
+            .map_err(|e| anyhow!("Invalid pubkey in work file: {e}"))?;
+        let affine = Option::<AffinePoint>::from(AffinePoint::from_encoded_point(&encoded))
+            .ok_or_else(|| anyhow!("Work file pubkey is not on curve"))?;
+
+        let mut start = [0u8; 32];
+        start.copy_from_slice(&bytes[45..77]);
+        let mut end = [0u8; 32];
+        end.copy_from_slice(&bytes[77..109]);
+
+        let flags = u32::from_le_bytes(bytes[113..117].try_into().unwrap());
+
+        Ok(Self {
+            pubkey: ProjectivePoint::from(affine),
+            range: KeyRange::new(start, end).context("Invalid range in work file")?,
+            dp_bits: u32::from_le_bytes(bytes[109..113].try_into().unwrap()),
+            negation: flags & FLAG_NEGATION != 0,
+            herds: Herds::from_code(flags >> HERDS_SHIFT & HERDS_MASK)?,
+            gaudry_schost: gaudry_schost::from_flag_bits(flags)?,
+        })
+    }
+
+    /// Same target, range and walk (DP bits may differ between runs)
+    pub fn same_search(&self, other: &WorkHeader) -> bool {
+        self.pubkey == other.pubkey
+            && self.range == other.range
+            && self.negation == other.negation
+            && self.herds == other.herds
+    }
+
+    /// Empty DP table that resolves collisions for this search (work files
+    /// only record searches on G)
+    pub fn dp_table(&self) -> DPTable {
+        DPTable::for_search(
+            vec![self.pubkey],
+            self.range,
+            self.negation,
+            self.herds,
+            ProjectivePoint::GENERATOR,
+        )
+    }
+}
+
+/// Appends DPs to a work file
+pub struct WorkFileWriter {
+    file: BufWriter<File>,
+    written: u64,
+}
+
+impl WorkFileWriter {
+    /// Create a new work file, or append to an existing one for the same search
+    pub fn open(path: &Path, header: &WorkHeader) -> Result<Self> {
+        let exists = path.exists() && std::fs::metadata(path)?.len() > 0;
+
+        if exists {
+            let mut head = vec![0u8; HEADER_SIZE];
+            File::open(path)
+                .and_then(|mut f| f.read_exact(&mut head))
+                .with_context(|| format!("Failed to read work file {}", path.display()))?;
+            let existing = WorkHeader::from_bytes(&head)
+                .with_context(|| format!("Invalid work file {}", path.display()))?;
+            if !existing.same_search(header) || existing.dp_bits != header.dp_bits {
+                bail!(
+                    "Work file {} belongs to a different search (pubkey, range, walk or DP bits differ)",
+                    path.display()
+                );
+            }
+        }
+
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .with_context(|| format!("Failed to open work file {}", path.display()))?;
+
+        // Drop a partial record left behind by a crash so new records stay aligned
+        let len = file.metadata()?.len();
+        if exists && !(len as usize - HEADER_SIZE).is_multiple_of(RECORD_SIZE) {
+            let aligned = HEADER_SIZE + (len as usize - HEADER_SIZE) / RECORD_SIZE * RECORD_SIZE;
+            file.set_len(aligned as u64)?;
+        }
+
+        let mut writer = Self {
+            file: BufWriter::new(file),
+            written: 0,
+        };
+        if !exists {
+            writer.file.write_all(&header.to_bytes())?;
+            writer.file.flush()?;
+        }
+        Ok(writer)
+    }
+
+    pub fn append(&mut self, dps: &[GpuDistinguishedPoint]) -> Result<()> {
+        if dps.is_empty() {
+            return Ok(());
+        }
+        self.file.write_all(bytemuck::cast_slice(dps))?;
+        self.file.flush()?;
+        self.written += dps.len() as u64;
+        Ok(())
+    }
+
+    /// Number of DPs appended through this writer
+    pub fn written(&self) -> u64 {
+        self.written
+    }
+}
+
+/// Read a complete work file
+pub fn read_work_file(path: &Path) -> Result<(WorkHeader, Vec<GpuDistinguishedPoint>)> {
+    let bytes = std::fs::read(path)
+        .with_context(|| format!("Failed to read work file {}", path.display()))?;
+    let header = WorkHeader::from_bytes(&bytes)
+        .with_context(|| format!("Invalid work file {}", path.display()))?;
+
+    let body = &bytes[HEADER_SIZE..];
+    if !body.len().is_multiple_of(RECORD_SIZE) {
+        tracing::warn!(
+            "{}: ignoring {} trailing bytes of an incomplete record",
+            path.display(),
+            body.len() % RECORD_SIZE
+        );
+    }
+    let dps = body
+        .chunks_exact(RECORD_SIZE)
+        .map(bytemuck::pod_read_unaligned)
+        .collect();
+
+    Ok((header, dps))
+}
+
+/// Outcome of merging work files
+pub struct MergeStats {
+    pub header: WorkHeader,
+    pub total_in: usize,
+    pub written: usize,
+}
+
+/// Merge work files for the same search into `output`, dropping duplicate DPs
+pub fn merge_work_files(inputs: &[&Path], output: &Path) -> Result<MergeStats> {
+    let Some((first, rest)) = inputs.split_first() else {
+        bail!("No input work files given");
+    };
+
+    let (mut header, mut dps) = read_work_file(first)?;
+    for path in rest {
+        let (other, more) = read_work_file(path)?;
+        if !header.same_search(&other) {
+            bail!(
+                "{} targets a different pubkey or range than {}",
+                path.display(),
+                first.display()
+            );
+        }
+        if other.dp_bits != header.dp_bits {
+            tracing::warn!(
+                "DP bits differ ({} vs {}); merged file records the smaller value",
+                header.dp_bits,
+                other.dp_bits
+            );
+            header.dp_bits = header.dp_bits.min(other.dp_bits);
+        }
+        dps.extend(more);
+    }
+
+    let total_in = dps.len();
+    let mut seen = HashSet::with_capacity(dps.len());
+    dps.retain(|dp| seen.insert((dp.x, dp.dist, dp.ktype)));
+
+    if output.exists() {
+        std::fs::remove_file(output)
+            .with_context(|| format!("Failed to replace {}", output.display()))?;
+    }
+    let mut writer = WorkFileWriter::open(output, &header)?;
+    writer.append(&dps)?;
+
+    Ok(MergeStats {
+        header,
+        total_in,
+        written: dps.len(),
+    })
+}
+
+/// Replay DPs through a `DPTable` and return the first tame/wild collision key
+pub fn check_for_collision(header: &WorkHeader, dps: &[GpuDistinguishedPoint]) -> Option<Vec<u8>> {
+    let table = header.dp_table();
+    dps.iter().find_map(|dp| table.insert_and_check(*dp))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use k256::elliptic_curve::ops::MulByGenerator;
+    use k256::Scalar;
+
+    fn header(dp_bits: u32) -> WorkHeader {
+        WorkHeader {
+            pubkey: ProjectivePoint::mul_by_generator(&Scalar::from(7u64)),
+            range: KeyRange::from_bits([0u8; 32], 8),
+            dp_bits,
+            negation: false,
+            herds: Herds::Vow,
+            gaudry_schost: None,
+        }
+    }
+
+    fn dp(x0: u32, dist0: u32, ktype: u32) -> GpuDistinguishedPoint {
+        GpuDistinguishedPoint {
+            x: [x0, 1, 2, 3, 4, 5, 6, 7],
+            dist: [dist0, 0, 0, 0, 0, 0, 0, 0],
+            ktype,
+            kangaroo_id: x0,
+            kind: 0,
+            target: 0,
+            _padding: [0; 4],
+        }
+    }
+
+    #[test]
+    fn test_append_and_reopen() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("a.kwf");
+
+        let mut w = WorkFileWriter::open(&path, &header(8)).unwrap();
+        w.append(&[dp(1, 10, 0), dp(2, 20, 1)]).unwrap();
+        drop(w);
+
+        let mut w = WorkFileWriter::open(&path, &header(8)).unwrap();
+        w.append(&[dp(3, 30, 0)]).unwrap();
+        drop(w);
+
+        let (h, dps) = read_work_file(&path).unwrap();
+        assert_eq!(h, header(8));
+        assert_eq!(dps.len(), 3);
+        assert_eq!(dps[2].kangaroo_id, 3);
+    }
+
+    #[test]
+    fn test_reopen_rejects_other_search() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("a.kwf");
+        WorkFileWriter::open(&path, &header(8)).unwrap();
+        assert!(WorkFileWriter::open(&path, &header(10)).is_err());
+    }
+
+    #[test]
+    fn test_truncated_record_is_ignored() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("a.kwf");
+        let mut w = WorkFileWriter::open(&path, &header(8)).unwrap();
+        w.append(&[dp(1, 10, 0), dp(2, 20, 1)]).unwrap();
+        drop(w);
+
+        let len = std::fs::metadata(&path).unwrap().len();
+        let f = OpenOptions::new().write(true).open(&path).unwrap();
+        f.set_len(len - 5).unwrap();
+
+        let (_, dps) = read_work_file(&path).unwrap();
+        assert_eq!(dps.len(), 1);
+
+        // Appending realigns the file
+        let mut w = WorkFileWriter::open(&path, &header(8)).unwrap();
+        w.append(&[dp(4, 40, 1)]).unwrap();
+        drop(w);
+        let (_, dps) = read_work_file(&path).unwrap();
+        assert_eq!(dps.len(), 2);
+        assert_eq!(dps[1].x[0], 4);
+    }
+
+    #[test]
+    fn test_merge_dedupes_and_finds_collision() {
+        let dir = tempfile::tempdir().unwrap();
+        let a = dir.path().join("a.kwf");
+        let b = dir.path().join("b.kwf");
+        let out = dir.path().join("c.kwf");
+
+        // Tame at x=9 with dist 12, wild at x=9 with dist 5 -> k = 0 + 12 - 5 = 7
+        let mut w = WorkFileWriter::open(&a, &header(8)).unwrap();
+        w.append(&[dp(1, 10, 0), dp(9, 12, 0)]).unwrap();
+        drop(w);
+        // Gaudry–Schost DPs are checked like kangaroo DPs
+        let restarting = WorkHeader {
+            gaudry_schost: Some(SetOverlap::default()),
+            ..header(6)
+        };
+        let mut w = WorkFileWriter::open(&b, &restarting).unwrap();
+        w.append(&[dp(1, 10, 0), dp(9, 5, 1)]).unwrap();
+        drop(w);
+        assert_eq!(read_work_file(&b).unwrap().0, restarting);
+
+        let (ha, da) = read_work_file(&a).unwrap();
+        assert!(check_for_collision(&ha, &da).is_none());
+
+        let stats = merge_work_files(&[a.as_path(), b.as_path()], &out).unwrap();
+        assert_eq!(stats.total_in, 4);
+        assert_eq!(stats.written, 3);
+        assert_eq!(stats.header.dp_bits, 6);
+
+        let (h, dps) = read_work_file(&out).unwrap();
+        assert_eq!(check_for_collision(&h, &dps), Some(vec![7]));
+    }
+
+    #[test]
+    fn test_merge_rejects_different_target() {
+        let dir = tempfile::tempdir().unwrap();
+        let a = dir.path().join("a.kwf");
+        let b = dir.path().join("b.kwf");
+        WorkFileWriter::open(&a, &header(8)).unwrap();
+        let mut other = header(8);
+        other.range = KeyRange::from_bits([0u8; 32], 9);
+        WorkFileWriter::open(&b, &other).unwrap();
+
+        let out = dir.path().join("c.kwf");
+        assert!(merge_work_files(&[a.as_path(), b.as_path()], &out).is_err());
+    }
+}
@@ -7,7 +7,9 @@ mod fixtures;
 
 use fixtures::{get_smoke_test_puzzle, get_test_puzzles, PuzzleTestCase};
 use kangaroo::{
-    full_verify, parse_hex_u256, parse_pubkey, verify_key, GpuBackend, GpuContext, KangarooSolver,
+    full_verify, parse_hex_u256, parse_pubkey, verify_key, AddressForm, BsgsSolver, GpuBackend,
+    GpuContext, Hash160Scanner, Herds, KangarooSolver, KeyRange, ScanTargets, SetOverlap,
+    SolverOptions,
 };
 use std::time::{Duration, Instant};
 
@@ -280,6 +282,129 @@ fn test_all_puzzles() {
     }
 }
 
+#[test]
+#[ignore] // Run with: cargo test --test puzzle_solve -- --ignored
+fn test_alternative_algorithms() {
+    let puzzle = get_smoke_test_puzzle();
+    let pubkey = parse_pubkey(puzzle.pubkey).expect("Valid pubkey");
+    let range = KeyRange::from_bits(
+        parse_hex_u256(puzzle.start.trim_start_matches("0x")).expect("Valid start"),
+        puzzle.range_bits - 1,
+    );
+
+    let algorithms = [
+        SolverOptions {
+            herds: Herds::Three,
+            ..SolverOptions::default()
+        },
+        SolverOptions {
+            herds: Herds::Four,
+            ..SolverOptions::default()
+        },
+        SolverOptions {
+            gaudry_schost: Some(SetOverlap::default()),
+            ..SolverOptions::default()
+        },
+    ];
+
+    for options in algorithms {
+        let name = format!("{:?}/{:?}", options.herds, options.gaudry_schost);
+        let ctx = match pollster::block_on(GpuContext::new(0, GpuBackend::Auto)) {
+            Ok(ctx) => ctx,
+            Err(e) => {
+                println!("  \x1b[31m✗ SKIP: {}\x1b[0m", e);
+                return;
+            }
+        };
+        let mut solver =
+            KangarooSolver::new_in_range(ctx, pubkey, range, 8, NUM_KANGAROOS, options)
+                .expect("Solver creation failed");
+
+        let start_time = Instant::now();
+        let key = loop {
+            assert!(
+                start_time.elapsed() < Duration::from_secs(TIMEOUT_SECS),
+                "{}: timeout after {}s",
+                name,
+                TIMEOUT_SECS
+            );
+            if let Some(key) = solver.step().expect("Solver error") {
+                break key;
+            }
+        };
+        assert_eq!(
+            normalize_key(&hex::encode(&key)),
+            normalize_key(puzzle.expected_key),
+            "{}",
+            name
+        );
+    }
+}
+
+#[test]
+fn test_bsgs_puzzles() {
+    for puzzle in get_test_puzzles() {
+        let pubkey = parse_pubkey(puzzle.pubkey).expect("Valid pubkey");
+        let start = parse_hex_u256(puzzle.start.trim_start_matches("0x")).expect("Valid start");
+        // Puzzle n has its key in [2^(n-1), 2^n)
+        let range = KeyRange::from_bits(start, puzzle.range_bits - 1);
+        let bsgs = BsgsSolver::new(range, 1 << 20).expect("BSGS table");
+
+        let key = bsgs
+            .solve(&pubkey, |_| {})
+            .unwrap_or_else(|| panic!("Puzzle {} not found", puzzle.puzzle_number));
+        assert_eq!(
+            normalize_key(&hex::encode(&key)),
+            normalize_key(puzzle.expected_key)
+        );
+    }
+
+    // Puzzle 20's key lies above the lower half of its range
+    let puzzle = get_smoke_test_puzzle();
+    let pubkey = parse_pubkey(puzzle.pubkey).expect("Valid pubkey");
+    let start = parse_hex_u256(puzzle.start.trim_start_matches("0x")).expect("Valid start");
+    let lower_half = KeyRange::from_bits(start, puzzle.range_bits - 2);
+    let bsgs = BsgsSolver::new(lower_half, 1 << 20).expect("BSGS table");
+    assert_eq!(bsgs.solve(&pubkey, |_| {}), None);
+}
+
+#[test]
+fn test_hash160_scan() {
+    let ctx = match pollster::block_on(GpuContext::new(0, GpuBackend::Auto)) {
+        Ok(ctx) => ctx,
+        Err(e) => {
+            println!("  \x1b[31m✗ SKIP: {}\x1b[0m", e);
+            return;
+        }
+    };
+
+    // A few thousand keys around puzzle 20's, found from its address alone
+    let puzzle = get_smoke_test_puzzle();
+    let key = u64::from_str_radix(puzzle.expected_key, 16).expect("Valid key");
+    let range = KeyRange::from_center(
+        parse_hex_u256(&format!("{:x}", key - 1000)).expect("Valid centre"),
+        parse_hex_u256("800").expect("Valid radius"),
+    )
+    .expect("Valid range");
+    let hash160 = ScanTargets::parse(puzzle.expected_address).expect("Valid address");
+    assert_eq!(hex::encode(hash160), puzzle.expected_hash160);
+    let targets = ScanTargets::new(vec![hash160]).expect("Targets");
+
+    let mut scanner =
+        Hash160Scanner::new(ctx, range, targets, AddressForm::Both, 64, None).expect("Scanner");
+    let mut found = Vec::new();
+    while !scanner.is_finished() {
+        found.extend(scanner.step().expect("Scan error"));
+    }
+    assert_eq!(scanner.keys_done(), scanner.keys_total());
+    assert_eq!(found.len(), 1);
+    assert_eq!(
+        normalize_key(&hex::encode(&found[0].key)),
+        normalize_key(puzzle.expected_key)
+    );
+    assert!(found[0].compressed);
+}
+
 #[test]
 fn test_key_verification() {
     // Test that verify_key works correctly with known puzzle